@@ -3,20 +3,26 @@ use std::time::Instant;
 use tori::{
     event::Key,
     graphics::{
+        particles::{Curve, Emitter, EmitterShape, ParticleSystem},
         shape::{Circle, Rect},
         text::{Handle, Text},
     },
     math::{vec2, vec4, Vec2, Vec4},
 };
 
+use crate::{ACTOR_LAYER, BRICK_LAYER, DEBRIS_LAYER, UI_LAYER};
+
 pub struct Game
 {
     paddle:     Rect,
     score_text: Text,
     score:      isize,
-    bricks:     Vec<Rect>,
+    /// Each brick with the index of its color, which is also its debris emitter.
+    bricks:     Vec<(Rect, usize)>,
+    debris:     ParticleSystem,
     info:       Text,
     timer:      Instant,
+    last:       Instant,
     toggle:     bool,
     ball:       Circle,
     ball_vel:   Vec2,
@@ -41,8 +47,9 @@ impl Game
         let h = 20.0;
         let mut y = 500.0;
         let mut x;
+        let mut debris = ParticleSystem::new();
 
-        for color in colors
+        for (i, color) in colors.into_iter().enumerate()
         {
             x = 0.0;
             for _ in 0..8
@@ -50,10 +57,26 @@ impl Game
                 x += p;
                 let mut rect = Rect::new(x, y, w, h);
                 rect.set_color(color);
-                bricks.push(rect);
+                bricks.push((rect, i));
                 x += w;
             }
             y -= h + p;
+
+            let mut faded = color;
+            faded.w = 0.0;
+            debris.emitters.push(Emitter {
+                shape: EmitterShape::Rect {
+                    size: vec2(w, h),
+                },
+                lifetime: (0.4, 0.9),
+                speed: (60.0, 220.0),
+                gravity: vec2(0.0, -600.0),
+                drag: 1.5,
+                spin: (-8.0, 8.0),
+                color: Curve::linear(color, faded),
+                size: Curve::linear(7.0, 2.0),
+                ..Emitter::default()
+            });
         }
 
         let y = 20.0;
@@ -69,6 +92,7 @@ impl Game
             score_text: Text::new(handle, "Score: 0", 20.0, 550.0, 0.4, vec4(1.0, 1.0, 1.0, 1.0)),
             score: 0,
             bricks,
+            debris,
             info: Text::new(
                 handle,
                 "PRESS SPACE TO LAUNCH",
@@ -78,6 +102,7 @@ impl Game
                 vec4(1.0, 1.0, 1.0, 1.0),
             ),
             timer: Instant::now(),
+            last: Instant::now(),
             toggle: true,
             ball: Self::new_ball(bb),
             ball_vel: vec2(0.0, 0.0),
@@ -102,6 +127,10 @@ impl crate::Scene for Game
 {
     fn update(&mut self, window: &tori::window::Window) -> Option<crate::NewSceneInfo>
     {
+        let dt = self.last.elapsed().as_secs_f32();
+        self.last = Instant::now();
+        self.debris.update(dt);
+
         if window.is_key_pressed(Key::Right)
         {
             self.paddle.entity.pos[0] += 12.0;
@@ -130,7 +159,7 @@ impl crate::Scene for Game
         else
         {
             let mut del = None;
-            for (i, brick) in self.bricks.iter().enumerate()
+            for (i, (brick, _)) in self.bricks.iter().enumerate()
             {
                 let bb = brick.entity.get_bouding_box();
                 if crate::util::circle_rect_intersects(self.ball.center, self.ball.radius, bb)
@@ -142,7 +171,10 @@ impl crate::Scene for Game
             if let Some(del) = del
             {
                 self.ball_vel.y = -5.0;
-                self.bricks.swap_remove(del);
+                let (brick, color) = self.bricks.swap_remove(del);
+                let size = brick.entity.size;
+                self.debris.emitters[color].position = brick.entity.pos + size / 2.0;
+                self.debris.burst(color, 40);
                 self.score += 1;
             }
 
@@ -183,15 +215,16 @@ impl crate::Scene for Game
         self.bricks.is_empty().then_some(crate::NewSceneInfo::Game(self.score))
     }
 
-    fn draw(&self, drawer: &dyn Fn(&dyn tori::graphics::drawable::Drawable))
+    fn draw(&self, drawer: &dyn Fn(i32, &dyn tori::graphics::drawable::Drawable))
     {
-        drawer(&self.paddle);
-        drawer(&self.ball);
-        drawer(&self.score_text);
-        drawer(&self.info);
-        for paddle in self.bricks.iter()
+        for (brick, _) in self.bricks.iter()
         {
-            drawer(&paddle);
+            drawer(BRICK_LAYER, brick);
         }
+        drawer(DEBRIS_LAYER, &self.debris);
+        drawer(ACTOR_LAYER, &self.paddle);
+        drawer(ACTOR_LAYER, &self.ball);
+        drawer(UI_LAYER, &self.score_text);
+        drawer(UI_LAYER, &self.info);
     }
 }
@@ -10,13 +10,19 @@ use tori::{
     math,
 };
 
+// Layers the scenes draw on, back to front.
+pub const BRICK_LAYER: i32 = 0;
+pub const DEBRIS_LAYER: i32 = 1;
+pub const ACTOR_LAYER: i32 = 2;
+pub const UI_LAYER: i32 = 3;
+
 fn main() -> Result<(), Box<dyn std::error::Error>>
 {
     let mut window = tori::window::Window::new("Hello", 800, 600)?;
 
     let font = window.load_font("Atari-90.ttf", CharSet::Ascii).unwrap();
 
-    let mut scene: Box<dyn Scene> = Box::new(menu::Menu::new(&font));
+    let mut scene: Box<dyn Scene> = Box::new(menu::Menu::new(&font)?);
 
     let mut fps = Text::new(&font, "starting..", 500.0, 550.0, 0.4, math::vec4(1.0, 1.0, 1.0, 1.0));
     let mut timer = Instant::now();
@@ -27,7 +33,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
         frames += 1.0;
         if timer.elapsed().as_secs() >= 1
         {
-            fps.text = format!("{:.2} ms/frame", 1000.0 / frames);
+            let stats = window.stats();
+            fps.text = format!("{:.2} ms/frame, {} draws", 1000.0 / frames, stats.draw_calls);
             timer = Instant::now();
             frames = 0.0;
         }
@@ -39,8 +46,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
             scene = next_state(scene_info, &font);
         }
 
-        scene.draw(&|d| window.draw_dyn(d));
-        window.draw(&fps);
+        scene.draw(&|layer, d| window.draw_layered(layer, 0.0, d));
+        window.draw_layered(UI_LAYER, 0.0, &fps);
 
         window.swap_buffers();
     }
@@ -68,5 +75,6 @@ pub enum NewSceneInfo
 pub trait Scene
 {
     fn update(&mut self, w: &tori::window::Window) -> Option<NewSceneInfo>;
-    fn draw(&self, d: &dyn Fn(&dyn Drawable));
+    /// Hands every drawable to `d` together with the layer it goes on.
+    fn draw(&self, d: &dyn Fn(i32, &dyn Drawable));
 }
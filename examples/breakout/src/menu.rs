@@ -1,30 +1,62 @@
 use math::vec4;
 use tori::{
+    error::Error,
     graphics::{
         drawable::Drawable,
-        shape::Rect,
+        nine_slice::{Insets, NineSlice},
         text::{Handle, Text},
+        texture::{Filter, TextureBuilder},
     },
     math,
 };
 
+use crate::UI_LAYER;
+
 pub struct Menu
 {
     pub welcome_text: Text,
     pub play_text:    Text,
-    play_rect:        Rect,
+    play_button:      NineSlice,
+}
+
+/// A 12x12 button frame: a light 4 pixel border with a darker bevel around a dark center.
+fn frame_pixels() -> Vec<u8>
+{
+    let mut pixels = Vec::with_capacity(12 * 12 * 4);
+    for y in 0..12
+    {
+        for x in 0..12
+        {
+            let edge = x.min(y).min(11 - x).min(11 - y);
+            let pixel = match edge
+            {
+                0 => [40, 40, 60, 255],
+                1 | 2 => [200, 200, 220, 255],
+                3 => [120, 120, 150, 255],
+                _ => [20, 20, 40, 200],
+            };
+            pixels.extend(pixel);
+        }
+    }
+    pixels
 }
 
 impl Menu
 {
-    pub fn new(handle: &Handle) -> Self
+    pub fn new(handle: &Handle) -> Result<Self, Error>
     {
         let play_text =
             Text::new(handle, "PLAY", 300.0, 150.0, 1.0, math::vec4(0.9, 0.9, 0.9, 1.0));
         let bb = play_text.get_bounding_box();
-        let rect = tori::graphics::shape::Rect::new(bb.x, bb.y, bb[2], bb[3]);
 
-        Self {
+        let pixels = frame_pixels();
+        let texture = TextureBuilder::from_rgba(12, 12, &pixels).filter(Filter::Nearest).build()?;
+        let mut play_button = NineSlice::new(&texture, Insets::uniform(4.0));
+        play_button.border_scale = 2.0;
+        play_button.entity.pos = math::vec2(bb.x - 20.0, bb.y - 16.0);
+        play_button.entity.size = math::vec2(bb[2] + 40.0, bb[3] + 32.0);
+
+        Ok(Self {
             welcome_text: Text::new(
                 handle,
                 "BREAKOUT",
@@ -33,9 +65,9 @@ impl Menu
                 1.0,
                 math::vec4(0.9, 0.9, 0.9, 1.0),
             ),
-            play_rect: rect,
+            play_button,
             play_text,
-        }
+        })
     }
 }
 
@@ -44,14 +76,16 @@ impl crate::Scene for Menu
     fn update(&mut self, window: &tori::window::Window) -> Option<crate::NewSceneInfo>
     {
         let mp = window.get_mouse_pos();
-        let bounding_box = self.play_rect.entity.get_bouding_box();
+        let bounding_box = self.play_button.entity.get_bouding_box();
         if crate::util::point_inside_rect(bounding_box, mp)
         {
             self.play_text.color = vec4(1.0, 1.0, 1.0, 1.0);
+            self.play_button.set_color(vec4(1.0, 0.9, 0.6, 1.0));
         }
         else
         {
             self.play_text.color = vec4(0.9, 0.9, 0.9, 1.0);
+            self.play_button.set_color(vec4(1.0, 1.0, 1.0, 1.0));
         }
 
         (crate::util::point_inside_rect(bounding_box, mp)
@@ -59,10 +93,10 @@ impl crate::Scene for Menu
         .then_some(crate::NewSceneInfo::Menu)
     }
 
-    fn draw(&self, drawer: &dyn Fn(&dyn Drawable))
+    fn draw(&self, drawer: &dyn Fn(i32, &dyn Drawable))
     {
-        // drawer(&self.play_rect);
-        drawer(&self.play_text);
-        drawer(&self.welcome_text);
+        drawer(UI_LAYER, &self.play_button);
+        drawer(UI_LAYER, &self.play_text);
+        drawer(UI_LAYER, &self.welcome_text);
     }
 }
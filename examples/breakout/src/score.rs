@@ -6,6 +6,8 @@ use tori::{
     math,
 };
 
+use crate::UI_LAYER;
+
 pub struct Score
 {
     text: Text
@@ -37,8 +39,8 @@ impl crate::Scene for Score
         None
     }
 
-    fn draw(&self, drawer: &dyn Fn(&dyn Drawable))
+    fn draw(&self, drawer: &dyn Fn(i32, &dyn Drawable))
     {
-        drawer(&self.text);
+        drawer(UI_LAYER, &self.text);
     }
 }
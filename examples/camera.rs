@@ -0,0 +1,75 @@
+use std::time::Instant;
+
+use tori::{
+    event::Key,
+    graphics::{camera::Camera2D, shape::Rect},
+    math::{vec2, vec4},
+    window::Window,
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let window = Window::new("Hello", 400, 300)?;
+
+    let mut camera = Camera2D::new(400.0, 300.0);
+    camera.smoothing = 5.0;
+    camera.dead_zone = vec2(40.0, 30.0);
+
+    let mut tiles = Vec::new();
+    for y in 0..20
+    {
+        for x in 0..20
+        {
+            let mut rect = Rect::new(x as f32 * 50.0, y as f32 * 50.0, 48.0, 48.0);
+            let shade = if (x + y) % 2 == 0 { 0.3 } else { 0.4 };
+            rect.set_color(vec4(shade, shade, shade, 1.0));
+            tiles.push(rect);
+        }
+    }
+
+    let mut player = Rect::new(200.0, 150.0, 20.0, 20.0);
+    player.set_color(vec4(0.85, 0.35, 0.2, 1.0));
+
+    let mut last = Instant::now();
+    while window.is_open()
+    {
+        let dt = last.elapsed().as_secs_f32();
+        last = Instant::now();
+
+        let _ = window.poll_events();
+
+        let speed = 200.0 * dt;
+        for (key, dir) in [
+            (Key::Left, vec2(-1.0, 0.0)),
+            (Key::Right, vec2(1.0, 0.0)),
+            (Key::Up, vec2(0.0, 1.0)),
+            (Key::Down, vec2(0.0, -1.0)),
+        ]
+        {
+            if window.is_key_pressed(key)
+            {
+                player.entity.pos += dir * speed;
+            }
+        }
+        if window.is_key_pressed(Key::Space)
+        {
+            camera.shake(6.0, 0.3);
+        }
+
+        camera.follow(player.entity.pos);
+        camera.update(dt);
+
+        window.clear();
+        window.set_camera(&camera);
+        for tile in tiles.iter()
+        {
+            window.draw(tile);
+        }
+        window.draw(&player);
+        window.reset_camera();
+
+        window.swap_buffers();
+    }
+
+    Ok(())
+}
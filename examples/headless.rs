@@ -0,0 +1,22 @@
+use tori::{
+    graphics::shape::{Circle, Rect},
+    math::{vec2, vec4},
+    window::Window,
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let window = Window::headless(300, 300)?;
+
+    let mut rect = Rect::new(50.0, 50.0, 75.0, 75.0);
+    rect.set_color(vec4(0., 0.5, 0.5, 1.0));
+    let circle = Circle::new(vec2(200.0, 200.0), 80.0, vec4(0.85, 0.35, 0.2, 1.0));
+
+    window.clear();
+    window.draw(&rect);
+    window.draw(&circle);
+
+    window.read_pixels().save("headless.png")?;
+
+    Ok(())
+}
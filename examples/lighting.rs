@@ -0,0 +1,60 @@
+use std::time::Instant;
+
+use tori::{
+    graphics::{
+        lighting::{Light, Lighting, Occluder, Shadows},
+        shape::Rect,
+    },
+    math::{vec2, vec3, vec4},
+    window::Window,
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let window = Window::new("Lighting", 400, 300)?;
+
+    let mut floor = Rect::new(0.0, 0.0, 400.0, 300.0);
+    floor.set_color(vec4(0.6, 0.55, 0.5, 1.0));
+
+    let mut walls = Vec::new();
+    let boxes = [(80.0, 60.0, 40.0, 40.0), (260.0, 180.0, 60.0, 20.0), (180.0, 40.0, 20.0, 90.0)];
+    for (x, y, w, h) in boxes
+    {
+        let mut wall = Rect::new(x, y, w, h);
+        wall.set_color(vec4(0.3, 0.3, 0.35, 1.0));
+        walls.push(wall);
+    }
+
+    let mut lighting = Lighting::new()?;
+    lighting.ambient = vec3(0.05, 0.05, 0.1);
+    lighting.shadows = Shadows::Soft {
+        radius: 2.0,
+    };
+    lighting.occluders = walls.iter().map(Occluder::from_rect).collect();
+    lighting.lights.push(Light::point(vec2(200.0, 150.0), 220.0, vec3(1.0, 0.7, 0.4)));
+    lighting.lights.push(Light::spot(vec2(380.0, 280.0), 300.0, vec3(0.4, 0.6, 1.0), -2.4, 0.8));
+
+    let start = Instant::now();
+    while window.is_open()
+    {
+        let _ = window.poll_events();
+
+        // The torch flickers and follows the mouse.
+        let t = start.elapsed().as_secs_f32();
+        lighting.lights[0].position = window.get_mouse_pos();
+        lighting.lights[0].intensity = 1.0 + 0.08 * (t * 13.0).sin() * (t * 7.0).cos();
+
+        window.clear();
+
+        window.draw(&floor);
+        for wall in walls.iter()
+        {
+            window.draw(wall);
+        }
+        window.draw_lighting(&mut lighting, None)?;
+
+        window.swap_buffers();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::time::Instant;
+
+use tori::{
+    graphics::{material::Material, preprocessor::Preprocessor, shape::Rect},
+    math::vec4,
+    window::Window,
+};
+
+const PULSE: &str = r#"
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform float u_Time;
+uniform vec4 u_Tint;
+
+void main()
+{
+    float wave = 0.5 + 0.5 * sin(u_Time * SPEED + v_TexCoord.x * 10.0);
+    color = vec4(u_Tint.rgb * wave, u_Tint.a);
+}
+"#;
+
+fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let window = Window::new("Hello", 300, 300)?;
+
+    let mut material = Material::from_source_with(PULSE, Preprocessor::new().define("SPEED", 3.0))?;
+    material.set("u_Tint", vec4(0.2, 0.6, 0.9, 1.0));
+
+    let mut rect = Rect::new(50.0, 50.0, 200.0, 200.0);
+
+    let start = Instant::now();
+    while window.is_open()
+    {
+        let _ = window.poll_events();
+
+        material.set("u_Time", start.elapsed().as_secs_f32());
+        rect.set_material(Some(material.clone()));
+
+        window.clear();
+        window.draw(&rect);
+        window.swap_buffers();
+    }
+
+    Ok(())
+}
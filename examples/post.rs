@@ -0,0 +1,47 @@
+use tori::{
+    graphics::{
+        post::{Effect, PostChain},
+        shape::{Circle, Rect},
+    },
+    math::{vec2, vec4},
+    window::Window,
+};
+fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let window = Window::new("Post", 400, 300)?;
+
+    let chain = PostChain::new()?
+        .with(Effect::Bloom {
+            threshold: 0.6,
+            intensity: 1.2,
+            radius: 4.0,
+        })
+        .with(Effect::Crt {
+            curvature: 0.1,
+            scanlines: 0.3,
+        })
+        .with(Effect::Vignette {
+            radius: 0.75,
+            softness: 0.45,
+            strength: 0.8,
+        });
+    window.set_post_chain(Some(chain))?;
+
+    let mut rect = Rect::new(60.0, 60.0, 120.0, 80.0);
+    rect.set_color(vec4(0.2, 0.6, 1.0, 1.0));
+    let circle = Circle::new(vec2(280.0, 160.0), 50.0, vec4(1.0, 0.9, 0.4, 1.0));
+
+    while window.is_open()
+    {
+        let _ = window.poll_events();
+
+        window.clear();
+
+        window.draw(&rect);
+        window.draw(&circle);
+
+        window.swap_buffers();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use tori::{
+    graphics::{render_target::RenderTarget, shape::Rect, sprite::Sprite},
+    math::{vec2, vec4},
+    window::Window,
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let window = Window::new("Hello", 300, 300)?;
+
+    // Draw a small scene once and show it scaled up.
+    let target = RenderTarget::new(64, 64)?;
+    let mut rect = Rect::new(16.0, 16.0, 32.0, 32.0);
+    rect.set_color(vec4(0., 0.5, 0.5, 1.0));
+
+    window.clear_target(&target, vec4(0.1, 0.1, 0.1, 1.0));
+    window.draw_to(&target, &rect);
+
+    let mut sprite = Sprite::new(target.texture());
+    sprite.entity.scale = vec2(4.0, 4.0);
+    sprite.entity.pos = vec2(22.0, 22.0);
+
+    while window.is_open()
+    {
+        let _ = window.poll_events();
+
+        window.clear();
+        window.draw(&sprite);
+        window.swap_buffers();
+    }
+
+    Ok(())
+}
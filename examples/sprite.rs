@@ -1,4 +1,7 @@
-use graphics::{sprite::Sprite, texture::Texture};
+use graphics::{
+    sprite::Sprite,
+    texture::{Filter, TextureBuilder},
+};
 use tori::{
     graphics::{self},
     window::Window,
@@ -11,7 +14,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
 {
     let window = Window::new("Hello", 300, 300)?;
 
-    let texture = Texture::new("sheet.png")?;
+    let texture = TextureBuilder::from_path("sheet.png").filter(Filter::Nearest).build()?;
     let mut sprite = Sprite::new(&texture);
     let mut sheet = sprite.make_sprite_sheet(8, 2);
 
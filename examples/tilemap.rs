@@ -0,0 +1,144 @@
+use std::time::Instant;
+
+use tori::{
+    event::Key,
+    graphics::{
+        camera::Camera2D,
+        shape::Rect,
+        texture::{Filter, TextureBuilder},
+        tilemap::{Layer, Tile, TileLayer, TileMap, Tileset},
+    },
+    math::{
+        grid::{GridLayout, Stagger, StaggerAxis, StaggerIndex},
+        vec2, vec4,
+    },
+    window::Window,
+};
+
+/// Four 16x16 tiles in a row: grass, dirt, water and stone.
+fn tileset_pixels() -> Vec<u8>
+{
+    let colors = [[90, 160, 70], [140, 100, 60], [60, 110, 200], [120, 120, 130]];
+    let mut pixels = Vec::with_capacity(64 * 16 * 4);
+    for y in 0..16
+    {
+        for x in 0..64
+        {
+            let [r, g, b] = colors[x / 16];
+            let edge = x % 16 == 0 || y == 0;
+            let shade = if edge { 0.8 } else { 1.0 };
+            let channel = |c: u8| (c as f32 * shade) as u8;
+            pixels.extend([channel(r), channel(g), channel(b), 255]);
+        }
+    }
+    pixels
+}
+
+/// A map made in code, for when no map file is given.
+fn generated() -> Result<TileMap, Box<dyn std::error::Error>>
+{
+    let pixels = tileset_pixels();
+    let texture = TextureBuilder::from_rgba(64, 16, &pixels).filter(Filter::Nearest).build()?;
+
+    let mut map = TileMap::new(200, 200, 16.0, 16.0);
+    let first = map.add_tileset(Tileset::new("terrain", &texture, 16, 16));
+    map.add_layer(Layer::Tiles(TileLayer::new("ground", 200, 200)));
+    for y in 0..200
+    {
+        for x in 0..200
+        {
+            let wave = ((x as f32 * 0.1).sin() + (y as f32 * 0.13).cos()) * 1.5 + 1.5;
+            map.set_tile(0, x, y, Some(Tile::new(first + wave as u32)));
+        }
+    }
+
+    // Stone pillars to bump into.
+    map.add_layer(Layer::Tiles(TileLayer::new("walls", 200, 200)));
+    for i in 0..400
+    {
+        let (x, y) = ((i * 37) % 200, (i * 91) % 200);
+        map.set_tile(1, x, y, Some(Tile::new(first + 3)));
+    }
+    Ok(map)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let window = Window::new("Tile map", 400, 300)?;
+
+    // `cargo run --example tilemap -- level.tmx` shows a map made with Tiled, an
+    // `.ldtk` file all levels of an LDtk project. Cells of a "walls" layer are solid.
+    // `isometric` and `hexagonal` lay the generated map out differently.
+    let mut maps = match std::env::args().nth(1)
+    {
+        Some(layout) if layout == "isometric" || layout == "hexagonal" =>
+        {
+            let mut map = generated()?;
+            map.set_layout(if layout == "isometric"
+            {
+                GridLayout::Isometric
+            }
+            else
+            {
+                let stagger = Stagger::new(StaggerAxis::Y, StaggerIndex::Odd);
+                GridLayout::Hexagonal {
+                    stagger,
+                    side: 8.0,
+                }
+            });
+            vec![map]
+        },
+        Some(path) if path.ends_with(".ldtk") => TileMap::from_ldtk(path)?,
+        Some(path) => vec![TileMap::from_tiled(path)?],
+        None => vec![generated()?],
+    };
+
+    let mut camera = Camera2D::new(400.0, 300.0);
+    let mut player = Rect::new(200.0, 150.0, 12.0, 12.0);
+    player.set_color(vec4(0.9, 0.3, 0.2, 1.0));
+
+    let mut last = Instant::now();
+    while window.is_open()
+    {
+        let dt = last.elapsed().as_secs_f32();
+        last = Instant::now();
+
+        let _ = window.poll_events();
+
+        for (key, dir) in [
+            (Key::Left, vec2(-1.0, 0.0)),
+            (Key::Right, vec2(1.0, 0.0)),
+            (Key::Up, vec2(0.0, 1.0)),
+            (Key::Down, vec2(0.0, -1.0)),
+        ]
+        {
+            if window.is_key_pressed(key)
+            {
+                let mut delta = dir * 150.0 * dt;
+                for map in maps.iter()
+                {
+                    let bounds = vec4(player.entity.pos.x, player.entity.pos.y, 12.0, 12.0);
+                    delta = map.move_box("walls", bounds, delta);
+                }
+                player.entity.pos += delta;
+            }
+        }
+
+        camera.follow(player.entity.pos);
+        camera.update(dt);
+
+        window.clear();
+        window.set_camera(&camera);
+        for map in maps.iter_mut()
+        {
+            map.update(dt);
+            window.draw(&*map);
+        }
+        window.draw(&player);
+        window.reset_camera();
+
+        window.swap_buffers();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,29 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+in vec4 v_Color;
+
+uniform sampler2D u_Texture;
+uniform float u_AlphaCutoff;
+uniform float u_Premultiply;
+
+void main()
+{
+    float dist = length(v_TexCoord - vec2(0.5));
+    if(dist >= 0.495)
+    {
+        discard;
+    }
+    color = texture(u_Texture, v_TexCoord) * v_Color;
+    if(color.a <= u_AlphaCutoff)
+    {
+        discard;
+    }
+    color.rgb *= mix(1.0, color.a, u_Premultiply);
+}
@@ -0,0 +1,26 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+in vec4 v_Color;
+
+uniform sampler2D u_Texture;
+// Set while drawing masks so transparent pixels stay out of them.
+uniform float u_AlphaCutoff;
+// `1.0` while drawing with `BlendMode::Multiply`, which takes premultiplied colors.
+uniform float u_Premultiply;
+
+void main()
+{
+    color = texture(u_Texture, v_TexCoord) * v_Color;
+    if(color.a <= u_AlphaCutoff)
+    {
+        discard;
+    }
+    color.rgb *= mix(1.0, color.a, u_Premultiply);
+}
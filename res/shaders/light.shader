@@ -0,0 +1,52 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+// From -1 to 1 across the light, 0 at its center.
+in vec2 v_TexCoord;
+// The light's color times its intensity.
+in vec4 v_Color;
+
+// White where the light is not blocked.
+uniform sampler2D u_Shadow;
+// Normals of normal-mapped sprites, transparent where there are none.
+uniform sampler2D u_Normals;
+// Size of the light map in pixels.
+uniform vec2 u_Resolution;
+uniform float u_Radius;
+uniform float u_Falloff;
+uniform float u_Height;
+uniform bool u_Spot;
+// Unit vector the cone points along.
+uniform vec2 u_Direction;
+// Cosines of the half angles at the outside and the inside of the cone's soft edge.
+uniform float u_ConeOuter;
+uniform float u_ConeInner;
+
+void main()
+{
+    float dist = length(v_TexCoord);
+    float light = pow(clamp(1.0 - dist, 0.0, 1.0), u_Falloff);
+    if(u_Spot && dist > 0.0)
+    {
+        float c = dot(v_TexCoord / dist, u_Direction);
+        light *= smoothstep(u_ConeOuter, u_ConeInner, c);
+    }
+
+    vec2 screen = gl_FragCoord.xy / u_Resolution;
+    light *= texture(u_Shadow, screen).r;
+
+    vec4 normal = texture(u_Normals, screen);
+    if(normal.a > 0.0)
+    {
+        vec3 n = normalize(normal.xyz * 2.0 - 1.0);
+        vec3 l = normalize(vec3(-v_TexCoord * u_Radius, u_Height));
+        light *= max(dot(n, l), 0.0);
+    }
+
+    color = vec4(v_Color.rgb * light, 1.0);
+}
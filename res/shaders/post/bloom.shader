@@ -0,0 +1,19 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Texture;
+uniform sampler2D u_Bloom;
+uniform float u_Intensity;
+
+void main()
+{
+    vec4 bloom = texture(u_Bloom, v_TexCoord) * u_Intensity;
+    color = texture(u_Texture, v_TexCoord) + vec4(bloom.rgb, 0.0);
+}
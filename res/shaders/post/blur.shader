@@ -0,0 +1,27 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Texture;
+// One texel along the blur direction, times the radius.
+uniform vec2 u_Direction;
+
+// A 9 tap gaussian squeezed into 5 fetches by sampling between texels.
+const float offsets[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float weights[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+void main()
+{
+    color = texture(u_Texture, v_TexCoord) * weights[0];
+    for(int i = 1; i < 3; i++)
+    {
+        color += texture(u_Texture, v_TexCoord + u_Direction * offsets[i]) * weights[i];
+        color += texture(u_Texture, v_TexCoord - u_Direction * offsets[i]) * weights[i];
+    }
+}
@@ -0,0 +1,19 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Texture;
+uniform float u_Threshold;
+
+void main()
+{
+    vec4 texel = texture(u_Texture, v_TexCoord);
+    float luma = dot(texel.rgb, vec3(0.2126, 0.7152, 0.0722));
+    color = texel * smoothstep(u_Threshold, u_Threshold + 0.1, luma);
+}
@@ -0,0 +1,31 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Texture;
+uniform vec2 u_Resolution;
+uniform float u_Curvature;
+uniform float u_Scanlines;
+
+void main()
+{
+    // Bulge the picture like the glass of a tube.
+    vec2 uv = v_TexCoord * 2.0 - 1.0;
+    uv *= 1.0 + u_Curvature * dot(uv, uv);
+    uv = uv * 0.5 + 0.5;
+    if(uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0)
+    {
+        color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    color = texture(u_Texture, uv);
+    float line = sin(uv.y * u_Resolution.y * 3.14159265);
+    color.rgb *= 1.0 - u_Scanlines * (0.5 - 0.5 * line);
+}
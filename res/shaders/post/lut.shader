@@ -0,0 +1,35 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Texture;
+// A strip of u_LutSize tiles of u_LutSize x u_LutSize texels, red growing to the right
+// within a tile, green downwards and blue from tile to tile.
+uniform sampler2D u_Lut;
+uniform float u_LutSize;
+uniform float u_Strength;
+
+vec3 lookup(vec3 c, float tile)
+{
+    float n = u_LutSize;
+    float u = (tile * n + c.r * (n - 1.0) + 0.5) / (n * n);
+    // Textures are stored bottom row first.
+    float v = 1.0 - (c.g * (n - 1.0) + 0.5) / n;
+    return texture(u_Lut, vec2(u, v)).rgb;
+}
+
+void main()
+{
+    color = texture(u_Texture, v_TexCoord);
+    vec3 c = clamp(color.rgb, 0.0, 1.0);
+
+    float blue = c.b * (u_LutSize - 1.0);
+    vec3 graded = mix(lookup(c, floor(blue)), lookup(c, ceil(blue)), fract(blue));
+    color.rgb = mix(color.rgb, graded, u_Strength);
+}
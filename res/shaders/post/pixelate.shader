@@ -0,0 +1,19 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Texture;
+uniform vec2 u_Resolution;
+uniform float u_PixelSize;
+
+void main()
+{
+    vec2 block = u_PixelSize / u_Resolution;
+    color = texture(u_Texture, (floor(v_TexCoord / block) + 0.5) * block);
+}
@@ -0,0 +1,22 @@
+#shader vertex
+#version 330 core
+#include "batch.vertex.glsl"
+
+#shader fragment
+#version 330 core
+layout(location = 0) out vec4 color;
+
+in vec2 v_TexCoord;
+
+uniform sampler2D u_Texture;
+uniform float u_Radius;
+uniform float u_Softness;
+uniform float u_Strength;
+
+void main()
+{
+    color = texture(u_Texture, v_TexCoord);
+    float dist = distance(v_TexCoord, vec2(0.5)) * 1.41421356;
+    float shade = smoothstep(u_Radius, u_Radius - u_Softness, dist);
+    color.rgb *= mix(1.0, shade, u_Strength);
+}
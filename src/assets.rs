@@ -0,0 +1,356 @@
+//! Loading and caching of textures, fonts and shaders by path.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc,
+};
+
+use crate::{
+    error::Error,
+    graphics::{
+        material::Material,
+        text::{CharSet, Handle},
+        texture::{Texture, TextureBuilder},
+    },
+    window::Window,
+};
+
+/// How far an asset got.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadState
+{
+    Loading,
+    Loaded,
+    /// Loading failed, with the error as text.
+    Failed(String),
+}
+
+struct Slot<T>
+{
+    path:  PathBuf,
+    state: RefCell<LoadState>,
+    value: RefCell<Option<T>>,
+}
+
+/// A handle to an asset that may still be loading in the background.
+///
+/// Handles are cheap to clone and all clones see the asset once it is loaded.
+pub struct Asset<T>
+{
+    slot: Rc<Slot<T>>,
+}
+
+impl<T> Clone for Asset<T>
+{
+    fn clone(&self) -> Self
+    {
+        Self {
+            slot: Rc::clone(&self.slot),
+        }
+    }
+}
+
+impl<T: Clone> Asset<T>
+{
+    pub fn path(&self) -> &Path
+    {
+        &self.slot.path
+    }
+
+    pub fn state(&self) -> LoadState
+    {
+        self.slot.state.borrow().clone()
+    }
+
+    pub fn is_loaded(&self) -> bool
+    {
+        *self.slot.state.borrow() == LoadState::Loaded
+    }
+
+    /// The asset, `None` while it is loading or if loading failed.
+    pub fn get(&self) -> Option<T>
+    {
+        self.slot.value.borrow().clone()
+    }
+}
+
+type Configure = Box<dyn FnOnce(TextureBuilder<'static>) -> TextureBuilder<'static>>;
+/// The request id, the path and the decoded image.
+type Decoded = (u64, PathBuf, Result<image::DynamicImage, image::ImageError>);
+
+/// Loads every file once, no matter how often it is asked for.
+///
+/// Images are decoded on a worker thread and uploaded to the GPU in `update`,
+/// which has to be called regularly on the thread owning the window.
+/// Fonts and shaders need the GL context throughout, so they load right away.
+///
+/// ```ignore
+/// let mut assets = Assets::new();
+/// let player = assets.texture("res/player.png");
+/// while !player.is_loaded()
+/// {
+///     assets.update();
+///     draw_loading_bar(assets.progress());
+/// }
+/// ```
+pub struct Assets
+{
+    textures:  HashMap<PathBuf, Asset<Texture>>,
+    /// By request id, as a path unloaded while decoding can be requested again.
+    configure: HashMap<u64, Configure>,
+    /// The request whose result goes into the texture at a path, older ones are stale.
+    loading:   HashMap<PathBuf, u64>,
+    materials: HashMap<PathBuf, Material>,
+    fonts:     HashMap<PathBuf, Handle>,
+
+    requests: mpsc::Sender<(u64, PathBuf)>,
+    results:  mpsc::Receiver<Decoded>,
+    next_id:  u64,
+    /// Decodes sent to the worker that have not come back yet.
+    pending:  usize,
+    /// Decodes requested since the worker was last idle, for `progress`.
+    started:  usize,
+}
+
+impl Default for Assets
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Assets
+{
+    pub fn new() -> Self
+    {
+        let (requests, jobs) = mpsc::channel::<(u64, PathBuf)>();
+        let (done, results) = mpsc::channel();
+
+        std::thread::Builder::new()
+            .name("tori assets".to_string())
+            .spawn(move || {
+                // Ends once `Assets` is dropped and the request channel closes.
+                for (id, path) in jobs
+                {
+                    let image = image::open(&path);
+                    if done.send((id, path, image)).is_err()
+                    {
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn the asset worker thread");
+
+        Self {
+            textures: HashMap::new(),
+            configure: HashMap::new(),
+            loading: HashMap::new(),
+            materials: HashMap::new(),
+            fonts: HashMap::new(),
+            requests,
+            results,
+            next_id: 0,
+            pending: 0,
+            started: 0,
+        }
+    }
+
+    /// Tells apart different spellings of the same file where possible.
+    fn key(path: &Path) -> PathBuf
+    {
+        std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// Starts loading the image at `path` with the default texture options.
+    pub fn texture<P: AsRef<Path>>(&mut self, path: P) -> Asset<Texture>
+    {
+        self.texture_with(path, |builder| builder)
+    }
+
+    /// Starts loading the image at `path`, letting `configure` set filtering and
+    /// the like before the upload. If the path was requested before, the existing
+    /// asset is returned and `configure` is ignored.
+    pub fn texture_with<P, F>(&mut self, path: P, configure: F) -> Asset<Texture>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(TextureBuilder<'static>) -> TextureBuilder<'static> + 'static,
+    {
+        let key = Self::key(path.as_ref());
+        if let Some(asset) = self.textures.get(&key)
+        {
+            return asset.clone();
+        }
+
+        let asset = Asset {
+            slot: Rc::new(Slot {
+                path: path.as_ref().to_path_buf(),
+                state: RefCell::new(LoadState::Loading),
+                value: RefCell::new(None),
+            }),
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        self.textures.insert(key.clone(), asset.clone());
+        self.loading.insert(key.clone(), id);
+        self.configure.insert(id, Box::new(configure));
+
+        if self.pending == 0
+        {
+            self.started = 0;
+        }
+        self.pending += 1;
+        self.started += 1;
+        // The worker only goes away together with `self`.
+        let _ = self.requests.send((id, key));
+
+        asset
+    }
+
+    /// Loads a material, sharing the compiled shader with earlier loads of the same file.
+    pub fn material<P: AsRef<Path>>(&mut self, path: P) -> Result<Material, Error>
+    {
+        let key = Self::key(path.as_ref());
+        if let Some(material) = self.materials.get(&key)
+        {
+            return Ok(material.clone());
+        }
+
+        let material = Material::new(path)?;
+        self.materials.insert(key, material.clone());
+        Ok(material)
+    }
+
+    /// Loads a font, see `Window::load_font`. Only the first load of a path
+    /// rasterizes glyphs, later calls get the same font whatever their `set`.
+    pub fn font<P: AsRef<Path>>(
+        &mut self,
+        window: &mut Window,
+        path: P,
+        set: CharSet,
+    ) -> Result<Handle, Error>
+    {
+        let key = Self::key(path.as_ref());
+        if let Some(font) = self.fonts.get(&key)
+        {
+            return Ok(font.clone());
+        }
+
+        let font = window.load_font(path, set)?;
+        self.fonts.insert(key, font.clone());
+        Ok(font)
+    }
+
+    /// Uploads the images decoded since the last call. Never blocks.
+    pub fn update(&mut self)
+    {
+        while let Ok((id, key, image)) = self.results.try_recv()
+        {
+            self.finish(id, key, image);
+        }
+    }
+
+    /// Blocks until every requested image is loaded.
+    pub fn wait(&mut self)
+    {
+        while self.pending > 0
+        {
+            match self.results.recv()
+            {
+                Ok((id, key, image)) => self.finish(id, key, image),
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn finish(
+        &mut self,
+        id: u64,
+        key: PathBuf,
+        image: Result<image::DynamicImage, image::ImageError>,
+    )
+    {
+        self.pending -= 1;
+        let configure = self.configure.remove(&id);
+
+        // Unloaded while it was still decoding, and maybe requested again since.
+        if self.loading.get(&key) != Some(&id)
+        {
+            return;
+        }
+        self.loading.remove(&key);
+        let Some(asset) = self.textures.get(&key)
+        else
+        {
+            return;
+        };
+
+        let texture = image.map_err(Error::from).and_then(|image| {
+            let builder = TextureBuilder::from_image(image);
+            match configure
+            {
+                Some(configure) => configure(builder).build(),
+                None => builder.build(),
+            }
+        });
+
+        let state = match texture
+        {
+            Ok(texture) =>
+            {
+                *asset.slot.value.borrow_mut() = Some(texture);
+                LoadState::Loaded
+            },
+            Err(e) =>
+            {
+                log::warn!(target: "tori::assets", "{}: {}", asset.slot.path.display(), e);
+                LoadState::Failed(e.to_string())
+            },
+        };
+        *asset.slot.state.borrow_mut() = state;
+    }
+
+    /// Number of images still being decoded or waiting for `update`.
+    pub fn pending(&self) -> usize
+    {
+        self.pending
+    }
+
+    /// Fraction of the images requested since loading was last idle that are done,
+    /// `1.0` when nothing is loading.
+    pub fn progress(&self) -> f32
+    {
+        if self.started == 0
+        {
+            return 1.0;
+        }
+        (self.started - self.pending) as f32 / self.started as f32
+    }
+
+    /// Drops every asset that is only referenced by the cache, so its GPU memory is
+    /// freed once nothing else uses it. Loading the path again reads the file again.
+    pub fn unload_unused(&mut self)
+    {
+        self.textures.retain(|_, asset| {
+            let texture_users = asset
+                .slot
+                .value
+                .borrow()
+                .as_ref()
+                .map_or(0, |texture| Rc::strong_count(&texture.texture) - 1);
+            Rc::strong_count(&asset.slot) > 1 || texture_users > 0
+        });
+        self.materials.retain(|_, material| Rc::strong_count(&material.shader) > 1);
+        self.fonts.retain(|_, font| {
+            if Rc::strong_count(&font.2) > 1
+            {
+                return true;
+            }
+            font.1.remove_font(font.0);
+            false
+        });
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    ffi::{c_void, CStr},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{core::util::has_extension, error::Error};
+
+/// Set once `KHR_debug` output is installed, at which point the driver reports
+/// errors itself and the `glGetError` fallback in `gl_call!` is skipped.
+static DEBUG_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn is_enabled() -> bool
+{
+    DEBUG_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Routes driver messages through the `log` facade.
+/// Does nothing if the context has no `KHR_debug` support.
+pub(crate) fn install(filter: log::LevelFilter)
+{
+    if !supported()
+    {
+        return;
+    }
+
+    unsafe
+    {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        if cfg!(debug_assertions)
+        {
+            // Report inside the offending call so backtraces point at it.
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+        gl::DebugMessageCallback(Some(callback), std::ptr::null());
+    }
+    DEBUG_OUTPUT.store(true, Ordering::Relaxed);
+
+    set_filter(filter);
+}
+
+/// Core since GL 4.3. Drivers often export the functions without supporting them,
+/// so a loaded function pointer says nothing.
+fn supported() -> bool
+{
+    let (mut major, mut minor) = (0, 0);
+    unsafe
+    {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    gl::DebugMessageCallback::is_loaded()
+        && ((major, minor) >= (4, 3) || has_extension(b"GL_KHR_debug"))
+}
+
+/// Disables driver messages below `filter` at the source, so they are never generated.
+pub(crate) fn set_filter(filter: log::LevelFilter)
+{
+    if !is_enabled()
+    {
+        return;
+    }
+
+    for severity in [
+        gl::DEBUG_SEVERITY_HIGH,
+        gl::DEBUG_SEVERITY_MEDIUM,
+        gl::DEBUG_SEVERITY_LOW,
+        gl::DEBUG_SEVERITY_NOTIFICATION,
+    ]
+    {
+        let enabled = level(severity) <= filter;
+        unsafe
+        {
+            gl::DebugMessageControl(
+                gl::DONT_CARE,
+                gl::DONT_CARE,
+                severity,
+                0,
+                std::ptr::null(),
+                enabled as _,
+            );
+        }
+    }
+}
+
+fn level(severity: u32) -> log::Level
+{
+    match severity
+    {
+        gl::DEBUG_SEVERITY_HIGH => log::Level::Error,
+        gl::DEBUG_SEVERITY_MEDIUM => log::Level::Warn,
+        gl::DEBUG_SEVERITY_LOW => log::Level::Info,
+        _ => log::Level::Debug,
+    }
+}
+
+extern "system" fn callback(
+    source: u32,
+    r#type: u32,
+    id: u32,
+    severity: u32,
+    _length: i32,
+    message: *const std::ffi::c_char,
+    _user: *mut c_void,
+)
+{
+    let message = unsafe
+    {
+        CStr::from_ptr(message).to_string_lossy()
+    };
+    let source = match source
+    {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    };
+    let kind = match r#type
+    {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        _ => "other",
+    };
+
+    log::log!(target: "tori::gl", level(severity), "[{} {} {}] {}", source, kind, id, message);
+}
+
+/// Drains the GL error queue, returning the first error as `Error::Gl`.
+pub(crate) fn check(call: &str) -> Result<(), Error>
+{
+    let mut first = None;
+    loop
+    {
+        let error = unsafe
+        {
+            gl::GetError()
+        };
+        if error == gl::NO_ERROR
+        {
+            break;
+        }
+        first.get_or_insert(error);
+    }
+
+    match first
+    {
+        Some(code) => Err(Error::Gl {
+            code,
+            call: call.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Logs anything in the GL error queue, the fallback for contexts without `KHR_debug`.
+pub(crate) fn log_errors(call: &str, file: &str, line: u32)
+{
+    if let Err(Error::Gl {
+        code, ..
+    }) = check(call)
+    {
+        log::error!(
+            target: "tori::gl",
+            "[OpenGL Error] ({:x}) {} in {}, line: {}",
+            code,
+            call,
+            file,
+            line
+        );
+    }
+}
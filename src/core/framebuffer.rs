@@ -0,0 +1,102 @@
+use crate::{
+    core::{
+        texture::Texture,
+        util::{gl_call, gl_try},
+    },
+    error::Error,
+};
+
+pub struct Framebuffer
+{
+    renderer_id:       u32,
+    /// Stencil buffer for masking.
+    stencil:           u32,
+    pub(crate) width:  i32,
+    pub(crate) height: i32,
+}
+
+impl Framebuffer
+{
+    pub fn new(texture: &Texture) -> Result<Self, Error>
+    {
+        let mut fb = Self {
+            renderer_id: 0,
+            stencil: 0,
+            width: texture.width,
+            height: texture.height,
+        };
+
+        // Creating one mid-frame must not pull drawing away from the renderer's target.
+        let mut previous = 0;
+        unsafe
+        {
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous);
+        }
+        // `fb` owns the names from here on, so they are deleted if `attach` fails.
+        let attached = fb.attach(texture);
+        unsafe
+        {
+            gl_call!(gl::BindFramebuffer(gl::FRAMEBUFFER, previous as u32));
+        }
+        attached.map(|()| fb)
+    }
+
+    fn attach(&mut self, texture: &Texture) -> Result<(), Error>
+    {
+        unsafe
+        {
+            gl_try!(gl::GenFramebuffers(1, &mut self.renderer_id));
+            gl_try!(gl::BindFramebuffer(gl::FRAMEBUFFER, self.renderer_id));
+            gl_try!(gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture.id(),
+                0
+            ));
+
+            gl_try!(gl::GenRenderbuffers(1, &mut self.stencil));
+            gl_try!(gl::BindRenderbuffer(gl::RENDERBUFFER, self.stencil));
+            gl_try!(gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                gl::DEPTH24_STENCIL8,
+                texture.width,
+                texture.height
+            ));
+            gl_try!(gl::BindRenderbuffer(gl::RENDERBUFFER, 0));
+            gl_try!(gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::RENDERBUFFER,
+                self.stencil
+            ));
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE
+            {
+                return Err(Error::Gl {
+                    code: status,
+                    call: "glCheckFramebufferStatus".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn id(&self) -> u32
+    {
+        self.renderer_id
+    }
+}
+
+impl Drop for Framebuffer
+{
+    fn drop(&mut self)
+    {
+        unsafe
+        {
+            gl_call!(gl::DeleteFramebuffers(1, &self.renderer_id as *const u32));
+            gl_call!(gl::DeleteRenderbuffers(1, &self.stencil as *const u32));
+        }
+    }
+}
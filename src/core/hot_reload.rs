@@ -0,0 +1,68 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+    time::{Duration, Instant},
+};
+
+use crate::core::shader::Shader;
+
+/// How often the watched files are checked, stat'ing every frame is wasteful.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+// GL objects belong to the thread owning the context, so the list is per thread.
+thread_local! {
+    static WATCHED: RefCell<Vec<Weak<Shader>>> = RefCell::default();
+    static LAST_POLL: Cell<Option<Instant>> = Cell::default();
+}
+
+/// Starts watching the file `shader` was loaded from, until the shader is dropped.
+pub(crate) fn watch(shader: &Rc<Shader>)
+{
+    if shader.path().is_some()
+    {
+        WATCHED.with(|w| w.borrow_mut().push(Rc::downgrade(shader)));
+    }
+}
+
+/// Recompiles watched shaders whose files changed.
+/// Failures are logged and leave the previous program in place.
+pub(crate) fn poll()
+{
+    let due = LAST_POLL.with(|last| match last.get()
+    {
+        Some(t) if t.elapsed() < INTERVAL => false,
+        _ =>
+        {
+            last.set(Some(Instant::now()));
+            true
+        },
+    });
+    if !due
+    {
+        return;
+    }
+
+    WATCHED.with(|w| {
+        w.borrow_mut().retain(|shader| {
+            let Some(shader) = shader.upgrade()
+            else
+            {
+                return false;
+            };
+
+            if shader.changed_on_disk()
+            {
+                let path = shader.path().unwrap().display();
+                match shader.reload()
+                {
+                    Ok(()) => log::info!(target: "tori::shader", "Reloaded {}", path),
+                    Err(e) =>
+                    {
+                        log::error!(target: "tori::shader", "Reloading {} failed: {}", path, e)
+                    },
+                }
+            }
+            true
+        });
+    });
+}
@@ -1,7 +1,11 @@
-pub(crate) mod renderer;
+pub mod renderer;
+pub(crate) mod debug;
+#[cfg(feature = "hot-reload")]
+pub(crate) mod hot_reload;
 pub(crate) mod util;
 pub(crate) mod vertex_buffer;
 
+pub(crate) mod framebuffer;
 pub(crate) mod index_buffer;
 pub(crate) mod vertex_array;
 
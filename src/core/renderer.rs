@@ -1,38 +1,1104 @@
-use crate::core::{
-    index_buffer::IndexBuffer, shader::Shader, util::gl_call, vertex_array::VertexArray,
+use std::{
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
 };
 
-pub struct Renderer {}
+use crate::{
+    core::{
+        framebuffer::Framebuffer, index_buffer::IndexBuffer, shader::Shader, texture::Texture,
+        util::gl_call,
+        vertex_array::VertexArray, vertex_buffer::VertexBuffer,
+        vertex_buffer_layout::VertexBufferLayout,
+    },
+    error::Error,
+    graphics::{blend::BlendMode, material::Material, texture},
+    math,
+    util::{get_shader, ShaderProgram},
+};
+
+/// Number of quads the shared vertex buffer can hold before it has to be flushed.
+pub(crate) const MAX_QUADS: usize = 10_000;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Vertex
+{
+    pub pos:   [f32; 2],
+    pub uv:    [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl Vertex
+{
+    pub fn new(pos: math::Vec2, uv: math::Vec2, color: math::Vec4) -> Self
+    {
+        Self {
+            pos: [pos.x, pos.y], uv: [uv.x, uv.y], color: [color.x, color.y, color.z, color.w]
+        }
+    }
+}
+
+/// How `Vertex` is laid out for the vertex shader.
+fn vertex_layout() -> VertexBufferLayout
+{
+    let mut layout = VertexBufferLayout::new();
+    layout.push(2, gl::FLOAT);
+    layout.push(2, gl::FLOAT);
+    layout.push(4, gl::FLOAT);
+    layout
+}
+
+/// Quads uploaded once, for things that rarely change such as the chunks of a tile map.
+/// Drawn with `Renderer::draw_mesh`.
+pub(crate) struct QuadMesh
+{
+    va:       VertexArray,
+    _vb:      VertexBuffer,
+    /// Kept around for draws that are recorded instead of drawn right away.
+    vertices: Vec<Vertex>,
+}
+
+impl QuadMesh
+{
+    /// Four vertices per quad, in the same order as for `Renderer::push_quad`.
+    pub(crate) fn new(vertices: Vec<Vertex>) -> Self
+    {
+        debug_assert!(vertices.len().is_multiple_of(4) && vertices.len() <= MAX_QUADS * 4);
+
+        let vb = VertexBuffer::new(&vertices);
+        let mut va = VertexArray::new();
+        va.add_buffer(&vb, vertex_layout());
+        vb.unbind();
+
+        Self {
+            va, _vb: vb, vertices
+        }
+    }
+
+    pub(crate) fn quads(&self) -> usize
+    {
+        self.vertices.len() / 4
+    }
+}
+
+/// The corners of the unit square transformed by `model`, see `Renderer::draw_quad`.
+fn model_vertices(model: &math::Mat4, uv: math::Vec4, color: math::Vec4) -> [Vertex; 4]
+{
+    let corner = |x: f32, y: f32| {
+        let p = model * math::vec4(x, y, 0.0, 1.0);
+        math::vec2(p.x, p.y)
+    };
+
+    [
+        Vertex::new(corner(0.0, 0.0), math::vec2(uv[0], uv[1]), color),
+        Vertex::new(corner(1.0, 0.0), math::vec2(uv[2], uv[1]), color),
+        Vertex::new(corner(1.0, 1.0), math::vec2(uv[2], uv[3]), color),
+        Vertex::new(corner(0.0, 1.0), math::vec2(uv[0], uv[3]), color),
+    ]
+}
+
+/// The shader a batch is drawn with.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Pipeline
+{
+    Quad,
+    Circle,
+    Material(Material),
+}
+
+/// Counters for a single frame, see `Window::stats`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stats
+{
+    pub draw_calls: usize,
+    pub quads:      usize,
+    pub vertices:   usize,
+}
+
+/// What the renderer is currently drawing into.
+#[derive(Clone, Copy)]
+struct Target
+{
+    fbo:      u32,
+    viewport: [i32; 4],
+    /// Size in logical units, what the default projection covers.
+    size:     math::Vec2,
+    proj:     math::Mat4,
+}
+
+impl Target
+{
+    fn ortho(&self) -> math::Mat4
+    {
+        math::ortho(0., self.size.x, 0., self.size.y, -1., 1.)
+    }
+}
+
+#[derive(Clone)]
+struct BatchKey
+{
+    pipeline: Pipeline,
+    /// Raw GL name, what batches are told apart by.
+    texture:  u32,
+    /// Keeps `texture` alive until the batch is drawn, `None` for the renderer's own.
+    owner:    Option<texture::Texture>,
+    blend:    BlendMode,
+    /// Scissor rectangle in framebuffer pixels.
+    clip:     Option<[i32; 4]>,
+}
+
+impl PartialEq for BatchKey
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.pipeline == other.pipeline
+            && self.texture == other.texture
+            && self.blend == other.blend
+            && self.clip == other.clip
+    }
+}
+
+/// How a draw layer is set up, see `Window::draw_layered`.
+#[derive(Clone, Copy, Default)]
+struct Layer
+{
+    /// `None` draws in window units, like UI.
+    proj:   Option<math::Mat4>,
+    y_sort: bool,
+}
+
+/// A mask pushed onto the window, kept so `flush_layers` can draw it again
+/// for the quads recorded while it was pushed.
+struct Mask
+{
+    /// The mask pushed before this one, which it is nested in.
+    parent: Option<Rc<Mask>>,
+    /// The quads of the mask with the projection each was drawn with.
+    quads:  Vec<(math::Mat4, BatchKey, [Vertex; 4])>,
+}
+
+/// A quad recorded by `draw_layered`, drawn once the layers are flushed.
+struct Queued
+{
+    layer:    i32,
+    z:        f32,
+    /// Lowest y of the drawable the quad belongs to, for y-sorting.
+    y:        f32,
+    key:      BatchKey,
+    vertices: [Vertex; 4],
+    /// The innermost mask pushed onto the window when the quad was recorded.
+    mask:     Option<Rc<Mask>>,
+}
+
+pub struct Renderer
+{
+    va:       VertexArray,
+    vb:       VertexBuffer,
+    ib:       IndexBuffer,
+    quad:     Shader,
+    circle:   Shader,
+    white:    Texture,
+    vertices: Vec<Vertex>,
+    key:      Option<BatchKey>,
+    proj:     math::Mat4,
+    screen:   Target,
+    target:   Option<Target>,
+    frame:    Stats,
+    last:     Stats,
+    blend:    BlendMode,
+    /// What GL is currently set to, so unchanged modes are not set again.
+    applied:  Option<BlendMode>,
+    /// The scissor rectangle GL is set to, `None` while scissoring is off.
+    scissor:  Option<[i32; 4]>,
+    /// Clip stacks by framebuffer, each entry already intersected with the one below.
+    clips:    HashMap<u32, Vec<[i32; 4]>>,
+    /// Number of masks pushed by framebuffer, which is the stencil value drawing passes at.
+    masks:    HashMap<u32, u8>,
+    /// The innermost mask pushed onto the window.
+    mask:     Option<Rc<Mask>>,
+    /// Quads of the window mask being pushed, see `Mask`.
+    shaping:  Option<Vec<(math::Mat4, BatchKey, [Vertex; 4])>>,
+    /// Fragments of built-in pipelines with less alpha than this are discarded.
+    cutoff:   f32,
+    /// While set only normal maps are drawn, see `begin_normals`.
+    normals:  bool,
+
+    layers:    BTreeMap<i32, Layer>,
+    queue:     Vec<Queued>,
+    /// Layer and z of the drawable being recorded, if any.
+    recording: Option<(i32, f32)>,
+}
 
 impl Renderer
 {
-    pub fn new() -> Self
+    pub(crate) fn new(width: i32, height: i32) -> Result<Self, Error>
     {
-        Self {}
+        let proj = math::ortho(0., width as _, 0., height as _, -1., 1.);
+
+        let vb = VertexBuffer::new_dynamic(
+            (MAX_QUADS * 4 * std::mem::size_of::<Vertex>()) as isize,
+        );
+        let mut va = VertexArray::new();
+        va.add_buffer(&vb, vertex_layout());
+
+        let indices: Vec<u32> = (0..MAX_QUADS as u32)
+            .flat_map(|i| {
+                let o = i * 4;
+                [o, o + 1, o + 2, o + 2, o + 3, o]
+            })
+            .collect();
+        let ib = IndexBuffer::new(&indices);
+
+        let quad = Shader::from_shader_string(get_shader(ShaderProgram::Batch))?;
+        let circle = Shader::from_shader_string(get_shader(ShaderProgram::Circle))?;
+
+        va.unbind();
+        vb.unbind();
+        ib.unbind();
+
+        Ok(Self {
+            va,
+            vb,
+            ib,
+            quad,
+            circle,
+            white: Texture::from_rgba(1, 1, &[255; 4])?,
+            vertices: Vec::with_capacity(MAX_QUADS * 4),
+            key: None,
+            proj,
+            screen: Target {
+                fbo: 0,
+                viewport: [0, 0, width, height],
+                size: math::vec2(width as _, height as _),
+                proj,
+            },
+            target: None,
+            frame: Stats::default(),
+            last: Stats::default(),
+            blend: BlendMode::default(),
+            applied: None,
+            scissor: None,
+            clips: HashMap::new(),
+            masks: HashMap::new(),
+            mask: None,
+            shaping: None,
+            cutoff: -1.0,
+            normals: false,
+            layers: BTreeMap::new(),
+            queue: Vec::new(),
+            recording: None,
+        })
     }
 
-    pub fn draw(&self, va: &VertexArray, ib: &IndexBuffer, shader: &Shader)
+    pub fn projection(&self) -> math::Mat4
     {
-        shader.bind();
-        va.bind();
-        ib.bind();
+        self.proj
+    }
+
+    /// Everything queued so far is drawn with the old projection before switching.
+    pub fn set_projection(&mut self, proj: math::Mat4)
+    {
+        if proj != self.proj
+        {
+            self.flush();
+            self.proj = proj;
+        }
+    }
+
+    /// Goes back to the pixel sized ortho projection of the current target.
+    pub fn reset_projection(&mut self)
+    {
+        let target = self.target.unwrap_or(self.screen);
+        self.set_projection(target.ortho());
+    }
+
+    /// Moves the window's drawing area after a resize. The default projection follows
+    /// the new size, a camera's projection is kept.
+    pub(crate) fn set_screen(&mut self, viewport: [i32; 4], size: math::Vec2)
+    {
+        self.flush();
+        let old = self.screen.ortho();
+        self.screen.viewport = viewport;
+        self.screen.size = size;
+
+        // While drawing to the window `proj` is its projection, else the saved one is.
+        let proj = if self.target.is_none() { self.proj } else { self.screen.proj };
+        self.screen.proj = if proj == old { self.screen.ortho() } else { proj };
+
+        if self.target.is_none()
+        {
+            self.proj = self.screen.proj;
+            self.apply_target(self.screen);
+        }
+    }
+
+    /// Makes `fbo` stand in for the window, e.g. to post-process everything drawn to it.
+    /// `0` goes back to the window itself.
+    pub(crate) fn set_screen_fbo(&mut self, fbo: u32)
+    {
+        if fbo == self.screen.fbo
+        {
+            return;
+        }
+
+        self.flush();
+        self.screen.fbo = fbo;
+        if self.target.is_none()
+        {
+            self.apply_target(self.screen);
+        }
+    }
+
+    /// Draws `source` over the whole `viewport` of `fbo` with `material`,
+    /// ignoring clips, masks and the projection. The current target is restored afterwards.
+    pub(crate) fn pass(
+        &mut self,
+        material: &Material,
+        source: u32,
+        fbo: u32,
+        viewport: [i32; 4],
+        blend: BlendMode,
+    )
+    {
+        self.flush();
+        let [x, y, w, h] = viewport;
+        unsafe
+        {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::Viewport(x, y, w, h);
+        }
+        self.apply_stencil(0, gl::KEEP);
+
+        let proj = self.proj;
+        self.proj = math::ortho(0., 1., 0., 1., -1., 1.);
+        let key = BatchKey {
+            pipeline: Pipeline::Material(material.clone()),
+            texture: source,
+            owner: None,
+            blend,
+            clip: None,
+        };
+        let white = math::vec4(1.0, 1.0, 1.0, 1.0);
+        let corner = |x: f32, y: f32| Vertex::new(math::vec2(x, y), math::vec2(x, y), white);
+        let corners = [corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0)];
+        self.queue_quad(key, corners);
+        self.flush();
+
+        self.proj = proj;
+        self.apply_target(self.target.unwrap_or(self.screen));
+    }
+
+    /// What stands in for the window and the viewport inside it.
+    pub(crate) fn screen(&self) -> (u32, [i32; 4])
+    {
+        (self.screen.fbo, self.screen.viewport)
+    }
+
+    /// Clears all of `fbo`, outside of the viewport too.
+    pub(crate) fn clear_fbo(&mut self, fbo: u32)
+    {
+        self.flush();
+        self.disable_scissor();
+        unsafe
+        {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+        }
+        self.apply_target(self.target.unwrap_or(self.screen));
+    }
+
+    /// Redirects drawing into `fb`, or back to the window for `None`.
+    ///
+    /// A framebuffer starts out with a pixel sized ortho projection,
+    /// the window's projection is restored when switching back.
+    pub(crate) fn set_target(&mut self, fb: Option<&Framebuffer>)
+    {
+        let fbo = fb.map(|fb| fb.id()).unwrap_or(self.screen.fbo);
+        let current = self.fbo();
+        if fbo == current
+        {
+            return;
+        }
+
+        self.flush();
+        if self.target.is_none()
+        {
+            self.screen.proj = self.proj;
+        }
+
+        self.target = fb.map(|fb| Target {
+            fbo,
+            viewport: [0, 0, fb.width, fb.height],
+            size: math::vec2(fb.width as _, fb.height as _),
+            proj: math::ortho(0., fb.width as _, 0., fb.height as _, -1., 1.),
+        });
+        let target = self.target.unwrap_or(self.screen);
+
+        self.proj = target.proj;
+        self.apply_target(target);
+    }
+
+    fn apply_target(&self, target: Target)
+    {
+        let [x, y, w, h] = target.viewport;
+        unsafe
+        {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.fbo);
+            gl::Viewport(x, y, w, h);
+        }
+        self.apply_stencil(self.mask_depth(), gl::KEEP);
+    }
+
+    fn fbo(&self) -> u32
+    {
+        self.target.map(|t| t.fbo).unwrap_or(self.screen.fbo)
+    }
+
+    /// Restricts drawing to `rect`, `(x, y, width, height)` in logical units of the
+    /// current target, ignoring the projection. Nested clips only draw where all of them overlap.
+    pub(crate) fn push_clip(&mut self, rect: math::Vec4)
+    {
+        let target = self.target.unwrap_or(self.screen);
+        let [vx, vy, vw, vh] = target.viewport;
+        let sx = vw as f32 / target.size.x;
+        let sy = vh as f32 / target.size.y;
+
+        let x0 = vx + (rect.x * sx).round() as i32;
+        let y0 = vy + (rect.y * sy).round() as i32;
+        let x1 = vx + ((rect.x + rect[2]) * sx).round() as i32;
+        let y1 = vy + ((rect.y + rect[3]) * sy).round() as i32;
+
+        let stack = self.clips.entry(target.fbo).or_default();
+        let [x0, y0, x1, y1] = match stack.last()
+        {
+            Some(&[px, py, pw, ph]) => [x0.max(px), y0.max(py), x1.min(px + pw), y1.min(py + ph)],
+            None => [x0, y0, x1, y1],
+        };
+        stack.push([x0, y0, (x1 - x0).max(0), (y1 - y0).max(0)]);
+    }
+
+    pub(crate) fn pop_clip(&mut self)
+    {
+        if let Some(stack) = self.clips.get_mut(&self.fbo())
+        {
+            stack.pop();
+        }
+    }
+
+    fn clip(&self) -> Option<[i32; 4]>
+    {
+        self.clips.get(&self.fbo()).and_then(|stack| stack.last().copied())
+    }
+
+    fn mask_depth(&self) -> u8
+    {
+        self.masks.get(&self.fbo()).copied().unwrap_or(0)
+    }
+
+    /// Draws pass where the stencil buffer equals `depth`, and `op` is applied to it there.
+    fn apply_stencil(&self, depth: u8, op: u32)
+    {
+        unsafe
+        {
+            if depth == 0 && op == gl::KEEP
+            {
+                gl::Disable(gl::STENCIL_TEST);
+                return;
+            }
+            gl::Enable(gl::STENCIL_TEST);
+            gl::StencilFunc(gl::EQUAL, depth as _, 0xFF);
+            gl::StencilOp(gl::KEEP, gl::KEEP, op);
+        }
+    }
+
+    /// Everything pushed until `end_mask` becomes a mask instead of being drawn.
+    /// Fully transparent pixels of the built-in pipelines are left out of the mask.
+    pub(crate) fn begin_mask(&mut self)
+    {
+        self.flush();
+        self.apply_stencil(self.mask_depth(), gl::INCR);
+        unsafe
+        {
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+        }
+        self.cutoff = 0.0;
+        if self.target.is_none()
+        {
+            self.shaping = Some(Vec::new());
+        }
+    }
+
+    /// From now on only draws inside the mask, and inside every mask pushed before it.
+    pub(crate) fn end_mask(&mut self)
+    {
+        self.flush();
+        self.cutoff = -1.0;
+        unsafe
+        {
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        }
+
+        let fbo = self.fbo();
+        let depth = self.masks.entry(fbo).or_default();
+        *depth = depth.saturating_add(1);
+        self.apply_stencil(self.mask_depth(), gl::KEEP);
+
+        if let Some(quads) = self.shaping.take()
+        {
+            self.mask = Some(Rc::new(Mask {
+                parent: self.mask.take(),
+                quads,
+            }));
+        }
+    }
+
+    /// Removes the mask pushed last.
+    pub(crate) fn pop_mask(&mut self)
+    {
+        let depth = self.mask_depth();
+        if depth == 0
+        {
+            return;
+        }
+        self.flush();
+        self.unstencil(depth);
+
+        let fbo = self.fbo();
+        self.masks.insert(fbo, depth - 1);
+        self.apply_stencil(depth - 1, gl::KEEP);
+        if self.target.is_none()
+        {
+            self.mask = self.mask.take().and_then(|mask| mask.parent.clone());
+        }
+    }
+
+    /// Takes the stencil buffer from `depth` down by one over the whole target,
+    /// without clipping, which undoes the mask that brought it there.
+    fn unstencil(&mut self, depth: u8)
+    {
+        self.apply_stencil(depth, gl::DECR);
+        unsafe
+        {
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+        }
+        let proj = self.proj;
+        self.proj = math::identity();
+        let key = BatchKey {
+            pipeline: Pipeline::Quad,
+            texture: self.white.id(),
+            owner: None,
+            blend: BlendMode::None,
+            clip: None,
+        };
+        let white = math::vec4(1.0, 1.0, 1.0, 1.0);
+        let corner = |x: f32, y: f32| Vertex::new(math::vec2(x, y), math::vec2(0.0, 0.0), white);
+        let corners = [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)];
+        self.queue_quad(key, corners);
+        self.flush();
+        self.proj = proj;
+        unsafe
+        {
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        }
+    }
+
+    /// Statistics for the last finished frame.
+    pub fn stats(&self) -> Stats
+    {
+        self.last
+    }
+
+    /// The blend mode for quads pushed from now on.
+    /// `Window` goes back to `BlendMode::Alpha` before every drawable it draws.
+    pub fn set_blend_mode(&mut self, blend: BlendMode)
+    {
+        self.blend = blend;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode
+    {
+        self.blend
+    }
+
+    /// Queues a quad given its corners in counter clockwise order,
+    /// starting at the bottom left.
+    ///
+    /// `None` draws with a plain white texture so the quad gets the vertex color.
+    /// The batch holds on to `texture` until it is drawn.
+    pub fn push_quad(
+        &mut self,
+        pipeline: Pipeline,
+        texture: Option<&texture::Texture>,
+        vertices: [Vertex; 4],
+    )
+    {
+        if self.normals
+        {
+            return;
+        }
+
+        let key = self.key_for(pipeline, texture);
+        self.queue_quad(key, vertices);
+    }
+
+    fn key_for(&self, pipeline: Pipeline, texture: Option<&texture::Texture>) -> BatchKey
+    {
+        BatchKey {
+            pipeline,
+            texture: texture.map_or(self.white.id(), |t| t.texture.id()),
+            owner: texture.cloned(),
+            blend: self.blend,
+            clip: self.clip(),
+        }
+    }
+
+    fn queue_quad(&mut self, key: BatchKey, vertices: [Vertex; 4])
+    {
+        if let Some((layer, z)) = self.recording
+        {
+            self.queue.push(Queued {
+                layer,
+                z,
+                y: 0.0,
+                key,
+                vertices,
+                mask: self.mask.clone(),
+            });
+            return;
+        }
+        if let Some(quads) = &mut self.shaping
+        {
+            quads.push((self.proj, key.clone(), vertices));
+        }
+
+        if self.key.as_ref() != Some(&key) || self.vertices.len() >= MAX_QUADS * 4
+        {
+            self.flush();
+            self.key = Some(key);
+        }
 
+        self.vertices.extend_from_slice(&vertices);
+    }
+
+    /// Queues the unit square `(0, 0) - (1, 1)` transformed by `model`.
+    ///
+    /// `uv` is `(u0, v0, u1, v1)`, the texture coordinates of the bottom left
+    /// and top right corners.
+    pub fn draw_quad(
+        &mut self,
+        model: &math::Mat4,
+        uv: math::Vec4,
+        color: math::Vec4,
+        texture: Option<&texture::Texture>,
+    )
+    {
+        self.draw_model(Pipeline::Quad, model, uv, color, texture);
+    }
+
+    /// Like `draw_quad`, but drawn with `pipeline`.
+    pub fn draw_model(
+        &mut self,
+        pipeline: Pipeline,
+        model: &math::Mat4,
+        uv: math::Vec4,
+        color: math::Vec4,
+        texture: Option<&texture::Texture>,
+    )
+    {
+        self.push_quad(pipeline, texture, model_vertices(model, uv, color));
+    }
+
+    /// Everything pushed until `end_normals` is dropped, only `draw_normals` draws.
+    /// Fully transparent pixels of normal maps are left out.
+    pub(crate) fn begin_normals(&mut self)
+    {
+        self.flush();
+        self.normals = true;
+        self.cutoff = 0.0;
+    }
+
+    pub(crate) fn end_normals(&mut self)
+    {
+        self.flush();
+        self.normals = false;
+        self.cutoff = -1.0;
+    }
+
+    /// Whether drawables should draw their normal maps instead of themselves.
+    pub(crate) fn drawing_normals(&self) -> bool
+    {
+        self.normals
+    }
+
+    /// Like `draw_quad` with `normal_map` as the texture, only while drawing normals.
+    pub(crate) fn draw_normals(
+        &mut self,
+        model: &math::Mat4,
+        uv: math::Vec4,
+        normal_map: &texture::Texture,
+    )
+    {
+        if !self.normals
+        {
+            return;
+        }
+
+        let key = BatchKey {
+            blend: BlendMode::None,
+            ..self.key_for(Pipeline::Quad, Some(normal_map))
+        };
+        let white = math::vec4(1.0, 1.0, 1.0, 1.0);
+        self.queue_quad(key, model_vertices(model, uv, white));
+    }
+
+    /// Draws all of `mesh` in one call, without copying it into the batch.
+    pub(crate) fn draw_mesh(
+        &mut self,
+        pipeline: Pipeline,
+        texture: Option<&texture::Texture>,
+        mesh: &QuadMesh,
+    )
+    {
+        if self.normals || mesh.quads() == 0
+        {
+            return;
+        }
+
+        let key = self.key_for(pipeline, texture);
+        if self.recording.is_some()
+        {
+            for quad in mesh.vertices.chunks_exact(4)
+            {
+                self.queue_quad(key.clone(), [quad[0], quad[1], quad[2], quad[3]]);
+            }
+            return;
+        }
+
+        self.flush();
+        self.key = Some(key);
+        self.bind_key();
         unsafe
         {
+            mesh.va.bind();
+            self.ib.bind();
             gl_call!(gl::DrawElements(
                 gl::TRIANGLES,
-                ib.count as _,
+                (mesh.quads() * 6) as _,
                 gl::UNSIGNED_INT,
                 std::ptr::null()
             ));
+            mesh.va.unbind();
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        self.frame.draw_calls += 1;
+        self.frame.quads += mesh.quads();
+        self.frame.vertices += mesh.vertices.len();
+    }
+
+    /// The projection what is drawn now ends up with, which differs from `projection`
+    /// while recording for a layer with its own camera.
+    pub(crate) fn drawing_projection(&self) -> math::Mat4
+    {
+        match self.recording
+        {
+            Some((layer, _)) => self
+                .layers
+                .get(&layer)
+                .and_then(|l| l.proj)
+                .unwrap_or_else(|| self.screen.ortho()),
+            None => self.proj,
         }
     }
 
-    pub fn clear(&self)
+    /// Records the quads pushed until `end_layered` instead of drawing them.
+    pub(crate) fn begin_layered(&mut self, layer: i32, z: f32) -> usize
+    {
+        self.recording = Some((layer, z));
+        self.queue.len()
+    }
+
+    /// Stops recording, `start` is what `begin_layered` returned.
+    pub(crate) fn end_layered(&mut self, start: usize)
     {
+        self.recording = None;
+
+        // Y-sorting goes by the bottom of the whole drawable, so its quads stay together.
+        let recorded = &mut self.queue[start..];
+        let y = recorded
+            .iter()
+            .flat_map(|q| q.vertices.iter().map(|v| v.pos[1]))
+            .fold(f32::INFINITY, f32::min);
+        for queued in recorded
+        {
+            queued.y = y;
+        }
+    }
+
+    /// `None` draws the layer without a camera, in window units.
+    pub(crate) fn set_layer_projection(&mut self, layer: i32, proj: Option<math::Mat4>)
+    {
+        self.layers.entry(layer).or_default().proj = proj;
+    }
+
+    pub(crate) fn set_layer_y_sort(&mut self, layer: i32, y_sort: bool)
+    {
+        self.layers.entry(layer).or_default().y_sort = y_sort;
+    }
+
+    /// Draws everything recorded with `begin_layered` into the window,
+    /// lower layers first and within a layer by z, then by submission order.
+    pub(crate) fn flush_layers(&mut self)
+    {
+        if self.queue.is_empty()
+        {
+            return;
+        }
+
+        let mut queue = std::mem::take(&mut self.queue);
+        let layers = &self.layers;
+        queue.sort_by(|a, b| {
+            let y_sort = layers.get(&a.layer).is_some_and(|l| l.y_sort);
+            a.layer.cmp(&b.layer).then(a.z.total_cmp(&b.z)).then_with(|| {
+                // Things further up are further away and go first.
+                if y_sort { b.y.total_cmp(&a.y) } else { std::cmp::Ordering::Equal }
+            })
+        });
+
+        self.set_target(None);
+        let proj = self.proj;
+        let mut current = None;
+        let mut mask: Option<Rc<Mask>> = None;
+        for queued in queue.drain(..)
+        {
+            if current != Some(queued.layer)
+            {
+                current = Some(queued.layer);
+                let layer = self.layers.get(&queued.layer).copied().unwrap_or_default();
+                self.set_projection(layer.proj.unwrap_or_else(|| self.screen.ortho()));
+            }
+            // The masks are gone from the stencil buffer by now, so they are drawn again.
+            let same = match (&mask, &queued.mask)
+            {
+                (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            };
+            if !same
+            {
+                self.flush();
+                self.remove_layered_mask(mask.as_deref());
+                self.apply_layered_mask(queued.mask.as_deref());
+                mask = queued.mask;
+            }
+            self.queue_quad(queued.key, queued.vertices);
+        }
+        self.flush();
+        self.remove_layered_mask(mask.as_deref());
+        self.set_projection(proj);
+
+        // Hand the allocation back for the next frame.
+        self.queue = queue;
+    }
+
+    /// Draws `mask` and the masks it is nested in on top of the masks pushed right now.
+    fn apply_layered_mask(&mut self, mask: Option<&Mask>)
+    {
+        let mut chain = Vec::new();
+        let mut next = mask;
+        while let Some(mask) = next
+        {
+            chain.push(mask);
+            next = mask.parent.as_deref();
+        }
+
+        let base = self.mask_depth();
+        let proj = self.proj;
+        for (depth, mask) in chain.iter().rev().enumerate()
+        {
+            self.apply_stencil(base + depth as u8, gl::INCR);
+            unsafe
+            {
+                gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            }
+            self.cutoff = 0.0;
+            for (mask_proj, key, vertices) in mask.quads.iter()
+            {
+                self.set_projection(*mask_proj);
+                self.queue_quad(key.clone(), *vertices);
+            }
+            self.flush();
+            self.cutoff = -1.0;
+            unsafe
+            {
+                gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            }
+        }
+        self.set_projection(proj);
+        self.apply_stencil(base + chain.len() as u8, gl::KEEP);
+    }
+
+    /// Undoes `apply_layered_mask` for `mask`.
+    fn remove_layered_mask(&mut self, mask: Option<&Mask>)
+    {
+        let base = self.mask_depth();
+        let mut depth = base;
+        let mut next = mask;
+        while let Some(mask) = next
+        {
+            depth += 1;
+            next = mask.parent.as_deref();
+        }
+        if depth == base
+        {
+            return;
+        }
+
+        for depth in (base + 1..=depth).rev()
+        {
+            self.unstencil(depth);
+        }
+        self.apply_stencil(base, gl::KEEP);
+    }
+
+    /// Issues a draw call for everything queued since the last flush.
+    pub fn flush(&mut self)
+    {
+        if self.key.is_none() || self.vertices.is_empty()
+        {
+            return;
+        }
+
+        self.bind_key();
+        let quads = self.vertices.len() / 4;
         unsafe
         {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+            self.vb.sub_data(&self.vertices);
+            self.va.bind();
+            self.ib.bind();
+
+            gl_call!(gl::DrawElements(
+                gl::TRIANGLES,
+                (quads * 6) as _,
+                gl::UNSIGNED_INT,
+                std::ptr::null()
+            ));
+
+            self.va.unbind();
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        self.frame.draw_calls += 1;
+        self.frame.quads += quads;
+        self.frame.vertices += self.vertices.len();
+        self.vertices.clear();
+    }
+
+    /// Sets GL up for drawing the current batch.
+    fn bind_key(&mut self)
+    {
+        let Some(key) = &self.key
+        else
+        {
+            return;
+        };
+
+        let shader = match &key.pipeline
+        {
+            Pipeline::Quad => &self.quad,
+            Pipeline::Circle => &self.circle,
+            Pipeline::Material(material) =>
+            {
+                material.apply();
+                &material.shader
+            },
+        };
+        if self.applied != Some(key.blend)
+        {
+            key.blend.apply();
+            self.applied = Some(key.blend);
+        }
+        if self.scissor != key.clip
+        {
+            unsafe
+            {
+                match key.clip
+                {
+                    Some([x, y, w, h]) =>
+                    {
+                        gl::Enable(gl::SCISSOR_TEST);
+                        gl::Scissor(x, y, w, h);
+                    },
+                    None => gl::Disable(gl::SCISSOR_TEST),
+                }
+            }
+            self.scissor = key.clip;
         }
+
+        shader.bind();
+        shader.set_uniform_mat4f("u_Projection\0", &self.proj);
+        shader.set_uniform_1i("u_Texture\0", 0);
+        shader.set_uniform_1f("u_AlphaCutoff\0", self.cutoff);
+        let premultiply = key.blend == BlendMode::Multiply;
+        shader.set_uniform_1f("u_Premultiply\0", if premultiply { 1.0 } else { 0.0 });
+
+        unsafe
+        {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, key.texture);
+        }
+    }
+
+    /// Clears the whole target, ignoring clips.
+    /// The stencil buffer is only cleared while no masks are pushed.
+    pub fn clear(&mut self)
+    {
+        self.flush();
+        self.disable_scissor();
+        unsafe
+        {
+            gl::Clear(self.clear_bits());
+        }
+    }
+
+    pub fn clear_with(&mut self, color: math::Vec4)
+    {
+        self.flush();
+        self.disable_scissor();
+        unsafe
+        {
+            let mut old = [0.0f32; 4];
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, old.as_mut_ptr());
+            gl::ClearColor(color.x, color.y, color.z, color.w);
+            gl::Clear(self.clear_bits());
+            gl::ClearColor(old[0], old[1], old[2], old[3]);
+        }
+    }
+
+    fn clear_bits(&self) -> u32
+    {
+        match self.mask_depth()
+        {
+            0 => gl::COLOR_BUFFER_BIT | gl::STENCIL_BUFFER_BIT,
+            _ => gl::COLOR_BUFFER_BIT,
+        }
+    }
+
+    fn disable_scissor(&mut self)
+    {
+        if self.scissor.take().is_some()
+        {
+            unsafe
+            {
+                gl::Disable(gl::SCISSOR_TEST);
+            }
+        }
+    }
+
+    pub(crate) fn end_frame(&mut self)
+    {
+        self.set_target(None);
+        self.flush_layers();
+        self.flush();
+        self.last = std::mem::take(&mut self.frame);
     }
 }
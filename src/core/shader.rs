@@ -1,7 +1,9 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::HashMap,
     ffi::{CStr, CString},
+    path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 pub enum Cow<'a>
@@ -34,6 +36,14 @@ impl<'a> Cow<'a>
     }
 }
 
+impl<'a> From<&'a CStr> for Cow<'a>
+{
+    fn from(value: &'a CStr) -> Self
+    {
+        Self::Borrow(value)
+    }
+}
+
 impl<'a> From<&'a str> for Cow<'a>
 {
     fn from(value: &'a str) -> Self
@@ -59,11 +69,25 @@ impl<'a> From<&'a str> for Cow<'a>
 }
 
 
-use crate::core::util::{gl_call, raw};
+use crate::{
+    core::util::{gl_call, raw},
+    error::{Error, ShaderStage},
+    graphics::{
+        material::Uniform,
+        preprocessor::{Preprocessor, Source},
+    },
+};
 
 pub struct Shader
 {
-    renderer_id: u32,
+    // A cell so the program can be swapped out by `reload`
+    // while materials share the shader.
+    renderer_id:  Cell<u32>,
+    path:         Option<PathBuf>,
+    /// The shader file and the files it includes, with their modification times
+    /// as of the last compile.
+    files:        RefCell<Vec<(PathBuf, Option<SystemTime>)>>,
+    preprocessor: Preprocessor,
 
     // I dont want the shader to require being mutable.
     // I'll change it to a mutex if it ever crashes
@@ -73,31 +97,123 @@ pub struct Shader
 
 impl Shader
 {
-    pub fn new<P: AsRef<std::path::Path>>(path: P) -> Self
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error>
+    {
+        Self::new_with(path, Preprocessor::default())
+    }
+
+    pub fn new_with<P: AsRef<Path>>(path: P, preprocessor: Preprocessor) -> Result<Self, Error>
+    {
+        let path = path.as_ref();
+        let modified = modified(path);
+        let source = std::fs::read_to_string(path)?;
+        let (shader, includes) = Self::compile(&source, &preprocessor, path.parent())?;
+
+        let mut files = vec![(path.to_path_buf(), modified)];
+        files.extend(stamp(includes));
+        Ok(Self {
+            renderer_id: Cell::new(shader),
+            path: Some(path.to_path_buf()),
+            files: RefCell::new(files),
+            preprocessor,
+            location_cache: RefCell::default(),
+        })
+    }
+
+    pub fn from_shader_string(s: &str) -> Result<Self, Error>
     {
-        let (vertex_shader, fragment_shader) = Self::parse_shader(path.as_ref());
-        let shader = Self::create_shader(vertex_shader.as_bytes(), fragment_shader.as_bytes());
+        Self::from_shader_string_with(s, Preprocessor::default())
+    }
+
+    pub fn from_shader_string_with(s: &str, preprocessor: Preprocessor) -> Result<Self, Error>
+    {
+        let (shader, _) = Self::compile(s, &preprocessor, None)?;
+
+        Ok(Self {
+            renderer_id: Cell::new(shader),
+            path: None,
+            files: RefCell::default(),
+            preprocessor,
+            location_cache: RefCell::default(),
+        })
+    }
 
-        Self {
-            renderer_id: shader as _, location_cache: RefCell::default()
+    pub fn path(&self) -> Option<&Path>
+    {
+        self.path.as_deref()
+    }
+
+    /// Whether the file the shader was loaded from, or a file it includes,
+    /// changed since it was last compiled.
+    pub fn changed_on_disk(&self) -> bool
+    {
+        self.files.borrow().iter().any(|(file, time)| modified(file) != *time)
+    }
+
+    /// Recompiles the shader from its file in place.
+    ///
+    /// On failure the previous program is kept, so drawing carries on as before.
+    pub fn reload(&self) -> Result<(), Error>
+    {
+        let Some(path) = &self.path
+        else
+        {
+            return Ok(());
+        };
+
+        // Remember the attempt even if it fails, so a broken file
+        // is not recompiled over and over until it is saved again.
+        for (file, time) in self.files.borrow_mut().iter_mut()
+        {
+            *time = modified(file);
         }
+        let source = std::fs::read_to_string(path)?;
+        let (program, includes) = Self::compile(&source, &self.preprocessor, path.parent())?;
+
+        // The includes may have changed along with the source.
+        let mut files = self.files.borrow_mut();
+        files.truncate(1);
+        files.extend(stamp(includes));
+
+        let old = self.renderer_id.replace(program);
+        unsafe
+        {
+            gl::DeleteProgram(old);
+        }
+
+        // Locations belong to the old program.
+        self.location_cache.borrow_mut().clear();
+        Ok(())
     }
 
-    pub fn from_shader_string(s: &str) -> Self
+    /// The program and the files included by any of its stages.
+    fn compile(
+        s: &str,
+        preprocessor: &Preprocessor,
+        dir: Option<&Path>,
+    ) -> Result<(u32, Vec<PathBuf>), Error>
     {
-        let (vertex_shader, fragment_shader) = Self::parse_shader_from_str(s);
-        let shader = Self::create_shader(vertex_shader.as_bytes(), fragment_shader.as_bytes());
+        let stages = Self::parse_shader_from_str(s)?
+            .into_iter()
+            .map(|(stage, source)| Ok((stage, preprocessor.process(stage, &source, dir)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
 
-        Self {
-            renderer_id: shader as _, location_cache: RefCell::default()
+        let mut files: Vec<PathBuf> = Vec::new();
+        for file in stages.iter().flat_map(|(_, source)| source.files.iter())
+        {
+            if !files.contains(file)
+            {
+                files.push(file.clone());
+            }
         }
+        Ok((Self::create_shader(&stages)?, files))
     }
 
     pub fn bind(&self)
     {
         unsafe
         {
-            gl::UseProgram(self.renderer_id);
+            gl::UseProgram(self.renderer_id.get());
         }
     }
 
@@ -109,11 +225,16 @@ impl Shader
         }
     }
 
+    pub fn set_uniform<U: Uniform + ?Sized>(&self, name: &CStr, value: &U)
+    {
+        value.set(self.get_uniform_location(name));
+    }
+
     pub fn set_uniform_1i(&self, name: &str, val: i32)
     {
         unsafe
         {
-            gl::Uniform1i(self.get_uniform_location(name), val);
+            gl_call!(gl::Uniform1i(self.get_uniform_location(name), val));
         }
     }
 
@@ -122,7 +243,7 @@ impl Shader
         unsafe
         {
             let location = self.get_uniform_location(name);
-            gl::Uniform4f(location, v1, v2, v3, v4);
+            gl_call!(gl::Uniform4f(location, v1, v2, v3, v4));
         }
     }
 
@@ -131,7 +252,7 @@ impl Shader
         unsafe
         {
             let location = self.get_uniform_location(name);
-            gl::Uniform3f(location, v1, v2, v3);
+            gl_call!(gl::Uniform3f(location, v1, v2, v3));
         }
     }
 
@@ -140,7 +261,7 @@ impl Shader
         unsafe
         {
             let location = self.get_uniform_location(name);
-            gl_call!(gl::UniformMatrix4fv(location, 1, gl::FALSE, matrix.as_ptr(),));
+            gl_call!(gl::UniformMatrix4fv(location, 1, gl::FALSE, matrix.as_ptr()));
         }
     }
 
@@ -149,7 +270,7 @@ impl Shader
         unsafe
         {
             let location = self.get_uniform_location(name);
-            gl::Uniform1ui(location, val);
+            gl_call!(gl::Uniform1ui(location, val));
         }
     }
 
@@ -158,7 +279,7 @@ impl Shader
         unsafe
         {
             let location = self.get_uniform_location(name);
-            gl::Uniform1f(location, val);
+            gl_call!(gl::Uniform1f(location, val));
         }
     }
 }
@@ -178,115 +299,149 @@ impl Shader
                 .location_cache
                 .borrow_mut()
                 .entry(key)
-                .or_insert_with(|| gl::GetUniformLocation(self.renderer_id, raw!(name)))
+                .or_insert_with(|| gl::GetUniformLocation(self.renderer_id.get(), raw!(name)))
         };
         location
     }
 
-    fn create_shader(vertex_shader: &[u8], fragment_shader: &[u8]) -> i32
+    fn create_shader(stages: &[(ShaderStage, Source)]) -> Result<u32, Error>
     {
         unsafe
         {
-            let program = gl::CreateProgram();
-            let vs = Self::compile_shader(gl::VERTEX_SHADER, vertex_shader);
-            let fs = Self::compile_shader(gl::FRAGMENT_SHADER, fragment_shader);
+            let mut ids = Vec::with_capacity(stages.len());
+            for (stage, source) in stages
+            {
+                match Self::compile_shader(*stage, source)
+                {
+                    Ok(id) => ids.push(id),
+                    Err(e) =>
+                    {
+                        ids.iter().for_each(|id| gl::DeleteShader(*id));
+                        return Err(e);
+                    },
+                }
+            }
 
-            gl::AttachShader(program, vs);
-            gl::AttachShader(program, fs);
+            let program = gl::CreateProgram();
+            for id in ids.iter()
+            {
+                gl::AttachShader(program, *id);
+            }
             gl::LinkProgram(program);
-            gl::ValidateProgram(program);
-
-            gl::DeleteShader(vs);
-            gl::DeleteShader(fs);
 
-            program as _
-        }
-    }
-
-    fn parse_shader_from_str(s: &str) -> (String, String)
-    {
-        let mut vertex = String::new();
-        let mut fragment = String::new();
-        let mut current = None;
-
-        for line in s.lines()
-        {
-            if line.starts_with("#shader")
+            for id in ids.iter()
             {
-                if line.contains("vertex")
-                {
-                    current = Some(&mut vertex);
-                }
-                else if line.contains("fragment")
-                {
-                    current = Some(&mut fragment);
-                }
-                else
-                {
-                    panic!("Encountered something else.. {}", line);
-                }
-                continue;
+                gl::DeleteShader(*id);
             }
-            if let Some(shader) = current.as_mut()
+
+            let mut res = 0;
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut res);
+            if res as u8 == gl::FALSE
             {
-                shader.push_str(line);
-                shader.push('\n');
+                let mut length = 0;
+                gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut length);
+
+                let mut message = vec![0u8; length.max(1) as usize];
+                gl::GetProgramInfoLog(program, length, &mut length, message.as_mut_ptr() as _);
+                message.truncate(length as usize);
+
+                gl::DeleteProgram(program);
+                return Err(Error::Shader {
+                    stage: ShaderStage::Link,
+                    log: String::from_utf8_lossy(&message).into_owned(),
+                    lines: Vec::new(),
+                });
             }
-        }
 
-        vertex.push('\0');
-        fragment.push('\0');
+            gl::ValidateProgram(program);
 
-        (vertex, fragment)
+            Ok(program)
+        }
     }
 
-    fn parse_shader<P: AsRef<std::path::Path>>(path: P) -> (String, String)
+    /// Splits a combined `.shader` file into its stages.
+    /// The vertex and fragment sections are required, a geometry section is optional.
+    fn parse_shader_from_str(s: &str) -> Result<Vec<(ShaderStage, Source)>, Error>
     {
-        let mut vertex = String::new();
-        let mut fragment = String::new();
-        let mut current = None;
+        let mut stages: Vec<(ShaderStage, Source)> = Vec::new();
 
-        for line in std::fs::read_to_string(path).unwrap().lines()
+        for (i, line) in s.lines().enumerate()
         {
             if line.starts_with("#shader")
             {
-                if line.contains("vertex")
+                let stage = if line.contains("vertex")
                 {
-                    current = Some(&mut vertex);
+                    ShaderStage::Vertex
                 }
                 else if line.contains("fragment")
                 {
-                    current = Some(&mut fragment);
+                    ShaderStage::Fragment
+                }
+                else if line.contains("geometry")
+                {
+                    ShaderStage::Geometry
                 }
                 else
                 {
-                    panic!("Encountered something else..");
+                    return Err(Error::Shader {
+                        stage: ShaderStage::Source,
+                        log: format!("Unknown shader section: {}", line),
+                        lines: vec![i + 1],
+                    });
+                };
+                if stages.iter().any(|(s, _)| *s == stage)
+                {
+                    return Err(Error::Shader {
+                        stage: ShaderStage::Source,
+                        log: format!("Duplicate shader section: {}", line),
+                        lines: vec![i + 1],
+                    });
                 }
+                stages.push((stage, Source::new()));
                 continue;
             }
-            if let Some(shader) = current.as_mut()
+            if let Some((_, shader)) = stages.last_mut()
             {
-                shader.push_str(line);
-                shader.push('\n');
+                shader.push(line, i + 1);
             }
         }
 
-        vertex.push('\0');
-        fragment.push('\0');
+        for (stage, name) in [(ShaderStage::Vertex, "vertex"), (ShaderStage::Fragment, "fragment")]
+        {
+            if !stages.iter().any(|(s, source)| *s == stage && !source.code.is_empty())
+            {
+                return Err(Error::Shader {
+                    stage: ShaderStage::Source,
+                    log: format!("Missing #shader {} section", name),
+                    lines: Vec::new(),
+                });
+            }
+        }
 
-        (vertex, fragment)
+        Ok(stages)
     }
 
-    fn compile_shader(r#type: u32, src: &[u8]) -> u32
+    fn compile_shader(stage: ShaderStage, src: &Source) -> Result<u32, Error>
     {
+        let r#type = match stage
+        {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            _ => gl::FRAGMENT_SHADER,
+        };
+
+        let code = CString::new(src.code.as_str()).map_err(|_| Error::Shader {
+            stage,
+            log: "Shader source contains a nul byte".to_string(),
+            lines: Vec::new(),
+        })?;
+
         unsafe
         {
             let id = gl::CreateShader(r#type);
 
-            let ptr = src.as_ptr();
-            let ptr_i8: *const i8 = std::mem::transmute(ptr);
-
-            gl::ShaderSource(id, 1, &ptr_i8, std::ptr::null());
+            let ptr = raw!(code);
+            gl::ShaderSource(id, 1, &ptr, std::ptr::null());
             gl::CompileShader(id);
 
             let mut res = 0;
@@ -296,37 +451,145 @@ impl Shader
                 let mut length = 0;
                 gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut length);
 
-                let message: Vec<u8> = Vec::with_capacity(length as usize);
-
-                gl::GetShaderInfoLog(
-                    id,
-                    length,
-                    &length as *const i32 as *mut i32,
-                    message.as_ptr() as _,
-                );
-
-                let s = std::str::from_utf8_unchecked(&message);
-                println!(
-                    "Failed to compile {} shader {}",
-                    if r#type == gl::VERTEX_SHADER { "vertex" } else { "fragment" },
-                    s
-                );
+                let mut message = vec![0u8; length.max(1) as usize];
+                gl::GetShaderInfoLog(id, length, &mut length, message.as_mut_ptr() as _);
+                message.truncate(length as usize);
+
                 gl::DeleteShader(id);
-                std::process::exit(1);
+
+                let log = String::from_utf8_lossy(&message);
+                let (log, lines) = map_log_lines(&log, &src.lines);
+                return Err(Error::Shader {
+                    stage,
+                    log,
+                    lines,
+                });
             }
 
-            id
+            Ok(id)
         }
     }
 }
 
+fn modified(path: &Path) -> Option<SystemTime>
+{
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn stamp(files: Vec<PathBuf>) -> impl Iterator<Item = (PathBuf, Option<SystemTime>)>
+{
+    files.into_iter().map(|file| {
+        let time = modified(&file);
+        (file, time)
+    })
+}
+
+/// How drivers start the lines of an info log that point at a line of source.
+const PREFIXES: [&str; 4] = ["ERROR: 0:", "WARNING: 0:", "0:", "0("];
+
+/// Rewrites the line numbers in a driver info log so they point into the
+/// combined `.shader` file instead of the single, preprocessed stage that was compiled.
+///
+/// Handles the `0:12(3)` (Mesa), `0(12)` (Nvidia) and `ERROR: 0:12:` (AMD, Intel) styles.
+/// Returns the new log and the mapped line numbers.
+fn map_log_lines(log: &str, origins: &[usize]) -> (String, Vec<usize>)
+{
+    let mut out = String::with_capacity(log.len());
+    let mut lines = Vec::new();
+
+    for line in log.lines()
+    {
+        // Only the start of a line is looked at, messages can hold `0:` themselves,
+        // like Nvidia's `C0000:` error codes.
+        let indent = line.len() - line.trim_start().len();
+        let found = PREFIXES
+            .iter()
+            .find(|prefix| line[indent..].starts_with(**prefix))
+            .map(|prefix| indent + prefix.len());
+        let number = found.and_then(|start| {
+            let len = line[start..].bytes().take_while(u8::is_ascii_digit).count();
+            line[start..start + len].parse::<usize>().ok().map(|n| (start, len, n))
+        });
+
+        match number
+        {
+            Some((start, len, n)) =>
+            {
+                let mapped = origins.get(n.wrapping_sub(1)).copied().unwrap_or(n);
+                lines.push(mapped);
+                out.push_str(&line[..start]);
+                out.push_str(&mapped.to_string());
+                out.push_str(&line[start + len..]);
+            },
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+
+    (out, lines)
+}
+
 impl Drop for Shader
 {
     fn drop(&mut self)
     {
         unsafe
         {
-            gl::DeleteProgram(self.renderer_id);
+            gl::DeleteProgram(self.renderer_id.get());
         }
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Line `n` of the compiled stage came from line `n + 100` of the file.
+    fn origins() -> Vec<usize>
+    {
+        (101..200).collect()
+    }
+
+    #[test]
+    fn mesa()
+    {
+        let log = "0:12(3): error: `foo' undeclared\n0:14(10): warning: unused variable `a'";
+        let (log, lines) = map_log_lines(log, &origins());
+        assert_eq!(lines, [112, 114]);
+        assert!(log.starts_with("0:112(3): error: `foo' undeclared\n0:114(10): warning"));
+    }
+
+    #[test]
+    fn nvidia()
+    {
+        let log = "0(12) : error C0000: syntax error, unexpected '}' at token \"}\"\n\
+                   0(7) : warning C7022: unrecognized profile specifier \"foo\"";
+        let (log, lines) = map_log_lines(log, &origins());
+        assert_eq!(lines, [112, 107]);
+        assert!(log.starts_with("0(112) : error C0000: syntax error"));
+        assert!(log.contains("\n0(107) : warning C7022:"));
+    }
+
+    #[test]
+    fn amd_and_intel()
+    {
+        let log = "ERROR: 0:12: 'foo' : undeclared identifier \n\
+                   WARNING: 0:3: 'bar' : extension not supported\n\
+                   ERROR: 2 compilation errors.  No code generated.";
+        let (log, lines) = map_log_lines(log, &origins());
+        assert_eq!(lines, [112, 103]);
+        assert!(log.starts_with("ERROR: 0:112: 'foo' : undeclared identifier"));
+        assert!(log.contains("\nWARNING: 0:103: 'bar'"));
+        assert!(log.contains("\nERROR: 2 compilation errors.  No code generated."));
+    }
+
+    #[test]
+    fn leaves_other_lines_alone()
+    {
+        let log = "  error C0000: 0: in the message\nlink failed";
+        let (mapped, lines) = map_log_lines(log, &origins());
+        assert!(lines.is_empty());
+        assert_eq!(mapped, format!("{}\n", log));
+    }
+}
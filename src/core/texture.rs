@@ -1,14 +1,55 @@
 use std::path::{Path, PathBuf};
 
 use crate::{
-    core::util::{gl_call, ptr},
+    core::util::{gl_call, gl_try, has_extension, ptr},
     error::Error,
+    graphics::texture::{Filter, Wrap},
 };
 
+/// Whether `pixels` holds exactly `width` x `height` RGBA8 pixels.
+pub(crate) fn check_pixels(width: u32, height: u32, pixels: &[u8]) -> Result<(), Error>
+{
+    let expected = (width as u64) * (height as u64) * 4;
+    if pixels.len() as u64 != expected
+    {
+        return Err(Error::PixelBuffer {
+            width,
+            height,
+            len: pixels.len(),
+        });
+    }
+    Ok(())
+}
+
+// From ARB_texture_filter_anisotropic, core since 4.6.
+const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+/// Sampling state a texture is created with.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Options
+{
+    pub filter:     Filter,
+    pub wrap:       [Wrap; 2],
+    pub mipmaps:    bool,
+    pub anisotropy: f32,
+}
+
+impl Default for Options
+{
+    fn default() -> Self
+    {
+        Self {
+            filter: Filter::Linear, wrap: [Wrap::ClampToEdge; 2], mipmaps: false, anisotropy: 1.0
+        }
+    }
+}
+
 pub struct Texture
 {
     renderer_id: u32,
     path:        PathBuf,
+    options:     Options,
 
     // local_buffer: Box<[u8]>,
     pub(crate) width:  i32,
@@ -18,29 +59,100 @@ pub struct Texture
 
 impl Texture
 {
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error>
+    pub fn new<P: AsRef<Path>>(path: P, options: &Options) -> Result<Self, Error>
+    {
+        let image = image::open(path.as_ref())?;
+
+        let mut texture = Self::from_image(image, options)?;
+        texture.path = path.as_ref().to_path_buf();
+        Ok(texture)
+    }
+
+    /// Creates a texture from a decoded image, which stores its top row first.
+    pub fn from_image(image: image::DynamicImage, options: &Options) -> Result<Self, Error>
+    {
+        let mut image = image.into_rgba8();
+        image::imageops::flip_vertical_in_place(&mut image);
+
+        Self::from_rgba_with(image.width() as _, image.height() as _, image.as_raw(), options)
+    }
+
+    /// Creates a texture from tightly packed RGBA8 pixels, bottom row first.
+    pub fn from_rgba(width: i32, height: i32, pixels: &[u8]) -> Result<Self, Error>
+    {
+        Self::from_rgba_with(width, height, pixels, &Options::default())
+    }
+
+    pub fn from_rgba_with(
+        width: i32,
+        height: i32,
+        pixels: &[u8],
+        options: &Options,
+    ) -> Result<Self, Error>
+    {
+        check_pixels(width.max(0) as u32, height.max(0) as u32, pixels)?;
+        Self::create(width, height, ptr!(pixels), options)
+    }
+
+    /// Creates a texture with undefined contents, to be rendered into.
+    pub fn with_size(width: i32, height: i32) -> Result<Self, Error>
+    {
+        Self::create(width, height, std::ptr::null(), &Options::default())
+    }
+
+    fn create(
+        width: i32,
+        height: i32,
+        pixels: *const std::ffi::c_void,
+        options: &Options,
+    ) -> Result<Self, Error>
     {
         let mut renderer_id = 0;
         unsafe
         {
             gl::GenTextures(1, &mut renderer_id);
+        }
+        // Owned right away, so the name is deleted if any call below fails.
+        let texture = Self {
+            renderer_id,
+            path: PathBuf::new(),
+            options: *options,
+            width,
+            height,
+            bpp: 4,
+        };
+        unsafe
+        {
             gl::BindTexture(gl::TEXTURE_2D, renderer_id);
 
-            let mut image = image::open(path.as_ref())?;
-            image::imageops::flip_vertical_in_place(&mut image);
+            let min = match (options.filter, options.mipmaps)
+            {
+                (Filter::Nearest, false) => gl::NEAREST,
+                (Filter::Linear, false) => gl::LINEAR,
+                (Filter::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST,
+                (Filter::Linear, true) => gl::LINEAR_MIPMAP_LINEAR,
+            };
+            let [wrap_s, wrap_t] = options.wrap;
 
-            let width = image.width() as i32;
-            let height = image.height() as i32;
-            let bpp = image.color().channel_count() as _;
+            let mag = options.filter.gl();
 
-            gl_call!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _));
-            gl_call!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _));
-            gl_call!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _));
-            gl_call!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _));
+            gl_try!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min as _));
+            gl_try!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag as _));
+            gl_try!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap_s.gl() as _));
+            gl_try!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap_t.gl() as _));
 
-            let buffer: *const std::ffi::c_void = ptr!(image.as_bytes());
+            if options.anisotropy > 1.0 && anisotropy_supported()
+            {
+                let mut max = 1.0;
+                gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max);
+                gl_try!(gl::TexParameterf(
+                    gl::TEXTURE_2D,
+                    TEXTURE_MAX_ANISOTROPY,
+                    options.anisotropy.min(max)
+                ));
+            }
 
-            gl_call!(gl::TexImage2D(
+            gl_try!(gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
                 gl::RGBA8 as _,
@@ -49,20 +161,54 @@ impl Texture
                 0,
                 gl::RGBA,
                 gl::UNSIGNED_BYTE,
-                buffer,
+                pixels,
             ));
-            gl_call!(gl::BindTexture(gl::TEXTURE_2D, 0));
+            if options.mipmaps && !pixels.is_null()
+            {
+                gl_try!(gl::GenerateMipmap(gl::TEXTURE_2D));
+            }
+            gl_try!(gl::BindTexture(gl::TEXTURE_2D, 0));
+        }
+
+        Ok(texture)
+    }
 
-            Ok(Self {
-                renderer_id,
-                path: path.as_ref().to_path_buf(),
+    /// Overwrites the `width` x `height` block whose bottom left texel is `x, y`
+    /// with tightly packed RGBA8 pixels, bottom row first.
+    pub fn update_region(&self, x: i32, y: i32, width: i32, height: i32, pixels: &[u8])
+    {
+        assert!(x >= 0 && y >= 0 && x + width <= self.width && y + height <= self.height);
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+
+        unsafe
+        {
+            gl::BindTexture(gl::TEXTURE_2D, self.renderer_id);
+            gl_call!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+            gl_call!(gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x,
+                y,
                 width,
                 height,
-                bpp,
-            })
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                ptr!(pixels),
+            ));
+            gl_call!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4));
+            if self.options.mipmaps
+            {
+                gl_call!(gl::GenerateMipmap(gl::TEXTURE_2D));
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
         }
     }
 
+    pub fn id(&self) -> u32
+    {
+        self.renderer_id
+    }
+
     pub fn bind(&self, slot: Option<u32>)
     {
         unsafe
@@ -82,6 +228,13 @@ impl Texture
     }
 }
 
+/// Anisotropic filtering is an extension before GL 4.6, ask the driver whether it is there.
+fn anisotropy_supported() -> bool
+{
+    has_extension(b"GL_ARB_texture_filter_anisotropic")
+        || has_extension(b"GL_EXT_texture_filter_anisotropic")
+}
+
 impl Drop for Texture
 {
     fn drop(&mut self)
@@ -10,30 +10,50 @@ macro_rules! raw {
     };
 }
 
+/// Runs a GL call and, in debug builds without `KHR_debug` output, logs any error it raised.
 macro_rules! gl_call {
     ($fun:expr) => {{
-        $fun;
-        let gl_log_call = || {
-            loop
-            {
-                let error = gl::GetError();
-                if error == gl::NO_ERROR
-                {
-                    break;
-                }
-                println!("[OpenGL Error] ({:x})", error);
-                return false;
-            }
-            return true;
-        };
-        if !gl_log_call()
+        let res = $fun;
+        if cfg!(debug_assertions) && !crate::core::debug::is_enabled()
         {
-            println!("{} yielded an error in {}, line: {}", stringify!($fun), file!(), line!());
-            std::process::exit(1);
+            crate::core::debug::log_errors(stringify!($fun), file!(), line!());
         }
+        res
+    }};
+}
+
+/// Runs a GL call and, in debug builds, returns `Error::Gl` from the enclosing function
+/// if it failed. Errors left over from earlier calls are logged first, not blamed on it.
+macro_rules! gl_try {
+    ($fun:expr) => {{
+        if cfg!(debug_assertions)
+        {
+            crate::core::debug::log_errors(concat!("before ", stringify!($fun)), file!(), line!());
+        }
+        let res = $fun;
+        if cfg!(debug_assertions)
+        {
+            crate::core::debug::check(stringify!($fun))?;
+        }
+        res
     }};
 }
 
 pub(crate) use gl_call;
+pub(crate) use gl_try;
 pub(crate) use ptr;
 pub(crate) use raw;
+
+/// Whether the current context lists the extension `name`, e.g. `b"GL_KHR_debug"`.
+pub(crate) fn has_extension(name: &[u8]) -> bool
+{
+    unsafe
+    {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count.max(0) as u32).any(|i| {
+            let found = gl::GetStringi(gl::EXTENSIONS, i);
+            !found.is_null() && std::ffi::CStr::from_ptr(found as _).to_bytes() == name
+        })
+    }
+}
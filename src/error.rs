@@ -10,6 +10,56 @@ pub enum Error
     Font(#[from] freetype::Error),
     NoFont,
     FontCharError(String),
+    /// A GL call raised `code`.
+    Gl
+    {
+        code: u32,
+        call: String,
+    },
+    /// A shader failed to parse, compile or link.
+    /// `lines` are line numbers in the combined `.shader` source.
+    Shader
+    {
+        stage: ShaderStage,
+        log:   String,
+        lines: Vec<usize>,
+    },
+    /// A data file such as a sprite sheet description is malformed or lacks a field.
+    Format(String),
+    /// A pixel buffer holds `len` bytes instead of `width` x `height` RGBA8 pixels.
+    PixelBuffer
+    {
+        width:  u32,
+        height: u32,
+        len:    usize,
+    },
+    /// A `width` x `height` region at `x, y` reaches outside the texture it updates.
+    TextureRegion
+    {
+        x:      u32,
+        y:      u32,
+        width:  u32,
+        height: u32,
+    },
+    /// An image added to an `AtlasBuilder` does not fit on an empty page,
+    /// `width` and `height` include padding and extrusion.
+    AtlasImageTooLarge
+    {
+        name:   String,
+        width:  u32,
+        height: u32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage
+{
+    /// Splitting the `#shader` sections apart.
+    Source,
+    Vertex,
+    Geometry,
+    Fragment,
+    Link,
 }
 
 impl std::fmt::Display for Error
@@ -0,0 +1,260 @@
+use std::rc::Rc;
+
+use crate::{
+    core::renderer::{Pipeline, Renderer},
+    graphics::{
+        animation::{Sheet, SheetFrame},
+        blend::BlendMode,
+        drawable::Drawable, entity::Entity, material::Material, texture,
+    },
+    math,
+};
+
+/// What happens when the last frame of a tag is reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayMode
+{
+    /// Starts over from the first frame.
+    Loop,
+    /// Plays backwards to the first frame, then forwards again.
+    PingPong,
+    /// Stops on the last frame, see `AnimatedSprite::is_finished`.
+    Once,
+}
+
+/// A sprite that plays the tags of a `Sheet`, advanced with `update`.
+///
+/// `entity.pos` is where the current frame's pivot ends up, and `entity.size`
+/// follows the untrimmed size of the current frame.
+pub struct AnimatedSprite
+{
+    sheet:    Rc<Sheet>,
+    texture:  texture::Texture,
+    material: Option<Material>,
+    blend:    BlendMode,
+
+    pub entity: Entity,
+    /// Multiplies the frame durations of the sheet, `2.0` plays twice as fast.
+    pub speed:  f32,
+
+    tag:      Option<usize>,
+    mode:     PlayMode,
+    /// Position inside the tag's frame list.
+    step:     usize,
+    forward:  bool,
+    elapsed:  f32,
+    playing:  bool,
+    finished: bool,
+}
+
+impl AnimatedSprite
+{
+    /// Shows the first frame of `sheet`, whose image is `texture`, until a tag is played.
+    pub fn new(texture: &texture::Texture, sheet: Rc<Sheet>) -> Self
+    {
+        let size = sheet.frames.first().map_or(math::vec2(0.0, 0.0), |f| f.source_size);
+
+        Self {
+            sheet,
+            texture: texture.clone(),
+            material: None,
+            blend: BlendMode::Alpha,
+            entity: Entity::new(size, math::vec2(0.0, 0.0), 0.0),
+            speed: 1.0,
+            tag: None,
+            mode: PlayMode::Loop,
+            step: 0,
+            forward: true,
+            elapsed: 0.0,
+            playing: false,
+            finished: false,
+        }
+    }
+
+    /// Draws with a user shader instead of the default one, `None` goes back to the default.
+    pub fn set_material(&mut self, material: Option<Material>)
+    {
+        self.material = material;
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode)
+    {
+        self.blend = blend;
+    }
+
+    pub fn sheet(&self) -> &Sheet
+    {
+        &self.sheet
+    }
+
+    /// Starts playing the tag called `name`. Does nothing if it is already playing
+    /// in the same mode, so this can be called every frame. Returns `false` for unknown tags.
+    pub fn play(&mut self, name: &str, mode: PlayMode) -> bool
+    {
+        let Some(tag) = self.sheet.tags.iter().position(|t| t.name == name)
+        else
+        {
+            return false;
+        };
+        if self.tag == Some(tag) && self.mode == mode && self.playing
+        {
+            return true;
+        }
+
+        self.tag = Some(tag);
+        self.mode = mode;
+        self.step = 0;
+        self.forward = true;
+        self.elapsed = 0.0;
+        self.playing = true;
+        self.finished = false;
+        self.entity.size = self.current().source_size;
+        true
+    }
+
+    /// Freezes on the current frame, `resume` continues from there.
+    pub fn pause(&mut self)
+    {
+        self.playing = false;
+    }
+
+    pub fn resume(&mut self)
+    {
+        self.playing = self.tag.is_some() && !self.finished;
+    }
+
+    /// Whether a `PlayMode::Once` tag reached its last frame.
+    pub fn is_finished(&self) -> bool
+    {
+        self.finished
+    }
+
+    /// Name of the tag that was played last.
+    pub fn tag(&self) -> Option<&str>
+    {
+        self.tag.map(|t| self.sheet.tags[t].name.as_str())
+    }
+
+    /// Index into `Sheet::frames` of the frame shown right now.
+    pub fn frame(&self) -> usize
+    {
+        match self.tag
+        {
+            Some(tag) => self.sheet.tags[tag].frames[self.step],
+            None => 0,
+        }
+    }
+
+    fn current(&self) -> &SheetFrame
+    {
+        &self.sheet.frames[self.frame()]
+    }
+
+    /// Advances the animation by `dt` seconds.
+    pub fn update(&mut self, dt: f32)
+    {
+        let Some(tag) = self.tag
+        else
+        {
+            return;
+        };
+        if !self.playing
+        {
+            return;
+        }
+
+        let len = self.sheet.tags[tag].frames.len();
+        self.elapsed += dt * self.speed;
+
+        // A long hitch can skip several frames at once.
+        while self.playing
+        {
+            let duration = self.current().duration;
+            if self.elapsed < duration || duration <= 0.0
+            {
+                break;
+            }
+            self.elapsed -= duration;
+            self.advance(len);
+        }
+
+        self.entity.size = self.current().source_size;
+    }
+
+    fn advance(&mut self, len: usize)
+    {
+        let last = len.saturating_sub(1);
+        match self.mode
+        {
+            PlayMode::Loop => self.step = if self.step >= last { 0 } else { self.step + 1 },
+            PlayMode::Once =>
+            {
+                if self.step >= last
+                {
+                    self.playing = false;
+                    self.finished = true;
+                    self.elapsed = 0.0;
+                }
+                else
+                {
+                    self.step += 1;
+                }
+            },
+            PlayMode::PingPong =>
+            {
+                if last == 0
+                {
+                    return;
+                }
+                if self.forward && self.step >= last
+                {
+                    self.forward = false;
+                }
+                else if !self.forward && self.step == 0
+                {
+                    self.forward = true;
+                }
+                self.step = if self.forward { self.step + 1 } else { self.step - 1 };
+            },
+        }
+    }
+}
+
+impl Drawable for AnimatedSprite
+{
+    fn draw(&self, renderer: &mut Renderer)
+    {
+        let Some(frame) = self.sheet.frames.get(self.frame())
+        else
+        {
+            return;
+        };
+
+        let scale = self.entity.scale;
+        let pivot = frame.pivot.component_mul(&frame.source_size);
+        let origin = (frame.offset - pivot).component_mul(&scale);
+        let size = math::vec2(frame.rect.width as f32, frame.rect.height as f32);
+        let size = size.component_mul(&scale);
+
+        let pos = self.entity.pos;
+        let mut model = math::identity();
+        model = math::translate(&model, &math::vec3(pos.x, pos.y, 0.0));
+        model = math::rotate(&model, self.entity.rotation, &math::vec3(0.0, 0.0, 1.0));
+        model = math::translate(&model, &math::vec3(origin.x, origin.y, 0.0));
+        model = math::scale(&model, &math::vec3(size.x, size.y, 1.0));
+
+        let (tw, th) = (self.texture.width() as f32, self.texture.height() as f32);
+        let rect = frame.rect;
+        let uv = math::vec4(
+            rect.x as f32 / tw,
+            1.0 - rect.bottom() as f32 / th,
+            rect.right() as f32 / tw,
+            1.0 - rect.y as f32 / th,
+        );
+
+        let pipeline = self.material.clone().map_or(Pipeline::Quad, Pipeline::Material);
+        renderer.set_blend_mode(self.blend);
+        let white = math::vec4(1., 1., 1., 1.);
+        renderer.draw_model(pipeline, &model, uv, white, Some(&self.texture));
+    }
+}
@@ -0,0 +1,5 @@
+mod sheet;
+pub use sheet::{Direction, Sheet, SheetFrame, Tag};
+
+mod animated_sprite;
+pub use animated_sprite::{AnimatedSprite, PlayMode};
@@ -0,0 +1,424 @@
+use std::path::{Path, PathBuf};
+
+use crate::{error::Error, graphics::atlas::PackRect, json::Json, math};
+
+/// Milliseconds a frame lasts when the file does not say, Aseprite's default.
+const DEFAULT_DURATION: f32 = 100.0;
+
+/// One image of a sprite sheet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SheetFrame
+{
+    pub name:        String,
+    /// The pixels on the sheet image, origin at its top left.
+    pub rect:        PackRect,
+    /// Size of the frame before transparent borders were trimmed away.
+    pub source_size: math::Vec2,
+    /// Bottom left corner of `rect` inside the untrimmed frame, y pointing up.
+    pub offset:      math::Vec2,
+    /// The point of the untrimmed frame placed at the sprite's position,
+    /// `(0, 0)` is its bottom left and `(1, 1)` its top right.
+    pub pivot:       math::Vec2,
+    /// In seconds.
+    pub duration:    f32,
+}
+
+/// Which way a tag plays, as set in Aseprite.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction
+{
+    Forward,
+    Reverse,
+    PingPong,
+    PingPongReverse,
+}
+
+/// A named run of frames, e.g. `"walk"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tag
+{
+    pub name:      String,
+    /// Indices into `Sheet::frames` in the order they are played,
+    /// already reversed for `Direction::Reverse` and `Direction::PingPongReverse`.
+    pub frames:    Vec<usize>,
+    pub direction: Direction,
+}
+
+/// Frames and animations exported by TexturePacker or Aseprite.
+///
+/// Both the hash and the array flavour of the JSON format are understood.
+/// Aseprite's frame tags become `tags`. TexturePacker has no tags, so frames are
+/// grouped by name instead, with `walk_01.png` and `walk_02.png` forming the tag `walk`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sheet
+{
+    /// The sheet image named in the file, relative to the file for `load`.
+    pub image:  Option<PathBuf>,
+    pub frames: Vec<SheetFrame>,
+    pub tags:   Vec<Tag>,
+}
+
+fn missing(what: &str, field: &str) -> Error
+{
+    Error::Format(format!("sprite sheet: {} has no valid \"{}\"", what, field))
+}
+
+fn field<'a>(json: &'a Json, what: &str, field: &str) -> Result<&'a Json, Error>
+{
+    json.get(field).ok_or_else(|| missing(what, field))
+}
+
+fn number(json: &Json, what: &str, name: &str) -> Result<f32, Error>
+{
+    field(json, what, name)?.as_f32().ok_or_else(|| missing(what, name))
+}
+
+fn read_rect(json: &Json, what: &str) -> Result<PackRect, Error>
+{
+    let int = |name| field(json, what, name)?.as_u32().ok_or_else(|| missing(what, name));
+    Ok(PackRect::new(int("x")?, int("y")?, int("w")?, int("h")?))
+}
+
+impl Sheet
+{
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error>
+    {
+        let path = path.as_ref();
+        let mut sheet = Self::from_json(&std::fs::read_to_string(path)?)?;
+        if let (Some(image), Some(dir)) = (&sheet.image, path.parent())
+        {
+            sheet.image = Some(dir.join(image));
+        }
+        Ok(sheet)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, Error>
+    {
+        let json = Json::parse(text)?;
+
+        let entries: Vec<(&str, &Json)> = match field(&json, "the file", "frames")?
+        {
+            Json::Object(members) => members.iter().map(|(k, v)| (k.as_str(), v)).collect(),
+            Json::Array(items) => items
+                .iter()
+                .map(|v| (v.get("filename").and_then(Json::as_str).unwrap_or_default(), v))
+                .collect(),
+            _ => return Err(missing("the file", "frames")),
+        };
+
+        let frames = entries
+            .into_iter()
+            .map(|(name, json)| Self::frame(name, json))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let meta = json.get("meta");
+        let image = meta.and_then(|m| m.get("image")).and_then(Json::as_str).map(PathBuf::from);
+
+        let tags = match meta.and_then(|m| m.get("frameTags"))
+        {
+            Some(tags) => Self::aseprite_tags(tags, frames.len())?,
+            None => Self::tags_by_name(&frames),
+        };
+
+        Ok(Self {
+            image,
+            frames,
+            tags,
+        })
+    }
+
+    fn frame(name: &str, json: &Json) -> Result<SheetFrame, Error>
+    {
+        let what = format!("frame \"{}\"", name);
+
+        if json.get("rotated").and_then(Json::as_bool) == Some(true)
+        {
+            return Err(Error::Format(format!(
+                "sprite sheet: {} is rotated, export without rotation",
+                what
+            )));
+        }
+
+        let rect = read_rect(field(json, &what, "frame")?, &what)?;
+        let trimmed = match json.get("spriteSourceSize")
+        {
+            Some(trimmed) => read_rect(trimmed, &what)?,
+            None => PackRect::new(0, 0, rect.width, rect.height),
+        };
+        let source_size = match json.get("sourceSize")
+        {
+            Some(size) => math::vec2(number(size, &what, "w")?, number(size, &what, "h")?),
+            None => math::vec2(rect.width as f32, rect.height as f32),
+        };
+        // Both formats measure from the top, the pivot is stored y up.
+        let pivot = match json.get("pivot")
+        {
+            Some(pivot) => math::vec2(number(pivot, &what, "x")?, 1.0 - number(pivot, &what, "y")?),
+            None => math::vec2(0.5, 0.5),
+        };
+        let duration = match json.get("duration")
+        {
+            Some(duration) => duration.as_f32().ok_or_else(|| missing(&what, "duration"))?,
+            None => DEFAULT_DURATION,
+        };
+
+        Ok(SheetFrame {
+            name: name.to_string(),
+            rect,
+            source_size,
+            offset: math::vec2(
+                trimmed.x as f32,
+                source_size.y - (trimmed.y + trimmed.height) as f32,
+            ),
+            pivot,
+            duration: duration / 1000.0,
+        })
+    }
+
+    fn aseprite_tags(json: &Json, count: usize) -> Result<Vec<Tag>, Error>
+    {
+        let tags = json.as_array().ok_or_else(|| missing("meta", "frameTags"))?;
+        tags.iter()
+            .map(|tag| {
+                let name = field(tag, "a frame tag", "name")?
+                    .as_str()
+                    .ok_or_else(|| missing("a frame tag", "name"))?;
+                let what = format!("tag \"{}\"", name);
+                let index = |field_name| {
+                    field(tag, &what, field_name)?
+                        .as_u32()
+                        .map(|i| i as usize)
+                        .filter(|&i| i < count)
+                        .ok_or_else(|| missing(&what, field_name))
+                };
+                let (from, to) = (index("from")?, index("to")?);
+                if from > to
+                {
+                    return Err(Error::Format(format!(
+                        "sprite sheet: {} ends at frame {} before it starts at {}",
+                        what, to, from
+                    )));
+                }
+
+                let direction = match tag.get("direction").and_then(Json::as_str)
+                {
+                    Some("reverse") => Direction::Reverse,
+                    Some("pingpong") => Direction::PingPong,
+                    Some("pingpong_reverse") => Direction::PingPongReverse,
+                    _ => Direction::Forward,
+                };
+                let mut frames: Vec<usize> = (from..=to).collect();
+                if matches!(direction, Direction::Reverse | Direction::PingPongReverse)
+                {
+                    frames.reverse();
+                }
+
+                Ok(Tag {
+                    name: name.to_string(),
+                    frames,
+                    direction,
+                })
+            })
+            .collect()
+    }
+
+    /// Groups frames whose names only differ in a trailing number.
+    fn tags_by_name(frames: &[SheetFrame]) -> Vec<Tag>
+    {
+        let mut tags: Vec<Tag> = Vec::new();
+        for (i, frame) in frames.iter().enumerate()
+        {
+            let name = match frame.name.rsplit_once('.')
+            {
+                Some((stem, _)) => stem,
+                None => &frame.name,
+            };
+            let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+            if prefix.len() == name.len()
+            {
+                continue;
+            }
+            let prefix = prefix.trim_end_matches(['_', '-', ' ', '/']);
+            if prefix.is_empty()
+            {
+                continue;
+            }
+
+            match tags.iter_mut().find(|t| t.name == prefix)
+            {
+                Some(tag) => tag.frames.push(i),
+                None => tags.push(Tag {
+                    name: prefix.to_string(),
+                    frames: vec![i],
+                    direction: Direction::Forward,
+                }),
+            }
+        }
+
+        // Exporters sort names as strings, which puts walk_10 before walk_2.
+        for tag in tags.iter_mut()
+        {
+            let number = |i: &usize| {
+                let name = &frames[*i].name;
+                let stem = name.rsplit_once('.').map_or(name.as_str(), |(stem, _)| stem);
+                let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+                stem[stem.len() - digits..].parse::<u64>().unwrap_or(0)
+            };
+            tag.frames.sort_by_key(number);
+        }
+        tags
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&Tag>
+    {
+        self.tags.iter().find(|t| t.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// TexturePacker's "JSON (Hash)" format, trimmed frames, names sorted as strings.
+    const TEXTURE_PACKER: &str = r#"{"frames": {
+"walk_01.png":
+{
+	"frame": {"x":2,"y":2,"w":20,"h":30},
+	"rotated": false,
+	"trimmed": true,
+	"spriteSourceSize": {"x":6,"y":1,"w":20,"h":30},
+	"sourceSize": {"w":32,"h":32},
+	"pivot": {"x":0.5,"y":1}
+},
+"walk_10.png":
+{
+	"frame": {"x":24,"y":2,"w":22,"h":31},
+	"rotated": false,
+	"trimmed": true,
+	"spriteSourceSize": {"x":5,"y":1,"w":22,"h":31},
+	"sourceSize": {"w":32,"h":32},
+	"pivot": {"x":0.5,"y":1}
+},
+"walk_2.png":
+{
+	"frame": {"x":48,"y":2,"w":32,"h":32},
+	"rotated": false,
+	"trimmed": false,
+	"spriteSourceSize": {"x":0,"y":0,"w":32,"h":32},
+	"sourceSize": {"w":32,"h":32},
+	"pivot": {"x":0.5,"y":1}
+}},
+"meta": {
+	"app": "https://www.codeandweb.com/texturepacker",
+	"version": "1.1",
+	"image": "hero.png",
+	"format": "RGBA8888",
+	"size": {"w":82,"h":36},
+	"scale": "1",
+	"smartupdate": "$TexturePacker:SmartUpdate:0a1b2c$"
+}
+}
+"#;
+
+    /// Aseprite's "Array" export with two tags.
+    const ASEPRITE: &str = r##"{ "frames": [
+   {
+    "filename": "slime 0.aseprite",
+    "frame": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "rotated": false,
+    "trimmed": false,
+    "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "sourceSize": { "w": 16, "h": 16 },
+    "duration": 100
+   },
+   {
+    "filename": "slime 1.aseprite",
+    "frame": { "x": 16, "y": 0, "w": 16, "h": 16 },
+    "rotated": false,
+    "trimmed": false,
+    "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "sourceSize": { "w": 16, "h": 16 },
+    "duration": 150
+   },
+   {
+    "filename": "slime 2.aseprite",
+    "frame": { "x": 32, "y": 0, "w": 16, "h": 16 },
+    "rotated": false,
+    "trimmed": false,
+    "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "sourceSize": { "w": 16, "h": 16 },
+    "duration": 100
+   }
+ ],
+ "meta": {
+  "app": "https://www.aseprite.org/",
+  "version": "1.3.2-x64",
+  "image": "slime.png",
+  "format": "RGBA8888",
+  "size": { "w": 48, "h": 16 },
+  "scale": "1",
+  "frameTags": [
+   { "name": "idle", "from": 0, "to": 1, "direction": "pingpong", "color": "#000000ff" },
+   { "name": "die", "from": 1, "to": 2, "direction": "reverse", "color": "#000000ff" }
+  ],
+  "layers": [
+   { "name": "Layer 1", "opacity": 255, "blendMode": "normal" }
+  ],
+  "slices": [
+  ]
+ }
+}
+"##;
+
+    #[test]
+    fn texture_packer_hash()
+    {
+        let sheet = Sheet::from_json(TEXTURE_PACKER).unwrap();
+        assert_eq!(sheet.image, Some(PathBuf::from("hero.png")));
+
+        let names: Vec<_> = sheet.frames.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["walk_01.png", "walk_10.png", "walk_2.png"]);
+
+        let first = &sheet.frames[0];
+        assert_eq!(first.rect, PackRect::new(2, 2, 20, 30));
+        assert_eq!(first.source_size, math::vec2(32.0, 32.0));
+        assert_eq!(first.offset, math::vec2(6.0, 1.0));
+        assert_eq!(first.pivot, math::vec2(0.5, 0.0));
+        assert_eq!(first.duration, DEFAULT_DURATION / 1000.0);
+
+        // Numbered by name, not by the order in the file.
+        assert_eq!(sheet.tags.len(), 1);
+        assert_eq!(sheet.tag("walk").unwrap().frames, [0, 2, 1]);
+    }
+
+    #[test]
+    fn aseprite_array()
+    {
+        let sheet = Sheet::from_json(ASEPRITE).unwrap();
+        assert_eq!(sheet.image, Some(PathBuf::from("slime.png")));
+        assert_eq!(sheet.frames.len(), 3);
+        assert_eq!(sheet.frames[1].name, "slime 1.aseprite");
+        assert_eq!(sheet.frames[1].rect, PackRect::new(16, 0, 16, 16));
+        assert_eq!(sheet.frames[1].offset, math::vec2(0.0, 0.0));
+        assert_eq!(sheet.frames[1].duration, 0.15);
+
+        let idle = sheet.tag("idle").unwrap();
+        assert_eq!((idle.frames.as_slice(), idle.direction), (&[0, 1][..], Direction::PingPong));
+        let die = sheet.tag("die").unwrap();
+        assert_eq!((die.frames.as_slice(), die.direction), (&[2, 1][..], Direction::Reverse));
+    }
+
+    #[test]
+    fn malformed_sheets()
+    {
+        let rotated = TEXTURE_PACKER.replacen("\"rotated\": false", "\"rotated\": true", 1);
+        let past_end = ASEPRITE.replace("\"to\": 2", "\"to\": 3");
+        let backwards = ASEPRITE.replace("\"from\": 1, \"to\": 2", "\"from\": 2, \"to\": 1");
+        let broken = ["{}", "{\"frames\": 1}", "{\"frames\": [{}]}"];
+        for text in broken.iter().copied().chain([&*rotated, &past_end, &backwards])
+        {
+            assert!(matches!(Sheet::from_json(text), Err(Error::Format(_))), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    error::Error,
+    graphics::{
+        atlas::{PackRect, Packer},
+        texture::{Filter, Texture, TextureBuilder},
+    },
+    math,
+};
+
+/// Where a named image ended up inside an `Atlas`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasRegion
+{
+    /// Index of the page texture, see `Atlas::page`.
+    pub page: usize,
+    /// The image's pixels on the page, without padding or extrusion.
+    pub rect: PackRect,
+    /// `(u0, v0, u1, v1)` of the bottom left and top right corners, as `Renderer::draw_quad` takes.
+    pub uv:   math::Vec4,
+}
+
+/// Collects images and packs them into as few textures as possible, so sprites
+/// drawn from them end up in the same batch.
+///
+/// ```ignore
+/// let atlas = AtlasBuilder::new(1024, 1024)
+///     .padding(2)
+///     .extrude(1)
+///     .add_file("res/player.png")?
+///     .add_file("res/coin.png")?
+///     .build()?;
+/// let player = Sprite::from_atlas(&atlas, "player");
+/// ```
+pub struct AtlasBuilder
+{
+    page_width:  u32,
+    page_height: u32,
+    padding:     u32,
+    extrude:     u32,
+    filter:      Filter,
+    images:      Vec<(String, image::RgbaImage)>,
+}
+
+impl AtlasBuilder
+{
+    /// Every page of the atlas will be `page_width` x `page_height` pixels.
+    pub fn new(page_width: u32, page_height: u32) -> Self
+    {
+        Self {
+            page_width,
+            page_height,
+            padding: 0,
+            extrude: 0,
+            filter: Filter::Linear,
+            images: Vec::new(),
+        }
+    }
+
+    /// Empty pixels left between neighbouring images.
+    pub fn padding(mut self, padding: u32) -> Self
+    {
+        self.padding = padding;
+        self
+    }
+
+    /// Repeats the outermost pixels of every image this many times around it,
+    /// which hides seams when regions are drawn scaled or at fractional positions.
+    pub fn extrude(mut self, extrude: u32) -> Self
+    {
+        self.extrude = extrude;
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self
+    {
+        self.filter = filter;
+        self
+    }
+
+    /// Adds an image under `name`, replacing any earlier image with that name.
+    pub fn add<S: Into<String>>(mut self, name: S, image: image::DynamicImage) -> Self
+    {
+        let name = name.into();
+        self.images.retain(|(n, _)| *n != name);
+        self.images.push((name, image.into_rgba8()));
+        self
+    }
+
+    /// Loads an image file and adds it under its file name without extension.
+    pub fn add_file<P: AsRef<Path>>(self, path: P) -> Result<Self, Error>
+    {
+        let path = path.as_ref();
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        let image = image::open(path)?;
+        Ok(self.add(name, image))
+    }
+
+    /// Lays out and composes the pages without touching GL.
+    pub fn pack(&self) -> Result<(Vec<image::RgbaImage>, HashMap<String, AtlasRegion>), Error>
+    {
+        let border = 2 * self.extrude + self.padding;
+
+        // Placing big images first packs noticeably tighter.
+        let mut order: Vec<&(String, image::RgbaImage)> = self.images.iter().collect();
+        order.sort_by(|(an, a), (bn, b)| {
+            let key = |i: &image::RgbaImage| (i.height(), i.width());
+            key(b).cmp(&key(a)).then_with(|| an.cmp(bn))
+        });
+
+        let mut packers: Vec<Packer> = Vec::new();
+        let mut pages: Vec<image::RgbaImage> = Vec::new();
+        let mut regions = HashMap::new();
+
+        for (name, image) in order
+        {
+            let (width, height) = (image.width() + border, image.height() + border);
+
+            let mut placed = packers
+                .iter_mut()
+                .enumerate()
+                .find_map(|(page, packer)| packer.insert(width, height).map(|rect| (page, rect)));
+            if placed.is_none()
+            {
+                let mut packer = Packer::new(self.page_width, self.page_height);
+                let rect = packer.insert(width, height).ok_or_else(|| {
+                    Error::AtlasImageTooLarge {
+                        name: name.clone(),
+                        width,
+                        height,
+                    }
+                })?;
+                placed = Some((packers.len(), rect));
+                packers.push(packer);
+                pages.push(image::RgbaImage::new(self.page_width, self.page_height));
+            }
+            let (page, cell) = placed.unwrap();
+
+            let rect = PackRect::new(
+                cell.x + self.extrude,
+                cell.y + self.extrude,
+                image.width(),
+                image.height(),
+            );
+            self.blit(&mut pages[page], image, &rect);
+
+            let (pw, ph) = (self.page_width as f32, self.page_height as f32);
+            let uv = math::vec4(
+                rect.x as f32 / pw,
+                1.0 - rect.bottom() as f32 / ph,
+                rect.right() as f32 / pw,
+                1.0 - rect.y as f32 / ph,
+            );
+            regions.insert(name.clone(), AtlasRegion {
+                page,
+                rect,
+                uv,
+            });
+        }
+
+        Ok((pages, regions))
+    }
+
+    /// Copies `image` to `rect` on `page`, clamping reads so the extruded border
+    /// repeats the edge pixels.
+    fn blit(&self, page: &mut image::RgbaImage, image: &image::RgbaImage, rect: &PackRect)
+    {
+        let e = self.extrude as i64;
+        let (w, h) = (image.width() as i64, image.height() as i64);
+        if w == 0 || h == 0
+        {
+            return;
+        }
+
+        for dy in -e..h + e
+        {
+            for dx in -e..w + e
+            {
+                let src = image.get_pixel(dx.clamp(0, w - 1) as u32, dy.clamp(0, h - 1) as u32);
+                page.put_pixel((rect.x as i64 + dx) as u32, (rect.y as i64 + dy) as u32, *src);
+            }
+        }
+    }
+
+    /// Packs the images and uploads one texture per page.
+    pub fn build(self) -> Result<Atlas, Error>
+    {
+        let (images, regions) = self.pack()?;
+        let pages = images
+            .into_iter()
+            .map(|page| TextureBuilder::from_image(page.into()).filter(self.filter).build())
+            .collect::<Result<_, _>>()?;
+
+        Ok(Atlas {
+            pages,
+            regions,
+        })
+    }
+}
+
+/// Images packed into one or more page textures, looked up by name.
+pub struct Atlas
+{
+    pages:   Vec<Texture>,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+impl Atlas
+{
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion>
+    {
+        self.regions.get(name)
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = (&str, &AtlasRegion)>
+    {
+        self.regions.iter().map(|(name, region)| (name.as_str(), region))
+    }
+
+    pub fn page(&self, page: usize) -> &Texture
+    {
+        &self.pages[page]
+    }
+
+    pub fn page_count(&self) -> usize
+    {
+        self.pages.len()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn image(width: u32, height: u32, shade: u8) -> image::DynamicImage
+    {
+        let image = image::RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([shade, x as u8, y as u8, 255])
+        });
+        image.into()
+    }
+
+    #[test]
+    fn keeps_padding_and_extrusion_free()
+    {
+        let (padding, extrude) = (3, 2);
+        let mut builder = AtlasBuilder::new(64, 64).padding(padding).extrude(extrude);
+        for i in 0..12
+        {
+            builder = builder.add(format!("{}", i), image(5 + i, 9 - i % 4, i as u8));
+        }
+        let (pages, regions) = builder.pack().unwrap();
+        assert_eq!(pages.len(), 1);
+
+        // The space each image takes up, as its rectangle was inserted into the packer.
+        let cell = |r: &PackRect| {
+            let border = 2 * extrude + padding;
+            PackRect::new(r.x - extrude, r.y - extrude, r.width + border, r.height + border)
+        };
+        let page = PackRect::new(0, 0, 64, 64);
+        let regions: Vec<_> = regions.values().collect();
+        for (i, a) in regions.iter().enumerate()
+        {
+            assert!(page.contains(&cell(&a.rect)));
+            for b in regions.iter().skip(i + 1)
+            {
+                assert!(!cell(&a.rect).intersects(&cell(&b.rect)), "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn extrusion_repeats_the_edges()
+    {
+        let builder = AtlasBuilder::new(32, 32).extrude(2).add("a", image(4, 3, 7));
+        let (pages, regions) = builder.pack().unwrap();
+        let rect = regions["a"].rect;
+        let page = &pages[0];
+
+        let corner = *page.get_pixel(rect.x, rect.y);
+        assert_eq!(*page.get_pixel(rect.x - 2, rect.y - 2), corner);
+        let far = *page.get_pixel(rect.right() - 1, rect.bottom() - 1);
+        assert_eq!(*page.get_pixel(rect.right() + 1, rect.bottom() + 1), far);
+        assert_eq!(*page.get_pixel(rect.x - 1, rect.y + 1), *page.get_pixel(rect.x, rect.y + 1));
+    }
+
+    #[test]
+    fn overflow_starts_a_new_page()
+    {
+        let mut builder = AtlasBuilder::new(32, 32);
+        for i in 0..5
+        {
+            builder = builder.add(format!("{}", i), image(16, 16, i));
+        }
+        let (pages, regions) = builder.pack().unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(regions.values().filter(|r| r.page == 1).count(), 1);
+    }
+
+    #[test]
+    fn too_large_is_an_error()
+    {
+        let result = AtlasBuilder::new(32, 32).padding(1).add("big", image(32, 8, 0)).pack();
+        match result
+        {
+            Err(Error::AtlasImageTooLarge {
+                name,
+                width,
+                height,
+            }) => assert_eq!((name.as_str(), width, height), ("big", 33, 9)),
+            _ => panic!("expected AtlasImageTooLarge"),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod packer;
+pub use packer::{PackRect, Packer};
+
+mod builder;
+pub use builder::{Atlas, AtlasBuilder, AtlasRegion};
@@ -0,0 +1,206 @@
+/// An axis aligned rectangle in pixels, origin at the top left like image files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PackRect
+{
+    pub x:      u32,
+    pub y:      u32,
+    pub width:  u32,
+    pub height: u32,
+}
+
+impl PackRect
+{
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self
+    {
+        Self {
+            x, y, width, height
+        }
+    }
+
+    pub fn right(&self) -> u32
+    {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> u32
+    {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, other: &PackRect) -> bool
+    {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    pub fn intersects(&self, other: &PackRect) -> bool
+    {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+}
+
+/// Packs rectangles into a fixed size area with the MaxRects algorithm,
+/// placing each one where it leaves the shortest leftover side.
+///
+/// This is only bookkeeping and needs no GL context, `AtlasBuilder` uses it to lay out pages.
+#[derive(Clone, Debug)]
+pub struct Packer
+{
+    width:  u32,
+    height: u32,
+    free:   Vec<PackRect>,
+}
+
+impl Packer
+{
+    pub fn new(width: u32, height: u32) -> Self
+    {
+        Self {
+            width, height, free: vec![PackRect::new(0, 0, width, height)]
+        }
+    }
+
+    pub fn width(&self) -> u32
+    {
+        self.width
+    }
+
+    pub fn height(&self) -> u32
+    {
+        self.height
+    }
+
+    /// Finds room for a `width` x `height` rectangle and marks it as used,
+    /// `None` if it does not fit anywhere anymore.
+    pub fn insert(&mut self, width: u32, height: u32) -> Option<PackRect>
+    {
+        if width == 0 || height == 0
+        {
+            return Some(PackRect::new(0, 0, width, height));
+        }
+
+        let placed = self
+            .free
+            .iter()
+            .filter(|f| width <= f.width && height <= f.height)
+            .min_by_key(|f| {
+                let (dw, dh) = (f.width - width, f.height - height);
+                (dw.min(dh), dw.max(dh))
+            })
+            .map(|f| PackRect::new(f.x, f.y, width, height))?;
+
+        let mut split = Vec::new();
+        self.free.retain(|f| {
+            if !f.intersects(&placed)
+            {
+                return true;
+            }
+
+            // Keep the up to four maximal pieces of `f` around the placed rectangle.
+            let (right, bottom) = (placed.right(), placed.bottom());
+            if placed.x > f.x
+            {
+                split.push(PackRect::new(f.x, f.y, placed.x - f.x, f.height));
+            }
+            if right < f.right()
+            {
+                split.push(PackRect::new(right, f.y, f.right() - right, f.height));
+            }
+            if placed.y > f.y
+            {
+                split.push(PackRect::new(f.x, f.y, f.width, placed.y - f.y));
+            }
+            if bottom < f.bottom()
+            {
+                split.push(PackRect::new(f.x, bottom, f.width, f.bottom() - bottom));
+            }
+            false
+        });
+        self.free.extend(split);
+        self.prune();
+
+        Some(placed)
+    }
+
+    /// Drops free rectangles that lie completely inside another one.
+    fn prune(&mut self)
+    {
+        let mut i = 0;
+        while i < self.free.len()
+        {
+            let rect = self.free[i];
+            let redundant = self
+                .free
+                .iter()
+                .enumerate()
+                .any(|(j, other)| j != i && other.contains(&rect) && (other != &rect || j < i));
+
+            if redundant
+            {
+                self.free.swap_remove(i);
+            }
+            else
+            {
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn packed_rects_stay_inside_and_apart()
+    {
+        let mut packer = Packer::new(128, 128);
+        let sizes = [(30, 20), (17, 40), (64, 9), (5, 5), (33, 33), (12, 50), (40, 12)];
+        let bounds = PackRect::new(0, 0, 128, 128);
+
+        let mut placed: Vec<PackRect> = Vec::new();
+        for _ in 0..4
+        {
+            for (w, h) in sizes
+            {
+                let Some(rect) = packer.insert(w, h)
+                else
+                {
+                    continue;
+                };
+                assert_eq!((rect.width, rect.height), (w, h));
+                assert!(bounds.contains(&rect), "{:?} is outside the page", rect);
+                assert!(placed.iter().all(|p| !p.intersects(&rect)), "{:?} overlaps", rect);
+                placed.push(rect);
+            }
+        }
+        assert!(placed.len() > sizes.len());
+    }
+
+    #[test]
+    fn fills_the_page_exactly()
+    {
+        let mut packer = Packer::new(64, 64);
+        for _ in 0..16
+        {
+            assert!(packer.insert(16, 16).is_some());
+        }
+        assert_eq!(packer.insert(1, 1), None);
+    }
+
+    #[test]
+    fn too_large_is_none()
+    {
+        let mut packer = Packer::new(32, 32);
+        assert_eq!(packer.insert(33, 1), None);
+        assert_eq!(packer.insert(1, 33), None);
+        // Nothing was used up by the failed attempts.
+        assert_eq!(packer.insert(32, 32), Some(PackRect::new(0, 0, 32, 32)));
+    }
+}
@@ -0,0 +1,56 @@
+/// How a drawable's colors are combined with what is already drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode
+{
+    /// Regular transparency.
+    #[default]
+    Alpha,
+    /// Adds up light, for particles, glows and the like.
+    Additive,
+    /// Darkens what is below, for shadows and tinting.
+    ///
+    /// Takes colors multiplied by their alpha, which the built-in pipelines output
+    /// in this mode. `Material` shaders have to do the same, they can declare
+    /// `uniform float u_Premultiply`, which is `1.0` while drawing with `Multiply`.
+    Multiply,
+    /// Lightens what is below, the inverse of `Multiply`.
+    Screen,
+    /// For colors already multiplied by their alpha, e.g. the texture of a `RenderTarget`
+    /// that was drawn into with `Alpha`.
+    Premultiplied,
+    /// Overwrites what is below, alpha included.
+    None,
+}
+
+impl BlendMode
+{
+    /// Makes this the GL blend state.
+    pub(crate) fn apply(self)
+    {
+        // The alpha channel is always accumulated like premultiplied alpha, which keeps
+        // the coverage of render targets right.
+        let color = match self
+        {
+            BlendMode::Alpha => (gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (gl::SRC_ALPHA, gl::ONE),
+            // `src * a * dst + dst * (1 - a)`, with `src * a` coming from the shader.
+            BlendMode::Multiply => (gl::DST_COLOR, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Screen => (gl::ONE, gl::ONE_MINUS_SRC_COLOR),
+            BlendMode::Premultiplied => (gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::None =>
+            {
+                unsafe
+                {
+                    gl::Disable(gl::BLEND);
+                }
+                return;
+            },
+        };
+
+        unsafe
+        {
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(color.0, color.1, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+}
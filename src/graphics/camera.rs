@@ -0,0 +1,163 @@
+use crate::math::{self, Mat4, Vec2};
+
+/// A 2D view into the world.
+///
+/// `position` is the world point shown at the center of the viewport, so a
+/// camera fresh from `new` shows the same thing as the default window projection.
+pub struct Camera2D
+{
+    pub position: Vec2,
+    pub zoom:     f32,
+    pub rotation: f32,
+    /// Size of the viewport in logical units, usually `Window::logical_size`.
+    pub size:     Vec2,
+
+    /// How quickly the camera catches up with its follow target, in 1/seconds.
+    /// `0.0` snaps to the target immediately.
+    pub smoothing: f32,
+    /// Half extents of the area around `position`, in world units, where the
+    /// follow target can move without the camera moving.
+    pub dead_zone: Vec2,
+
+    target: Option<Vec2>,
+    shake:  Shake,
+}
+
+#[derive(Default)]
+struct Shake
+{
+    intensity: f32,
+    duration:  f32,
+    remaining: f32,
+    time:      f32,
+}
+
+impl Shake
+{
+    fn offset(&self) -> Vec2
+    {
+        if self.remaining <= 0.0
+        {
+            return Vec2::zeros();
+        }
+
+        // Two incommensurable frequencies give a jitter that does not
+        // visibly repeat, without pulling in a random number generator.
+        let falloff = self.remaining / self.duration;
+        let t = self.time;
+        let x = (t * 71.0).sin() + 0.5 * (t * 113.0).sin();
+        let y = (t * 83.0).cos() + 0.5 * (t * 127.0).cos();
+        math::vec2(x, y) * (self.intensity * falloff / 1.5)
+    }
+}
+
+impl Camera2D
+{
+    pub fn new(width: f32, height: f32) -> Self
+    {
+        Self {
+            position: math::vec2(width / 2.0, height / 2.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            size: math::vec2(width, height),
+            smoothing: 0.0,
+            dead_zone: Vec2::zeros(),
+            target: None,
+            shake: Shake::default(),
+        }
+    }
+
+    /// Keeps `target` in view, call this every frame with the new target position.
+    pub fn follow(&mut self, target: Vec2)
+    {
+        self.target = Some(target);
+    }
+
+    pub fn stop_following(&mut self)
+    {
+        self.target = None;
+    }
+
+    /// Shakes the view by up to `intensity` world units, fading out over `duration` seconds.
+    pub fn shake(&mut self, intensity: f32, duration: f32)
+    {
+        self.shake = Shake {
+            intensity,
+            duration,
+            remaining: duration,
+            time: 0.0,
+        };
+    }
+
+    /// Advances following and shaking by `dt` seconds.
+    pub fn update(&mut self, dt: f32)
+    {
+        if let Some(target) = self.target
+        {
+            let offset = target - self.position;
+            let outside = |d: f32, zone: f32| {
+                if d > zone
+                {
+                    d - zone
+                }
+                else if d < -zone
+                {
+                    d + zone
+                }
+                else
+                {
+                    0.0
+                }
+            };
+            let wanted = math::vec2(
+                outside(offset.x, self.dead_zone.x),
+                outside(offset.y, self.dead_zone.y),
+            );
+
+            let t = if self.smoothing > 0.0 { 1.0 - (-self.smoothing * dt).exp() } else { 1.0 };
+            self.position += wanted * t;
+        }
+
+        if self.shake.remaining > 0.0
+        {
+            self.shake.remaining -= dt;
+            self.shake.time += dt;
+        }
+    }
+
+    /// The combined view and projection matrix to hand to the renderer.
+    pub fn view_projection(&self) -> Mat4
+    {
+        let half = self.size / 2.0;
+        let proj = math::ortho(-half.x, half.x, -half.y, half.y, -1.0, 1.0);
+        let center = self.position + self.shake.offset();
+
+        let mut view = math::identity();
+        view = math::rotate(&view, -self.rotation, &math::vec3(0.0, 0.0, 1.0));
+        view = math::scale(&view, &math::vec3(self.zoom, self.zoom, 1.0));
+        view = math::translate(&view, &math::vec3(-center.x, -center.y, 0.0));
+
+        proj * view
+    }
+
+    /// Converts a point on screen, origin at the bottom left as returned by
+    /// `Window::get_mouse_pos`, to world coordinates.
+    pub fn screen_to_world(&self, p: Vec2) -> Vec2
+    {
+        let ndc = math::vec4(
+            p.x / self.size.x * 2.0 - 1.0,
+            p.y / self.size.y * 2.0 - 1.0,
+            0.0,
+            1.0,
+        );
+        let inv = self.view_projection().try_inverse().unwrap_or_else(math::identity);
+        let w = inv * ndc;
+        math::vec2(w.x, w.y)
+    }
+
+    pub fn world_to_screen(&self, p: Vec2) -> Vec2
+    {
+        let ndc = self.view_projection() * math::vec4(p.x, p.y, 0.0, 1.0);
+        math::vec2((ndc.x + 1.0) / 2.0 * self.size.x, (ndc.y + 1.0) / 2.0 * self.size.y)
+    }
+}
@@ -0,0 +1,64 @@
+use crate::math::{Vec2, Vec3};
+
+/// A light for `Lighting`, positioned in world units.
+#[derive(Clone, Debug)]
+pub struct Light
+{
+    pub position:  Vec2,
+    pub color:     Vec3,
+    pub intensity: f32,
+    /// How far the light reaches.
+    pub radius:    f32,
+    /// How the light fades out towards `radius`, `1.0` is linear and higher
+    /// values fade out sooner.
+    pub falloff:   f32,
+    /// How far above the scene the light hangs, in world units. Only matters for
+    /// normal-mapped sprites, which get flatter shading from higher lights.
+    pub height:    f32,
+    /// Whether occluders block this light.
+    pub shadows:   bool,
+    /// Narrows the light down to a cone, `None` shines in every direction.
+    pub cone:      Option<Cone>,
+}
+
+/// The beam of a spot light.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cone
+{
+    /// Where the cone points, in radians counterclockwise from the x axis.
+    pub direction: f32,
+    /// Half the opening angle in radians.
+    pub angle:     f32,
+    /// Width of the fade at the edge in radians, taken from the inside of `angle`.
+    pub softness:  f32,
+}
+
+impl Light
+{
+    pub fn point(position: Vec2, radius: f32, color: Vec3) -> Self
+    {
+        Self {
+            position,
+            color,
+            intensity: 1.0,
+            radius,
+            falloff: 2.0,
+            height: radius / 4.0,
+            shadows: true,
+            cone: None,
+        }
+    }
+
+    /// A light shining towards `direction` with a cone `angle` radians wide.
+    pub fn spot(position: Vec2, radius: f32, color: Vec3, direction: f32, angle: f32) -> Self
+    {
+        Self {
+            cone: Some(Cone {
+                direction,
+                angle: angle / 2.0,
+                softness: angle / 8.0,
+            }),
+            ..Self::point(position, radius, color)
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use crate::{
+    core::renderer::{Pipeline, Renderer, Vertex},
+    error::Error,
+    graphics::{
+        blend::BlendMode,
+        lighting::{Light, Occluder},
+        material::Material,
+        render_target::RenderTarget,
+        texture::{Texture, TextureBuilder},
+    },
+    math::{self, Vec3},
+    util::{get_shader, ShaderProgram},
+};
+
+/// How occluders block the lights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shadows
+{
+    /// Light passes through occluders.
+    None,
+    /// Sharp shadow edges.
+    Hard,
+    /// Shadow edges blurred by `radius` pixels.
+    Soft
+    {
+        radius: f32
+    },
+}
+
+/// Darkness and the lights and shadows in it, see `Window::draw_lighting`.
+///
+/// The lights are added up into a light map at the window's resolution, starting
+/// from `ambient`, which is then multiplied over the scene.
+pub struct Lighting
+{
+    /// The light everything gets, and so how bright it is where no light reaches.
+    pub ambient:   Vec3,
+    pub shadows:   Shadows,
+    pub lights:    Vec<Light>,
+    pub occluders: Vec<Occluder>,
+
+    light:       Material,
+    blur:        Material,
+    copy:        Material,
+    /// Stand-ins for the shadows of lights without any, and for missing normals.
+    white:       Texture,
+    transparent: Texture,
+    buffers:     Option<Buffers>,
+    /// Whether normals were drawn since the light map was last rendered.
+    has_normals: bool,
+}
+
+struct Buffers
+{
+    light_map: RenderTarget,
+    /// Shadows of the current light, and the half blurred version of them.
+    shadow:    RenderTarget,
+    blurred:   RenderTarget,
+    normals:   RenderTarget,
+}
+
+impl Lighting
+{
+    /// Compiles the lighting shaders, so a `Window` has to exist.
+    pub fn new() -> Result<Self, Error>
+    {
+        let material = |program| Material::from_source(get_shader(program));
+        let pixel = |rgba: &[u8]| TextureBuilder::from_rgba(1, 1, rgba).build();
+
+        Ok(Self {
+            ambient: math::vec3(0.1, 0.1, 0.15),
+            shadows: Shadows::Hard,
+            lights: Vec::new(),
+            occluders: Vec::new(),
+            light: material(ShaderProgram::Light)?,
+            blur: material(ShaderProgram::Blur)?,
+            copy: material(ShaderProgram::Copy)?,
+            white: pixel(&[255; 4])?,
+            transparent: pixel(&[0; 4])?,
+            buffers: None,
+            has_normals: false,
+        })
+    }
+
+    /// Makes sure the buffers are `width` x `height` pixels.
+    fn fit(&mut self, width: i32, height: i32) -> Result<(), Error>
+    {
+        let (width, height) = (width.max(1) as u32, height.max(1) as u32);
+        let fits = self
+            .buffers
+            .as_ref()
+            .is_some_and(|b| b.light_map.width() == width && b.light_map.height() == height);
+        if fits
+        {
+            return Ok(());
+        }
+
+        self.buffers = Some(Buffers {
+            light_map: RenderTarget::new(width, height)?,
+            shadow: RenderTarget::new(width, height)?,
+            blurred: RenderTarget::new(width, height)?,
+            normals: RenderTarget::new(width, height)?,
+        });
+        self.has_normals = false;
+        Ok(())
+    }
+
+    /// Points the renderer at the normal buffer, clearing it on the first call of a frame.
+    /// `proj` is what the scene is drawn with.
+    pub(crate) fn begin_normals(
+        &mut self,
+        renderer: &mut Renderer,
+        proj: math::Mat4,
+    ) -> Result<(), Error>
+    {
+        let (_, [_, _, w, h]) = renderer.screen();
+        self.fit(w, h)?;
+        let fresh = !std::mem::replace(&mut self.has_normals, true);
+        let buffers = self.buffers.as_ref().expect("fit creates the buffers");
+
+        renderer.set_target(Some(buffers.normals.framebuffer()));
+        renderer.set_projection(proj);
+        if fresh
+        {
+            renderer.clear_with(math::vec4(0.0, 0.0, 0.0, 0.0));
+        }
+        renderer.begin_normals();
+        Ok(())
+    }
+
+    pub(crate) fn end_normals(&mut self, renderer: &mut Renderer)
+    {
+        renderer.end_normals();
+        renderer.set_target(None);
+    }
+
+    /// Renders the light map and multiplies it over the window.
+    /// `proj` is what the scene is drawn with.
+    pub(crate) fn render(&mut self, renderer: &mut Renderer, proj: math::Mat4) -> Result<(), Error>
+    {
+        let (screen, viewport) = renderer.screen();
+        self.fit(viewport[2], viewport[3])?;
+        let has_normals = std::mem::take(&mut self.has_normals);
+        let buffers = self.buffers.as_ref().expect("fit creates the buffers");
+
+        let normals = if has_normals { buffers.normals.texture() } else { &self.transparent };
+        let (width, height) = (buffers.light_map.width(), buffers.light_map.height());
+        let resolution = math::vec2(width as f32, height as f32);
+
+        renderer.set_target(Some(buffers.light_map.framebuffer()));
+        renderer.clear_with(math::vec4(self.ambient.x, self.ambient.y, self.ambient.z, 1.0));
+
+        for light in self.lights.iter()
+        {
+            let casts = light.shadows && self.shadows != Shadows::None;
+            let casts = casts && !self.occluders.is_empty();
+            let shadow =
+                if casts { self.draw_shadows(renderer, proj, light, buffers) } else { &self.white };
+
+            let mut material = self.light.clone();
+            material.set("u_Resolution", resolution);
+            material.set("u_Radius", light.radius);
+            material.set("u_Falloff", light.falloff.max(0.0));
+            material.set("u_Height", light.height);
+            material.set("u_Spot", light.cone.is_some());
+            if let Some(cone) = light.cone
+            {
+                let (sin, cos) = cone.direction.sin_cos();
+                material.set("u_Direction", math::vec2(cos, sin));
+                material.set("u_ConeOuter", cone.angle.cos());
+                material.set("u_ConeInner", (cone.angle - cone.softness).max(0.0).cos());
+            }
+            material.set_texture("u_Shadow", shadow);
+            material.set_texture("u_Normals", normals);
+
+            renderer.set_target(Some(buffers.light_map.framebuffer()));
+            renderer.set_projection(proj);
+            renderer.set_blend_mode(BlendMode::Additive);
+
+            let color = light.color * light.intensity;
+            let color = math::vec4(color.x, color.y, color.z, 1.0);
+            let corner = |x: f32, y: f32| {
+                let position = light.position + math::vec2(x, y) * light.radius;
+                Vertex::new(position, math::vec2(x, y), color)
+            };
+            let quad = [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)];
+            renderer.push_quad(Pipeline::Material(material), None, quad);
+        }
+
+        renderer.set_target(None);
+        // The light map is cleared opaque and stays that way, so it already is the
+        // premultiplied color `Multiply` takes.
+        let light_map = buffers.light_map.texture().texture.id();
+        renderer.pass(&self.copy, light_map, screen, viewport, BlendMode::Multiply);
+        Ok(())
+    }
+
+    /// Draws what the occluders hide from `light` into the shadow buffer, black on white.
+    fn draw_shadows<'a>(
+        &'a self,
+        renderer: &mut Renderer,
+        proj: math::Mat4,
+        light: &Light,
+        buffers: &'a Buffers,
+    ) -> &'a Texture
+    {
+        renderer.set_target(Some(buffers.shadow.framebuffer()));
+        renderer.set_projection(proj);
+        renderer.clear_with(math::vec4(1.0, 1.0, 1.0, 1.0));
+        renderer.set_blend_mode(BlendMode::None);
+
+        // Every side is stretched away from the light to well past its radius.
+        let far = light.radius * 2.0;
+        let away = |p: math::Vec2| {
+            let direction = (p - light.position).try_normalize(1e-6).unwrap_or_default();
+            p + direction * far
+        };
+        let black = math::vec4(0.0, 0.0, 0.0, 1.0);
+        let vertex = |p: math::Vec2| Vertex::new(p, math::vec2(0.0, 0.0), black);
+        for (a, b) in self.occluders.iter().flat_map(Occluder::edges)
+        {
+            let quad = [vertex(a), vertex(b), vertex(away(b)), vertex(away(a))];
+            renderer.push_quad(Pipeline::Quad, None, quad);
+        }
+
+        if let Shadows::Soft {
+            radius,
+        } = self.shadows
+        {
+            let (width, height) = (buffers.shadow.width(), buffers.shadow.height());
+            let full = [0, 0, width as i32, height as i32];
+            let shadow = buffers.shadow.texture().texture.id();
+            let blurred = buffers.blurred.texture().texture.id();
+
+            let mut blur = self.blur.clone();
+            blur.set("u_Direction", math::vec2(radius / width as f32, 0.0));
+            renderer.pass(&blur, shadow, buffers.blurred.framebuffer().id(), full, BlendMode::None);
+            blur.set("u_Direction", math::vec2(0.0, radius / height as f32));
+            renderer.pass(&blur, blurred, buffers.shadow.framebuffer().id(), full, BlendMode::None);
+        }
+
+        buffers.shadow.texture()
+    }
+}
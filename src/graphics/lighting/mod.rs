@@ -0,0 +1,8 @@
+mod light;
+pub use light::{Cone, Light};
+
+mod occluder;
+pub use occluder::Occluder;
+
+mod lighting;
+pub use lighting::{Lighting, Shadows};
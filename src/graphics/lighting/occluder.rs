@@ -0,0 +1,53 @@
+use crate::{
+    graphics::shape::Rect,
+    math::{self, Vec2},
+};
+
+/// A shape that blocks light and casts shadows, in world units.
+///
+/// Occluders shadow themselves too, so walls seen from above stay dark.
+#[derive(Clone, Debug)]
+pub struct Occluder
+{
+    /// Corners of the polygon, in order around it. Two points make a thin wall.
+    pub points: Vec<Vec2>,
+}
+
+impl Occluder
+{
+    pub fn polygon(points: Vec<Vec2>) -> Self
+    {
+        Self {
+            points,
+        }
+    }
+
+    pub fn rect(x: f32, y: f32, w: f32, h: f32) -> Self
+    {
+        Self::polygon(vec![
+            math::vec2(x, y),
+            math::vec2(x + w, y),
+            math::vec2(x + w, y + h),
+            math::vec2(x, y + h),
+        ])
+    }
+
+    /// Covers `rect` where it is drawn, rotation included.
+    pub fn from_rect(rect: &Rect) -> Self
+    {
+        let model = rect.entity.get_model();
+        let corner = |x: f32, y: f32| {
+            let p = model * math::vec4(x, y, 0.0, 1.0);
+            math::vec2(p.x, p.y)
+        };
+        Self::polygon(vec![corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0)])
+    }
+
+    /// Every side of the polygon as a pair of corners.
+    pub(crate) fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_
+    {
+        let next = self.points.iter().cycle().skip(1);
+        let count = if self.points.len() > 1 { self.points.len() } else { 0 };
+        self.points.iter().zip(next).take(count).map(|(a, b)| (*a, *b))
+    }
+}
@@ -0,0 +1,237 @@
+use std::{collections::HashMap, ffi::CString, rc::Rc};
+
+use crate::{
+    core::shader::Shader,
+    error::Error,
+    graphics::{preprocessor::Preprocessor, texture::Texture},
+    math,
+};
+
+/// A value that can be uploaded to a shader uniform.
+pub trait Uniform
+{
+    /// Uploads the value to `location` of the currently bound program.
+    fn set(&self, location: i32);
+}
+
+macro_rules! uniform_vec {
+    ($ty:ty, $scalar:ty, $fun:ident) => {
+        impl Uniform for $ty
+        {
+            fn set(&self, location: i32)
+            {
+                unsafe
+                {
+                    gl::$fun(location, 1, self as *const $ty as *const $scalar);
+                }
+            }
+        }
+
+        impl Uniform for [$ty]
+        {
+            fn set(&self, location: i32)
+            {
+                // nalgebra stores vectors as tightly packed scalars.
+                unsafe
+                {
+                    gl::$fun(location, self.len() as _, self.as_ptr() as *const $scalar);
+                }
+            }
+        }
+    };
+}
+
+macro_rules! uniform_mat {
+    ($ty:ty, $fun:ident) => {
+        impl Uniform for $ty
+        {
+            fn set(&self, location: i32)
+            {
+                unsafe
+                {
+                    gl::$fun(location, 1, gl::FALSE, self.as_ptr());
+                }
+            }
+        }
+
+        impl Uniform for [$ty]
+        {
+            fn set(&self, location: i32)
+            {
+                unsafe
+                {
+                    gl::$fun(location, self.len() as _, gl::FALSE, self.as_ptr() as *const f32);
+                }
+            }
+        }
+    };
+}
+
+uniform_vec!(f32, f32, Uniform1fv);
+uniform_vec!(i32, i32, Uniform1iv);
+uniform_vec!(u32, u32, Uniform1uiv);
+uniform_vec!(math::Vec2, f32, Uniform2fv);
+uniform_vec!(math::Vec3, f32, Uniform3fv);
+uniform_vec!(math::Vec4, f32, Uniform4fv);
+uniform_vec!(math::IVec2, i32, Uniform2iv);
+uniform_vec!(math::IVec3, i32, Uniform3iv);
+uniform_vec!(math::IVec4, i32, Uniform4iv);
+uniform_mat!(math::Mat2, UniformMatrix2fv);
+uniform_mat!(math::Mat3, UniformMatrix3fv);
+uniform_mat!(math::Mat4, UniformMatrix4fv);
+
+impl Uniform for bool
+{
+    fn set(&self, location: i32)
+    {
+        (*self as i32).set(location);
+    }
+}
+
+impl<T> Uniform for Vec<T>
+where
+    [T]: Uniform,
+{
+    fn set(&self, location: i32)
+    {
+        self.as_slice().set(location);
+    }
+}
+
+impl<T, const N: usize> Uniform for [T; N]
+where
+    [T]: Uniform,
+{
+    fn set(&self, location: i32)
+    {
+        self.as_slice().set(location);
+    }
+}
+
+type Uniforms = HashMap<CString, Rc<dyn Uniform>>;
+type Textures = Vec<(CString, Texture)>;
+
+/// A user shader together with the uniform values it is drawn with.
+///
+/// The source uses the same `#shader vertex` / `#shader fragment` format as the
+/// built-in shaders, with an optional `#shader geometry` section, and goes through
+/// a `Preprocessor`. Quads are fed in with the vertex attributes
+/// `a_Position` (vec2, location 0), `a_TexCoord` (vec2, location 1) and
+/// `a_Color` (vec4, location 2), and the renderer sets `u_Projection` (mat4)
+/// and `u_Texture` (sampler2D, unit 0). The built-in vertex shader doing exactly that
+/// can be pulled in with `#include "batch.vertex.glsl"`.
+///
+/// Further textures can be bound with `set_texture`.
+///
+/// Cloning is cheap and shares the compiled program. Uniforms set on a clone
+/// only affect that clone.
+#[derive(Clone)]
+pub struct Material
+{
+    pub(crate) shader:   Rc<Shader>,
+    pub(crate) uniforms: Rc<Uniforms>,
+    pub(crate) textures: Rc<Textures>,
+}
+
+impl Material
+{
+    /// Loads the shader from a file.
+    ///
+    /// With the `hot-reload` feature the file and the files it includes are watched,
+    /// and the material is recompiled in place whenever one changes,
+    /// see `Window::poll_events`.
+    pub fn new<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error>
+    {
+        Self::new_with(path, Preprocessor::default())
+    }
+
+    /// Like `new`, running the source through `preprocessor` first.
+    pub fn new_with<P: AsRef<std::path::Path>>(
+        path: P,
+        preprocessor: Preprocessor,
+    ) -> Result<Self, Error>
+    {
+        let material = Self::with_shader(Shader::new_with(path, preprocessor)?);
+
+        #[cfg(feature = "hot-reload")]
+        crate::core::hot_reload::watch(&material.shader);
+
+        Ok(material)
+    }
+
+    pub fn from_source(source: &str) -> Result<Self, Error>
+    {
+        Self::from_source_with(source, Preprocessor::default())
+    }
+
+    /// Like `from_source`, running the source through `preprocessor` first.
+    pub fn from_source_with(source: &str, preprocessor: Preprocessor) -> Result<Self, Error>
+    {
+        Ok(Self::with_shader(Shader::from_shader_string_with(source, preprocessor)?))
+    }
+
+    fn with_shader(shader: Shader) -> Self
+    {
+        Self {
+            shader: Rc::new(shader), uniforms: Rc::default(), textures: Rc::default()
+        }
+    }
+
+    /// Sets `name` to `value` for everything drawn with this material from now on.
+    pub fn set<U: Uniform + 'static>(&mut self, name: &str, value: U)
+    {
+        let name = CString::new(name).expect("uniform names cannot contain nul bytes");
+
+        // Draws still waiting in the batch hold on to the old map and keep their values.
+        Rc::make_mut(&mut self.uniforms).insert(name, Rc::new(value));
+    }
+
+    /// Binds `texture` to the sampler uniform `name` for everything drawn with this
+    /// material from now on. Texture unit 0 stays reserved for `u_Texture`.
+    pub fn set_texture(&mut self, name: &str, texture: &Texture)
+    {
+        let name = CString::new(name).expect("uniform names cannot contain nul bytes");
+
+        let textures = Rc::make_mut(&mut self.textures);
+        match textures.iter_mut().find(|(n, _)| *n == name)
+        {
+            Some((_, t)) => *t = texture.clone(),
+            None => textures.push((name, texture.clone())),
+        }
+    }
+
+    /// Recompiles the shader from the file it was loaded from, keeping the
+    /// previous program if that fails. Does nothing for materials made with `from_source`.
+    pub fn reload(&self) -> Result<(), Error>
+    {
+        self.shader.reload()
+    }
+
+    /// Binds the program and uploads the stored uniforms.
+    pub(crate) fn apply(&self)
+    {
+        self.shader.bind();
+        for (name, value) in self.uniforms.iter()
+        {
+            self.shader.set_uniform(name, value.as_ref());
+        }
+        for (unit, (name, texture)) in self.textures.iter().enumerate()
+        {
+            let unit = unit as u32 + 1;
+            texture.texture.bind(Some(unit));
+            self.shader.set_uniform(name, &(unit as i32));
+        }
+    }
+}
+
+impl PartialEq for Material
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        Rc::ptr_eq(&self.shader, &other.shader)
+            && Rc::ptr_eq(&self.uniforms, &other.uniforms)
+            && Rc::ptr_eq(&self.textures, &other.textures)
+    }
+}
+
+impl Eq for Material {}
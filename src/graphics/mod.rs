@@ -1,6 +1,18 @@
+pub mod animation;
+pub mod atlas;
+pub mod blend;
+pub mod camera;
 pub mod drawable;
 pub mod entity;
+pub mod lighting;
+pub mod material;
+pub mod nine_slice;
+pub mod particles;
+pub mod post;
+pub mod preprocessor;
+pub mod render_target;
 pub mod shape;
 pub mod sprite;
 pub mod text;
 pub mod texture;
+pub mod tilemap;
@@ -0,0 +1,247 @@
+use crate::{
+    core::renderer::{Pipeline, Renderer, Vertex},
+    graphics::{
+        atlas::Atlas, blend::BlendMode, drawable::Drawable, entity::Entity, material::Material,
+        texture,
+    },
+    math,
+};
+
+/// Widths of the borders of a `NineSlice`, in pixels of its image.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Insets
+{
+    pub left:   f32,
+    pub right:  f32,
+    pub bottom: f32,
+    pub top:    f32,
+}
+
+impl Insets
+{
+    pub fn new(left: f32, right: f32, bottom: f32, top: f32) -> Self
+    {
+        Self {
+            left, right, bottom, top
+        }
+    }
+
+    /// The same width on all four sides.
+    pub fn uniform(width: f32) -> Self
+    {
+        Self::new(width, width, width, width)
+    }
+
+    /// Keeps the borders inside an image of `size`, shrinking them together where
+    /// they overlap.
+    fn fit(self, size: math::Vec2) -> Self
+    {
+        let fit = |near: f32, far: f32, size: f32| {
+            let (near, far) = (near.max(0.0), far.max(0.0));
+            if near + far <= size
+            {
+                return (near, far);
+            }
+            let shrink = size / (near + far);
+            (near * shrink, far * shrink)
+        };
+        let (left, right) = fit(self.left, self.right, size.x);
+        let (bottom, top) = fit(self.bottom, self.top, size.y);
+        Self::new(left, right, bottom, top)
+    }
+}
+
+/// How the edges and the center of a `NineSlice` fill the space between the corners.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SliceMode
+{
+    #[default]
+    Stretch,
+    /// Repeated at their size in the image, the last copy cut off where it runs over.
+    Tile,
+}
+
+/// An image cut into a 3x3 grid, for panels and buttons of any size.
+///
+/// `entity.size` times `entity.scale` is the size drawn. The corners keep their size in
+/// the image times `border_scale`, the edges and the center take up the rest. When the
+/// borders do not fit they are shrunk together.
+pub struct NineSlice
+{
+    color:    math::Vec4,
+    material: Option<Material>,
+    blend:    BlendMode,
+    texture:  texture::Texture,
+    /// The part of the texture the image is in, as `(u0, v0, u1, v1)`.
+    region:   math::Vec4,
+    /// Size of the image in pixels.
+    source:   math::Vec2,
+    insets:   Insets,
+
+    pub entity:       Entity,
+    pub mode:         SliceMode,
+    /// How large a pixel of the image is drawn in the borders, `1.0` by default.
+    pub border_scale: f32,
+}
+
+impl NineSlice
+{
+    /// The nine slice keeps its own handle to `texture`, see `Texture`.
+    pub fn new(texture: &texture::Texture, insets: Insets) -> Self
+    {
+        let core = texture.get_core();
+        let size = math::vec2(core.width as f32, core.height as f32);
+
+        Self::with_region(texture, math::vec4(0.0, 0.0, 1.0, 1.0), size, insets)
+    }
+
+    /// A nine slice of the image packed into `atlas` under `name`.
+    pub fn from_atlas(atlas: &Atlas, name: &str, insets: Insets) -> Option<Self>
+    {
+        let region = atlas.region(name)?;
+        let size = math::vec2(region.rect.width as f32, region.rect.height as f32);
+        Some(Self::with_region(atlas.page(region.page), region.uv, size, insets))
+    }
+
+    fn with_region(
+        texture: &texture::Texture,
+        region: math::Vec4,
+        source: math::Vec2,
+        insets: Insets,
+    ) -> Self
+    {
+        Self {
+            color: math::vec4(1.0, 1.0, 1.0, 1.0),
+            material: None,
+            blend: BlendMode::Alpha,
+            texture: texture.clone(),
+            region,
+            source,
+            insets: insets.fit(source),
+            entity: Entity::new(source, math::vec2(0.0, 0.0), 0.0),
+            mode: SliceMode::Stretch,
+            border_scale: 1.0,
+        }
+    }
+
+    pub fn insets(&self) -> Insets
+    {
+        self.insets
+    }
+
+    /// Borders wider than the image are shrunk to fit it.
+    pub fn set_insets(&mut self, insets: Insets)
+    {
+        self.insets = insets.fit(self.source);
+    }
+
+    /// Multiplied with the image's colors, white by default.
+    pub fn set_color(&mut self, color: math::Vec4)
+    {
+        self.color = color;
+    }
+
+    /// Draws with a user shader instead of the default one, `None` goes back to the default.
+    /// The texture is bound as `u_Texture`.
+    pub fn set_material(&mut self, material: Option<Material>)
+    {
+        self.material = material;
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode)
+    {
+        self.blend = blend;
+    }
+
+    /// The three spans of one axis as `(from, to, uv from, uv to)`, `from` and `to` in
+    /// world units from the start of the drawn size, the middle one cut into tiles if
+    /// the mode says so.
+    fn spans(&self, size: f32, source: f32, (near, far): (f32, f32), (uv0, uv1): (f32, f32))
+        -> Vec<(f32, f32, f32, f32)>
+    {
+        let (mut a, mut b) = (near * self.border_scale, far * self.border_scale);
+        if a + b > size
+        {
+            let shrink = size / (a + b);
+            a *= shrink;
+            b *= shrink;
+        }
+        let uv = |pixels: f32| uv0 + (uv1 - uv0) * pixels / source;
+        let (middle0, middle1) = (uv(near), uv(source - far));
+
+        let mut spans = vec![(0.0, a, uv0, middle0)];
+        let tile = (source - near - far) * self.border_scale;
+        if self.mode == SliceMode::Tile && tile > 0.0
+        {
+            let mut from = a;
+            while from < size - b
+            {
+                let to = (from + tile).min(size - b);
+                let cut = middle0 + (middle1 - middle0) * (to - from) / tile;
+                spans.push((from, to, middle0, cut));
+                from = to;
+            }
+        }
+        else
+        {
+            spans.push((a, size - b, middle0, middle1));
+        }
+        spans.push((size - b, size, middle1, uv1));
+        spans
+    }
+}
+
+impl Drawable for NineSlice
+{
+    fn draw(&self, renderer: &mut Renderer)
+    {
+        let size = self.entity.size.component_mul(&self.entity.scale);
+        if size.x <= 0.0 || size.y <= 0.0
+        {
+            return;
+        }
+
+        let [u0, v0, u1, v1] = self.region.into();
+        let insets = self.insets;
+        let columns = self.spans(size.x, self.source.x, (insets.left, insets.right), (u0, u1));
+        let rows = self.spans(size.y, self.source.y, (insets.bottom, insets.top), (v0, v1));
+
+        // The model takes the unit square to the drawn size, rotation included.
+        let model = self.entity.get_model();
+        let corner = |x: f32, y: f32, u: f32, v: f32| {
+            let p = model * math::vec4(x / size.x, y / size.y, 0.0, 1.0);
+            Vertex::new(math::vec2(p.x, p.y), math::vec2(u, v), self.color)
+        };
+
+        let pipeline = self.material.clone().map_or(Pipeline::Quad, Pipeline::Material);
+        renderer.set_blend_mode(self.blend);
+        let texture = Some(&self.texture);
+        for &(y0, y1, tv0, tv1) in rows.iter().filter(|(y0, y1, ..)| y1 > y0)
+        {
+            for &(x0, x1, tu0, tu1) in columns.iter().filter(|(x0, x1, ..)| x1 > x0)
+            {
+                renderer.push_quad(pipeline.clone(), texture, [
+                    corner(x0, y0, tu0, tv0),
+                    corner(x1, y0, tu1, tv0),
+                    corner(x1, y1, tu1, tv1),
+                    corner(x0, y1, tu0, tv1),
+                ]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn insets_fit_the_image()
+    {
+        let size = math::vec2(12.0, 8.0);
+        assert_eq!(Insets::new(4.0, 4.0, 2.0, 3.0).fit(size), Insets::new(4.0, 4.0, 2.0, 3.0));
+        assert_eq!(Insets::new(9.0, 3.0, 8.0, 8.0).fit(size), Insets::new(9.0, 3.0, 4.0, 4.0));
+        assert_eq!(Insets::new(-1.0, 30.0, 0.0, 0.0).fit(size), Insets::new(0.0, 12.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,55 @@
+use std::ops::{Add, Mul};
+
+/// A value changing over a particle's life, from `0.0` at its birth to `1.0` at its death.
+///
+/// The value is interpolated linearly between keys and held before the first
+/// and after the last one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve<T>
+{
+    keys: Vec<(f32, T)>,
+}
+
+impl<T> Curve<T>
+where
+    T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+{
+    pub fn constant(value: T) -> Self
+    {
+        Self {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    /// Goes from `from` at birth to `to` at death.
+    pub fn linear(from: T, to: T) -> Self
+    {
+        Self {
+            keys: vec![(0.0, from), (1.0, to)],
+        }
+    }
+
+    /// Adds a key at `t`, keys may be added in any order.
+    pub fn key(mut self, t: f32, value: T) -> Self
+    {
+        let at = self.keys.partition_point(|(k, _)| *k <= t);
+        self.keys.insert(at, (t, value));
+        self
+    }
+
+    pub fn sample(&self, t: f32) -> T
+    {
+        let at = self.keys.partition_point(|(k, _)| *k <= t);
+        match (at.checked_sub(1).map(|i| self.keys[i]), self.keys.get(at))
+        {
+            (Some((t0, a)), Some(&(t1, b))) =>
+            {
+                let f = (t - t0) / (t1 - t0);
+                a * (1.0 - f) + b * f
+            },
+            (Some((_, a)), None) => a,
+            (None, Some(&(_, b))) => b,
+            (None, None) => unreachable!("curves always have a key"),
+        }
+    }
+}
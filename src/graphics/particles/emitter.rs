@@ -0,0 +1,88 @@
+use crate::{
+    graphics::particles::Curve,
+    math::{self, Vec2, Vec4},
+};
+
+/// The area new particles appear in, relative to `Emitter::position`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmitterShape
+{
+    Point,
+    /// Anywhere on the line from the position to the position plus `to`.
+    Line
+    {
+        to: Vec2
+    },
+    /// Anywhere inside the circle around the position.
+    Circle
+    {
+        radius: f32
+    },
+    /// Anywhere inside the rectangle centered on the position.
+    Rect
+    {
+        size: Vec2
+    },
+}
+
+/// Where and how a `ParticleSystem` spawns particles, and how they behave afterwards.
+///
+/// Ranges are `(min, max)`, every particle picks its own value in between.
+/// ```ignore
+/// let sparks = Emitter {
+///     rate: 40.0,
+///     speed: (80.0, 160.0),
+///     gravity: vec2(0.0, -300.0),
+///     ..Emitter::default()
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct Emitter
+{
+    pub position:  Vec2,
+    pub shape:     EmitterShape,
+    /// Particles per second, `0.0` only spawns them with `ParticleSystem::burst`.
+    pub rate:      f32,
+    /// Seconds a particle lives.
+    pub lifetime:  (f32, f32),
+    /// Units per second a particle starts out with.
+    pub speed:     (f32, f32),
+    /// Where particles head, in radians counterclockwise from the x axis.
+    pub direction: f32,
+    /// How far particles may head off `direction` either way, `PI` goes all around.
+    pub spread:    f32,
+    /// Acceleration in units per second squared.
+    pub gravity:   Vec2,
+    /// How quickly particles slow down, in 1/seconds.
+    pub drag:      f32,
+    /// Radians per second a particle turns by.
+    pub spin:      (f32, f32),
+    pub color:     Curve<Vec4>,
+    /// Width and height of a particle in units.
+    pub size:      Curve<f32>,
+    /// Plays the frames of the system over a particle's life instead of
+    /// giving every particle a random one.
+    pub animate:   bool,
+}
+
+impl Default for Emitter
+{
+    fn default() -> Self
+    {
+        Self {
+            position: math::vec2(0.0, 0.0),
+            shape: EmitterShape::Point,
+            rate: 0.0,
+            lifetime: (1.0, 1.0),
+            speed: (50.0, 100.0),
+            direction: std::f32::consts::FRAC_PI_2,
+            spread: std::f32::consts::PI,
+            gravity: math::vec2(0.0, 0.0),
+            drag: 0.0,
+            spin: (0.0, 0.0),
+            color: Curve::linear(math::vec4(1.0, 1.0, 1.0, 1.0), math::vec4(1.0, 1.0, 1.0, 0.0)),
+            size: Curve::constant(8.0),
+            animate: false,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+mod curve;
+pub use curve::Curve;
+
+mod emitter;
+pub use emitter::{Emitter, EmitterShape};
+
+mod system;
+pub use system::ParticleSystem;
@@ -0,0 +1,332 @@
+use std::hash::{BuildHasher, Hasher};
+
+use crate::{
+    core::renderer::{Pipeline, Renderer},
+    graphics::{
+        animation::Sheet,
+        blend::BlendMode,
+        material::Material,
+        particles::{Emitter, EmitterShape},
+        texture,
+    },
+    math::{self, Vec2, Vec4},
+};
+
+struct Particle
+{
+    pos:      Vec2,
+    vel:      Vec2,
+    age:      f32,
+    lifetime: f32,
+    rotation: f32,
+    spin:     f32,
+    frame:    usize,
+    emitter:  usize,
+}
+
+/// Xorshift, particles only need numbers that look random.
+struct Rng(u64);
+
+impl Rng
+{
+    fn next(&mut self) -> f32
+    {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, (min, max): (f32, f32)) -> f32
+    {
+        min + (max - min) * self.next()
+    }
+}
+
+/// Lots of small, short lived quads spawned by `Emitter`s. All of them go into a single
+/// batch, so a whole system usually takes one draw call.
+///
+/// Without a texture particles are round dots. Particle positions are in the same
+/// units as the emitters, so moving an emitter leaves its particles where they are.
+pub struct ParticleSystem
+{
+    pub emitters:      Vec<Emitter>,
+    /// Spawning stops while this many particles are alive.
+    pub max_particles: usize,
+
+    particles: Vec<Particle>,
+    /// Particles owed by each emitter's rate, carried over between updates.
+    owed:      Vec<f32>,
+    rng:       Rng,
+
+    texture:  Option<texture::Texture>,
+    /// `(u0, v0, u1, v1)` of every frame.
+    frames:   Vec<Vec4>,
+    material: Option<Material>,
+    blend:    BlendMode,
+}
+
+impl Default for ParticleSystem
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl ParticleSystem
+{
+    pub fn new() -> Self
+    {
+        // A fresh `RandomState` is seeded randomly by std.
+        let seed = std::collections::hash_map::RandomState::new().build_hasher().finish();
+
+        Self {
+            emitters: Vec::new(),
+            max_particles: 10_000,
+            particles: Vec::new(),
+            owed: Vec::new(),
+            rng: Rng(seed | 1),
+            texture: None,
+            frames: vec![math::vec4(0.0, 0.0, 1.0, 1.0)],
+            material: None,
+            blend: BlendMode::Alpha,
+        }
+    }
+
+    pub fn with(mut self, emitter: Emitter) -> Self
+    {
+        self.emitters.push(emitter);
+        self
+    }
+
+    /// Makes the particles repeatable, the same seed and updates give the same particles.
+    pub fn set_seed(&mut self, seed: u64)
+    {
+        self.rng = Rng(seed | 1);
+    }
+
+    /// Draws every particle with the whole of `texture`, `None` goes back to round dots.
+    pub fn set_texture(&mut self, texture: Option<&texture::Texture>)
+    {
+        self.texture = texture.cloned();
+        self.frames = vec![math::vec4(0.0, 0.0, 1.0, 1.0)];
+    }
+
+    /// Cuts `texture` into a grid of frames, row by row from the top left.
+    pub fn set_grid(&mut self, texture: &texture::Texture, cols: u32, rows: u32)
+    {
+        let (w, h) = (1.0 / cols.max(1) as f32, 1.0 / rows.max(1) as f32);
+        self.texture = Some(texture.clone());
+        self.frames = (0..rows.max(1))
+            .flat_map(|row| (0..cols.max(1)).map(move |col| (col, row)))
+            .map(|(col, row)| {
+                let (x, y) = (col as f32 * w, 1.0 - (row + 1) as f32 * h);
+                math::vec4(x, y, x + w, y + h)
+            })
+            .collect();
+    }
+
+    /// Uses the frames of `tag` in `sheet`, or all of them for `None`, with `texture`
+    /// being the sheet's image. Returns `false` if the tag does not exist.
+    pub fn set_sheet(
+        &mut self,
+        texture: &texture::Texture,
+        sheet: &Sheet,
+        tag: Option<&str>,
+    ) -> bool
+    {
+        let indices: Vec<usize> = match tag
+        {
+            Some(name) => match sheet.tag(name)
+            {
+                Some(tag) => tag.frames.clone(),
+                None => return false,
+            },
+            None => (0..sheet.frames.len()).collect(),
+        };
+
+        let (tw, th) = (texture.width() as f32, texture.height() as f32);
+        let frames: Vec<Vec4> = indices
+            .iter()
+            .filter_map(|i| sheet.frames.get(*i))
+            .map(|frame| {
+                let rect = frame.rect;
+                math::vec4(
+                    rect.x as f32 / tw,
+                    1.0 - rect.bottom() as f32 / th,
+                    rect.right() as f32 / tw,
+                    1.0 - rect.y as f32 / th,
+                )
+            })
+            .collect();
+        if frames.is_empty()
+        {
+            return false;
+        }
+
+        self.texture = Some(texture.clone());
+        self.frames = frames;
+        true
+    }
+
+    /// Draws with a user shader instead of the default one, `None` goes back to the default.
+    pub fn set_material(&mut self, material: Option<Material>)
+    {
+        self.material = material;
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode)
+    {
+        self.blend = blend;
+    }
+
+    /// Spawns `count` particles from the emitter at `emitter` right away.
+    pub fn burst(&mut self, emitter: usize, count: usize)
+    {
+        for _ in 0..count
+        {
+            self.spawn(emitter);
+        }
+    }
+
+    /// Number of particles alive.
+    pub fn len(&self) -> usize
+    {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.particles.is_empty()
+    }
+
+    /// Removes every particle.
+    pub fn clear(&mut self)
+    {
+        self.particles.clear();
+    }
+
+    fn spawn(&mut self, index: usize)
+    {
+        let Some(emitter) = self.emitters.get(index)
+        else
+        {
+            return;
+        };
+        if self.particles.len() >= self.max_particles
+        {
+            return;
+        }
+
+        let rng = &mut self.rng;
+        let offset = match emitter.shape
+        {
+            EmitterShape::Point => math::vec2(0.0, 0.0),
+            EmitterShape::Line {
+                to,
+            } => to * rng.next(),
+            EmitterShape::Circle {
+                radius,
+            } =>
+            {
+                // The square root spreads particles evenly instead of bunching them up
+                // in the middle.
+                let r = radius * rng.next().sqrt();
+                let (sin, cos) = (rng.next() * std::f32::consts::TAU).sin_cos();
+                math::vec2(cos, sin) * r
+            },
+            EmitterShape::Rect {
+                size,
+            } => math::vec2(rng.next() - 0.5, rng.next() - 0.5).component_mul(&size),
+        };
+
+        let angle = emitter.direction + rng.range((-emitter.spread, emitter.spread));
+        let (sin, cos) = angle.sin_cos();
+        let count = self.frames.len();
+        let frame = if emitter.animate { 0 } else { (rng.next() * count as f32) as usize };
+
+        self.particles.push(Particle {
+            pos: emitter.position + offset,
+            vel: math::vec2(cos, sin) * rng.range(emitter.speed),
+            age: 0.0,
+            lifetime: rng.range(emitter.lifetime).max(f32::EPSILON),
+            rotation: 0.0,
+            spin: rng.range(emitter.spin),
+            frame: frame.min(count - 1),
+            emitter: index,
+        });
+    }
+
+    /// Spawns particles by the emitters' rates and moves the living ones on by `dt` seconds.
+    pub fn update(&mut self, dt: f32)
+    {
+        let emitters = &self.emitters;
+        self.particles.retain_mut(|p| {
+            p.age += dt;
+            let Some(emitter) = emitters.get(p.emitter)
+            else
+            {
+                return false;
+            };
+            if p.age >= p.lifetime
+            {
+                return false;
+            }
+
+            p.vel += emitter.gravity * dt;
+            p.vel *= (-emitter.drag * dt).exp();
+            p.pos += p.vel * dt;
+            p.rotation += p.spin * dt;
+            true
+        });
+
+        self.owed.resize(self.emitters.len(), 0.0);
+        for index in 0..self.emitters.len()
+        {
+            self.owed[index] += self.emitters[index].rate.max(0.0) * dt;
+            let count = self.owed[index].floor();
+            self.owed[index] -= count;
+            self.burst(index, count as usize);
+        }
+    }
+}
+
+use crate::graphics::drawable::Drawable;
+impl Drawable for ParticleSystem
+{
+    fn draw(&self, renderer: &mut Renderer)
+    {
+        let (pipeline, texture) = match (&self.material, &self.texture)
+        {
+            (Some(material), texture) => (Pipeline::Material(material.clone()), texture.as_ref()),
+            (None, Some(texture)) => (Pipeline::Quad, Some(texture)),
+            (None, None) => (Pipeline::Circle, None),
+        };
+        renderer.set_blend_mode(self.blend);
+
+        for p in self.particles.iter()
+        {
+            let Some(emitter) = self.emitters.get(p.emitter)
+            else
+            {
+                continue;
+            };
+
+            let t = p.age / p.lifetime;
+            let size = emitter.size.sample(t);
+            let count = self.frames.len();
+            let frame = if emitter.animate { (t * count as f32) as usize } else { p.frame };
+            let frame = frame.min(count - 1);
+
+            let mut model = math::identity();
+            model = math::translate(&model, &math::vec3(p.pos.x, p.pos.y, 0.0));
+            model = math::rotate(&model, p.rotation, &math::vec3(0.0, 0.0, 1.0));
+            model = math::scale(&model, &math::vec3(size, size, 1.0));
+            model = math::translate(&model, &math::vec3(-0.5, -0.5, 0.0));
+
+            let color = emitter.color.sample(t);
+            renderer.draw_model(pipeline.clone(), &model, self.frames[frame], color, texture);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use crate::{
+    core::renderer::Renderer,
+    error::Error,
+    graphics::{
+        blend::BlendMode, material::Material, render_target::RenderTarget, texture::Texture,
+    },
+    math,
+    util::{get_shader, ShaderProgram},
+};
+
+/// A full-screen effect, see `PostChain`.
+#[derive(Clone)]
+pub enum Effect
+{
+    /// Gaussian blur, `radius` in pixels.
+    Blur
+    {
+        radius: f32
+    },
+    /// Makes everything brighter than `threshold`, from `0.0` to `1.0`, glow.
+    Bloom
+    {
+        threshold: f32,
+        intensity: f32,
+        radius:    f32,
+    },
+    /// The curved glass and scanlines of an old monitor, both from `0.0` for none.
+    Crt
+    {
+        curvature: f32, scanlines: f32
+    },
+    /// Darkens the picture outside of `radius`, where `1.0` reaches the corners,
+    /// over a band `softness` wide.
+    Vignette
+    {
+        radius:   f32,
+        softness: f32,
+        strength: f32,
+    },
+    /// Remaps colors through a lookup table, mixed in by `strength`.
+    ///
+    /// `lut` is a strip of N tiles of N x N pixels, e.g. 256 x 16, with red growing
+    /// to the right within a tile, green growing downwards and blue from tile to tile.
+    ColorGrade
+    {
+        lut: Texture, strength: f32
+    },
+    /// Blocks of `size` x `size` pixels.
+    Pixelate
+    {
+        size: f32
+    },
+    /// A user shader in tori's `.shader` format, see `Material`. It gets the result of
+    /// the previous effects as `u_Texture` and its size in pixels as `u_Resolution` (vec2).
+    Custom(Material),
+}
+
+/// Effects applied one after another to everything drawn to a window, see
+/// `Window::set_post_chain`, or to a `RenderTarget` with `Window::post_process`,
+/// e.g. to only process what one camera sees.
+pub struct PostChain
+{
+    pub effects: Vec<Effect>,
+    materials:   HashMap<ShaderProgram, Material>,
+    /// Ping-pong buffers, the size of the last source.
+    buffers:     Vec<RenderTarget>,
+}
+
+impl PostChain
+{
+    /// Compiles the built-in effects, so a `Window` has to exist.
+    pub fn new() -> Result<Self, Error>
+    {
+        use ShaderProgram as SP;
+        let programs = [
+            SP::Copy,
+            SP::Blur,
+            SP::Bright,
+            SP::Bloom,
+            SP::Crt,
+            SP::Vignette,
+            SP::Lut,
+            SP::Pixelate,
+        ];
+
+        let mut materials = HashMap::new();
+        for program in programs
+        {
+            materials.insert(program, Material::from_source(get_shader(program))?);
+        }
+
+        Ok(Self {
+            effects: Vec::new(),
+            materials,
+            buffers: Vec::new(),
+        })
+    }
+
+    pub fn with(mut self, effect: Effect) -> Self
+    {
+        self.effects.push(effect);
+        self
+    }
+
+    fn material(&self, program: ShaderProgram) -> Material
+    {
+        self.materials[&program].clone()
+    }
+
+    /// Runs the effects on `source` and draws the result over `viewport` of `fbo`.
+    pub(crate) fn run(
+        &mut self,
+        renderer: &mut Renderer,
+        source: &Texture,
+        fbo: u32,
+        viewport: [i32; 4],
+        blend: BlendMode,
+    ) -> Result<(), Error>
+    {
+        let (width, height) = (source.width(), source.height());
+        let fits = self.buffers.first().is_some_and(|b| b.width() == width && b.height() == height);
+        if !fits
+        {
+            self.buffers = (0..3)
+                .map(|_| RenderTarget::new(width, height))
+                .collect::<Result<_, _>>()?;
+        }
+
+        let resolution = math::vec2(width as f32, height as f32);
+        let texel = math::vec2(1.0 / resolution.x, 1.0 / resolution.y);
+        let full = [0, 0, width as i32, height as i32];
+        let buffers = &self.buffers;
+
+        // `None` is `source`, otherwise an index into `buffers`.
+        let id = |buffer: Option<usize>| match buffer
+        {
+            Some(i) => buffers[i].texture().texture.id(),
+            None => source.texture.id(),
+        };
+        let free = |taken: &[Option<usize>]| (0..3).find(|i| !taken.contains(&Some(*i))).unwrap();
+        let draw = |renderer: &mut Renderer, material: &Material, from: Option<usize>, to: usize| {
+            let fbo = buffers[to].framebuffer().id();
+            renderer.pass(material, id(from), fbo, full, BlendMode::None);
+        };
+        // Two passes, leaving `taken` alone. Returns the buffer holding the result.
+        let blur = |renderer: &mut Renderer,
+                    from: Option<usize>,
+                    radius: f32,
+                    taken: &[Option<usize>]| {
+            let mut material = self.material(ShaderProgram::Blur);
+            let a = free(&[&[from], taken].concat());
+            material.set("u_Direction", math::vec2(texel.x * radius, 0.0));
+            draw(renderer, &material, from, a);
+
+            let b = free(&[&[Some(a)], taken].concat());
+            material.set("u_Direction", math::vec2(0.0, texel.y * radius));
+            draw(renderer, &material, Some(a), b);
+            b
+        };
+
+        let mut current = None;
+        for effect in self.effects.iter()
+        {
+            current = Some(match effect
+            {
+                Effect::Blur {
+                    radius,
+                } => blur(renderer, current, *radius, &[]),
+                Effect::Bloom {
+                    threshold,
+                    intensity,
+                    radius,
+                } =>
+                {
+                    let mut bright = self.material(ShaderProgram::Bright);
+                    bright.set("u_Threshold", *threshold);
+                    let glow = free(&[current]);
+                    draw(renderer, &bright, current, glow);
+                    let glow = blur(renderer, Some(glow), *radius, &[current]);
+
+                    let mut bloom = self.material(ShaderProgram::Bloom);
+                    bloom.set("u_Intensity", *intensity);
+                    bloom.set_texture("u_Bloom", buffers[glow].texture());
+                    let out = free(&[current, Some(glow)]);
+                    draw(renderer, &bloom, current, out);
+                    out
+                },
+                Effect::Crt {
+                    curvature,
+                    scanlines,
+                } =>
+                {
+                    let mut crt = self.material(ShaderProgram::Crt);
+                    crt.set("u_Resolution", resolution);
+                    crt.set("u_Curvature", *curvature);
+                    crt.set("u_Scanlines", *scanlines);
+                    let out = free(&[current]);
+                    draw(renderer, &crt, current, out);
+                    out
+                },
+                Effect::Vignette {
+                    radius,
+                    softness,
+                    strength,
+                } =>
+                {
+                    let mut vignette = self.material(ShaderProgram::Vignette);
+                    vignette.set("u_Radius", *radius);
+                    vignette.set("u_Softness", *softness);
+                    vignette.set("u_Strength", *strength);
+                    let out = free(&[current]);
+                    draw(renderer, &vignette, current, out);
+                    out
+                },
+                Effect::ColorGrade {
+                    lut,
+                    strength,
+                } =>
+                {
+                    let mut grade = self.material(ShaderProgram::Lut);
+                    grade.set_texture("u_Lut", lut);
+                    grade.set("u_LutSize", lut.height() as f32);
+                    grade.set("u_Strength", *strength);
+                    let out = free(&[current]);
+                    draw(renderer, &grade, current, out);
+                    out
+                },
+                Effect::Pixelate {
+                    size,
+                } =>
+                {
+                    let mut pixelate = self.material(ShaderProgram::Pixelate);
+                    pixelate.set("u_Resolution", resolution);
+                    pixelate.set("u_PixelSize", size.max(1.0));
+                    let out = free(&[current]);
+                    draw(renderer, &pixelate, current, out);
+                    out
+                },
+                Effect::Custom(material) =>
+                {
+                    let mut material = material.clone();
+                    material.set("u_Resolution", resolution);
+                    let out = free(&[current]);
+                    draw(renderer, &material, current, out);
+                    out
+                },
+            });
+        }
+
+        let copy = self.material(ShaderProgram::Copy);
+        renderer.pass(&copy, id(current), fbo, viewport, blend);
+        Ok(())
+    }
+}
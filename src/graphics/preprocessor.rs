@@ -0,0 +1,322 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{Error, ShaderStage},
+    util::get_include,
+};
+
+/// A single stage of a `.shader` file on its way to the driver.
+pub(crate) struct Source
+{
+    pub code:  String,
+    /// For every line of `code`, the line in the combined `.shader` file it came from.
+    /// Lines pulled in by `#include` map to the `#include` itself.
+    pub lines: Vec<usize>,
+    /// The files `#include` pulled in, built-in includes aside.
+    pub files: Vec<PathBuf>,
+}
+
+impl Source
+{
+    pub fn new() -> Self
+    {
+        Self {
+            code: String::new(), lines: Vec::new(), files: Vec::new()
+        }
+    }
+
+    pub fn push(&mut self, line: &str, origin: usize)
+    {
+        self.code.push_str(line);
+        self.code.push('\n');
+        self.lines.push(origin);
+    }
+}
+
+/// Expands the tori specific directives in shader sources before they are compiled.
+///
+/// * `#include "name"` pastes in another file. It is looked up next to the shader
+///   file, then in the directories added with `include_dir`, then among the built-in
+///   includes from `res/shaders` such as `"batch.vertex.glsl"`.
+///   Every file is included at most once per stage.
+/// * Defines added with `define` are inserted right after the `#version` line.
+/// * `version` replaces the `#version` line of every stage, or adds one if missing.
+#[derive(Clone, Debug, Default)]
+pub struct Preprocessor
+{
+    include_dirs: Vec<PathBuf>,
+    defines:      Vec<(String, String)>,
+    version:      Option<String>,
+}
+
+impl Preprocessor
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    pub fn include_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self
+    {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    pub fn define<N: Into<String>, V: ToString>(mut self, name: N, value: V) -> Self
+    {
+        self.defines.push((name.into(), value.to_string()));
+        self
+    }
+
+    /// Sets the version written after `#version`, e.g. `"330 core"` or `"300 es"`.
+    pub fn version<S: Into<String>>(mut self, version: S) -> Self
+    {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// The GLSL version matching the current context, e.g. `"410 core"` or `"300 es"`.
+    /// Only call this once a `Window` exists.
+    pub fn context_version() -> String
+    {
+        let version = unsafe
+        {
+            let ptr = gl::GetString(gl::SHADING_LANGUAGE_VERSION);
+            if ptr.is_null()
+            {
+                return "330 core".to_string();
+            }
+            std::ffi::CStr::from_ptr(ptr as _).to_string_lossy().into_owned()
+        };
+
+        // Looks like "4.10 NVIDIA ..." or "OpenGL ES GLSL ES 3.00".
+        let number = version
+            .split_whitespace()
+            .find(|w| w.contains('.') && w.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap_or("3.30")
+            .replace('.', "");
+        if version.contains("ES")
+        {
+            format!("{} es", number)
+        }
+        else
+        {
+            format!("{} core", number)
+        }
+    }
+
+    pub(crate) fn process(
+        &self,
+        stage: ShaderStage,
+        source: &Source,
+        dir: Option<&Path>,
+    ) -> Result<Source, Error>
+    {
+        let mut out = Source::new();
+        let mut included = HashSet::new();
+        let mut has_version = false;
+
+        for (line, &origin) in source.code.lines().zip(source.lines.iter())
+        {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("#version")
+            {
+                has_version = true;
+                match &self.version
+                {
+                    Some(v) => out.push(&format!("#version {}", v), origin),
+                    None => out.push(line, origin),
+                }
+                self.push_defines(&mut out, origin);
+            }
+            else if let Some(rest) = trimmed.strip_prefix("#include")
+            {
+                self.include(stage, rest, origin, dir, &mut included, &mut out)?;
+            }
+            else
+            {
+                out.push(line, origin);
+            }
+        }
+
+        if !has_version
+        {
+            let origin = source.lines.first().copied().unwrap_or(1);
+            let mut head = Source::new();
+            let version = self.version.as_deref().unwrap_or("330 core");
+            head.push(&format!("#version {}", version), origin);
+            self.push_defines(&mut head, origin);
+
+            head.code.push_str(&out.code);
+            head.lines.extend(out.lines);
+            head.files = out.files;
+            out = head;
+        }
+
+        Ok(out)
+    }
+
+    fn push_defines(&self, out: &mut Source, origin: usize)
+    {
+        for (name, value) in self.defines.iter()
+        {
+            out.push(&format!("#define {} {}", name, value), origin);
+        }
+    }
+
+    fn include(
+        &self,
+        stage: ShaderStage,
+        rest: &str,
+        origin: usize,
+        dir: Option<&Path>,
+        included: &mut HashSet<PathBuf>,
+        out: &mut Source,
+    ) -> Result<(), Error>
+    {
+        let error = |log: String| Error::Shader {
+            stage,
+            log,
+            lines: vec![origin],
+        };
+
+        let name = rest.trim().trim_matches(|c| c == '"' || c == '<' || c == '>');
+        if name.is_empty()
+        {
+            return Err(error("#include without a file name".to_string()));
+        }
+
+        let found = dir
+            .into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|d| d.join(name))
+            .find(|p| p.is_file());
+
+        // Files count as the same however they are reached. Built-in includes go by name,
+        // which never clashes with a canonical path as those are absolute.
+        let key = match &found
+        {
+            Some(path) => std::fs::canonicalize(path).unwrap_or_else(|_| path.clone()),
+            None => PathBuf::from(name),
+        };
+        if !included.insert(key)
+        {
+            return Ok(());
+        }
+
+        let (code, dir) = match found
+        {
+            Some(path) =>
+            {
+                let code = std::fs::read_to_string(&path).map_err(|e| {
+                    error(format!("Could not read include \"{}\": {}", path.display(), e))
+                })?;
+                let dir = path.parent().map(Path::to_path_buf);
+                out.files.push(path);
+                (code, dir)
+            },
+            None => match get_include(name)
+            {
+                Some(code) => (code.to_string(), None),
+                None => return Err(error(format!("Could not find include \"{}\"", name))),
+            },
+        };
+
+        for line in code.lines()
+        {
+            match line.trim_start().strip_prefix("#include")
+            {
+                Some(rest) => self.include(stage, rest, origin, dir.as_deref(), included, out)?,
+                None => out.push(line, origin),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn source(code: &str) -> Source
+    {
+        let mut source = Source::new();
+        for (i, line) in code.lines().enumerate()
+        {
+            source.push(line, i + 1);
+        }
+        source
+    }
+
+    #[test]
+    fn records_included_files()
+    {
+        let dir = std::env::temp_dir().join(format!("tori-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("a.glsl"), "#include \"lib/b.glsl\"\nfloat a;\n").unwrap();
+        std::fs::write(dir.join("lib/b.glsl"), "float b;\n").unwrap();
+
+        let code = "#version 330 core\n#include \"a.glsl\"\n#include \"batch.vertex.glsl\"\n";
+        let out = Preprocessor::new()
+            .process(ShaderStage::Vertex, &source(code), Some(&dir))
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(out.files, [dir.join("a.glsl"), dir.join("lib/b.glsl")]);
+        assert!(out.code.contains("float b;\nfloat a;\n"));
+        assert_eq!(out.lines.len(), out.code.lines().count());
+    }
+
+    #[test]
+    fn includes_are_told_apart_by_file()
+    {
+        let dir = std::env::temp_dir().join(format!("tori-same-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("common.glsl"), "float top;\n").unwrap();
+        std::fs::write(dir.join("lib/common.glsl"), "float lib;\n").unwrap();
+        std::fs::write(dir.join("lib/a.glsl"), "#include \"common.glsl\"\n").unwrap();
+
+        // `lib/a.glsl` reaches `lib/common.glsl` again, the last one is another file.
+        let code = ["lib/common.glsl", "lib/a.glsl", "common.glsl"]
+            .map(|name| format!("#include \"{}\"\n", name))
+            .concat();
+        let out = Preprocessor::new()
+            .process(ShaderStage::Vertex, &source(&code), Some(&dir))
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(out.code.matches("float top;").count(), 1);
+        assert_eq!(out.code.matches("float lib;").count(), 1);
+    }
+
+    #[test]
+    fn include_errors_point_at_the_include()
+    {
+        let dir = std::env::temp_dir().join(format!("tori-bad-include-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("bad.glsl"), [0xff, 0xfe, b'\n']).unwrap();
+
+        let code = "#version 330 core\nvoid main() {}\n#include \"bad.glsl\"\n";
+        let process = |code: &str| {
+            Preprocessor::new().process(ShaderStage::Fragment, &source(code), Some(&dir))
+        };
+        let not_utf8 = process(code);
+        let missing = process(&code.replace("bad", "none"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        for result in [not_utf8, missing]
+        {
+            match result
+            {
+                Err(Error::Shader {
+                    stage, lines, ..
+                }) => assert_eq!((stage, lines), (ShaderStage::Fragment, vec![3])),
+                other => panic!("{:?}", other.map(|s| s.code)),
+            }
+        }
+    }
+}
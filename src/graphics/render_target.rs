@@ -0,0 +1,51 @@
+use std::rc::Rc;
+
+use crate::{
+    core::{self, framebuffer::Framebuffer},
+    error::Error,
+    graphics::texture::Texture,
+};
+
+/// An offscreen surface that drawables can be rendered into with
+/// `Window::draw_to`, and that can then be drawn itself through `texture`.
+pub struct RenderTarget
+{
+    framebuffer: Framebuffer,
+    texture:     Texture,
+}
+
+impl RenderTarget
+{
+    pub fn new(width: u32, height: u32) -> Result<Self, Error>
+    {
+        let texture = core::texture::Texture::with_size(width as _, height as _)?;
+        let framebuffer = Framebuffer::new(&texture)?;
+
+        Ok(Self {
+            framebuffer,
+            texture: Texture {
+                texture: Rc::new(texture),
+            },
+        })
+    }
+
+    pub fn texture(&self) -> &Texture
+    {
+        &self.texture
+    }
+
+    pub fn width(&self) -> u32
+    {
+        self.framebuffer.width as _
+    }
+
+    pub fn height(&self) -> u32
+    {
+        self.framebuffer.height as _
+    }
+
+    pub(crate) fn framebuffer(&self) -> &Framebuffer
+    {
+        &self.framebuffer
+    }
+}
@@ -1,15 +1,14 @@
 use crate::{
-    core::*,
+    core::renderer::{Pipeline, Renderer},
+    graphics::{blend::BlendMode, material::Material},
     math,
-    util::{get_shader, ShaderProgram},
 };
 
 pub struct Circle
 {
-    va:         vertex_array::VertexArray,
-    vb:         vertex_buffer::VertexBuffer,
-    ib:         index_buffer::IndexBuffer,
-    shader:     shader::Shader,
+    color:      math::Vec4,
+    material:   Option<Material>,
+    blend:      BlendMode,
     pub center: math::Vec2,
     pub radius: f32,
 }
@@ -18,60 +17,51 @@ impl Circle
 {
     pub fn new(center: math::Vec2, radius: f32, color: math::Vec4) -> Self
     {
-        #[rustfmt::skip]
-        let quad: [f32; 16] = [
-            -0.5, -0.5,  0., 0., // bottom left
-             0.5, -0.5,  1., 0., // bottom right
-             0.5,  0.5,  1., 1., // top right
-            -0.5,  0.5,  0., 1.  // top left
-        ];
-
-        #[rustfmt::skip]
-        let indices: [u32; 6] = [
-            0, 1, 2, 
-            2, 3, 0
-        ];
-
-        let vb = vertex_buffer::VertexBuffer::new(&quad);
-        let mut va = vertex_array::VertexArray::new();
-        let mut layout = vertex_buffer_layout::VertexBufferLayout::new();
-        layout.push(2, gl::FLOAT);
-        layout.push(2, gl::FLOAT);
-        va.add_buffer(&vb, layout);
-
-        let ib = index_buffer::IndexBuffer::new(&indices);
-
-        let shader = shader::Shader::from_shader_string(get_shader(ShaderProgram::Circle));
-        shader.bind();
-        shader.set_uniform_f4("u_Color\0", color[0], color[1], color[2], color[3]);
-
-        va.unbind();
-        vb.unbind();
-        ib.unbind();
-        shader.unbind();
-
         Self {
-            va,
-            vb,
-            ib,
-            shader,
+            color,
+            material: None,
+            blend: BlendMode::Alpha,
             center,
             radius,
         }
     }
+
+    pub fn set_color(&mut self, color: math::Vec4)
+    {
+        self.color = color;
+    }
+
+    /// Draws with a user shader instead of the built-in circle one, `None` goes back to
+    /// the default. The material has to cut out the circle itself.
+    pub fn set_material(&mut self, material: Option<Material>)
+    {
+        self.material = material;
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode)
+    {
+        self.blend = blend;
+    }
 }
 
-use crate::graphics::drawable::{std_draw, Drawable};
+use crate::graphics::drawable::Drawable;
 impl Drawable for Circle
 {
-    fn draw(&self, proj: math::Mat4)
+    fn draw(&self, renderer: &mut Renderer)
     {
-        self.shader.bind();
-
         let mut model = math::identity();
         model = math::translate(&model, &math::vec3(self.center[0], self.center[1], 1.0));
         model = math::scale(&model, &math::vec3(self.radius, self.radius, 1.0));
+        model = math::translate(&model, &math::vec3(-0.5, -0.5, 0.0));
 
-        std_draw(&self.va, &self.ib, &self.shader, model, proj, None);
+        let pipeline = self.material.clone().map_or(Pipeline::Circle, Pipeline::Material);
+        renderer.set_blend_mode(self.blend);
+        renderer.draw_model(
+            pipeline,
+            &model,
+            math::vec4(0., 0., 1., 1.),
+            self.color,
+            None,
+        );
     }
 }
@@ -1,15 +1,14 @@
 use crate::{
-    core::{index_buffer, shader, vertex_array, vertex_buffer, vertex_buffer_layout},
-    graphics::entity::Entity,
+    core::renderer::{Pipeline, Renderer},
+    graphics::{blend::BlendMode, entity::Entity, material::Material},
     math,
-    util::{get_shader, ShaderProgram::Basic},
 };
 
 pub struct Rect
 {
-    shader:     shader::Shader,
-    va:         vertex_array::VertexArray,
-    ib:         index_buffer::IndexBuffer,
+    color:      math::Vec4,
+    material:   Option<Material>,
+    blend:      BlendMode,
     pub entity: Entity,
 }
 
@@ -17,65 +16,41 @@ impl Rect
 {
     pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self
     {
-        #[rustfmt::skip]
-        let positions: [f32; 16] = [
-             0.0, 0.0,  0., 0., // bottom left
-             1.0, 0.0,  1., 0., // bottom right
-             1.0, 1.0,  1., 1., // top right
-             0.0, 1.0,  0., 1.  // top left
-        ];
-
-        #[rustfmt::skip]
-        let indices: [u32; 6] = [
-            0, 1, 2, 
-            2, 3, 0
-        ];
-
-        let vb = vertex_buffer::VertexBuffer::new(&positions);
-        let mut va = vertex_array::VertexArray::new();
-        let mut layout = vertex_buffer_layout::VertexBufferLayout::new();
-        layout.push(2, gl::FLOAT);
-        layout.push(2, gl::FLOAT);
-        va.add_buffer(&vb, layout);
-
-        let ib = index_buffer::IndexBuffer::new(&indices);
-
-        let shader = shader::Shader::from_shader_string(get_shader(Basic));
-        shader.bind();
-        shader.set_uniform_f4("u_Color\0", 1., 0., 0., 1.);
-
-        va.unbind();
-        vb.unbind();
-        ib.unbind();
-        shader.unbind();
-
         let entity = Entity::new(math::vec2(w, h), math::vec2(x, y), 0.0);
 
         Self {
-            shader,
-            va,
-            ib,
+            color: math::vec4(1., 0., 0., 1.),
+            material: None,
+            blend: BlendMode::Alpha,
             entity,
         }
     }
 
     pub fn set_color(&mut self, color: math::Vec4)
     {
-        let g = |idx: usize| unsafe
-        {
-            *color.get_unchecked(idx)
-        };
-        self.shader.bind();
-        self.shader.set_uniform_f4("u_Color\0", g(0), g(1), g(1), g(3));
+        self.color = color;
+    }
+
+    /// Draws with a user shader instead of the plain color one, `None` goes back to the default.
+    pub fn set_material(&mut self, material: Option<Material>)
+    {
+        self.material = material;
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode)
+    {
+        self.blend = blend;
     }
 }
 
-use crate::graphics::drawable::{std_draw, Drawable};
+use crate::graphics::drawable::Drawable;
 impl Drawable for Rect
 {
-    fn draw(&self, proj: math::Mat4)
+    fn draw(&self, renderer: &mut Renderer)
     {
         let model = self.entity.get_model();
-        std_draw(&self.va, &self.ib, &self.shader, model, proj, None);
+        let pipeline = self.material.clone().map_or(Pipeline::Quad, Pipeline::Material);
+        renderer.set_blend_mode(self.blend);
+        renderer.draw_model(pipeline, &model, math::vec4(0., 0., 1., 1.), self.color, None);
     }
 }
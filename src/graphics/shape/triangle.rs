@@ -1,14 +1,14 @@
 use crate::{
-    core::{index_buffer, shader, vertex_array, vertex_buffer, vertex_buffer_layout},
+    core::renderer::{Pipeline, Renderer, Vertex},
+    graphics::blend::BlendMode,
     math::{self, Vec2},
-    util::{get_shader, ShaderProgram::Basic},
 };
 
 pub struct Triangle
 {
-    shader:  shader::Shader,
-    va:      vertex_array::VertexArray,
-    ib:      index_buffer::IndexBuffer,
+    points:  [Vec2; 3],
+    color:   math::Vec4,
+    blend:   BlendMode,
     pub pos: Vec2,
 }
 
@@ -16,71 +16,35 @@ impl Triangle
 {
     pub fn new(p1: Vec2, p2: Vec2, p3: Vec2) -> Self
     {
-        // SAFETY:
-        // points cannot be created without calling
-        // something like `new`.
-        let g = |p: Vec2, idx: usize| unsafe
-        {
-            *p.get_unchecked(idx)
-        };
-
-        #[rustfmt::skip]
-        let positions: [f32; 6] = [
-            g(p1, 0), g(p1, 1),
-            g(p2, 0), g(p2, 1),
-            g(p3, 0), g(p3, 1),
-        ];
-
-        #[rustfmt::skip]
-        let indices: [u32; 3] = [
-            0, 1, 2, 
-        ];
-
-        let vb = vertex_buffer::VertexBuffer::new(&positions);
-        let mut va = vertex_array::VertexArray::new();
-        let mut layout = vertex_buffer_layout::VertexBufferLayout::new();
-        layout.push(2, gl::FLOAT);
-        va.add_buffer(&vb, layout);
-
-        let shader = shader::Shader::from_shader_string(get_shader(Basic));
-        shader.bind();
-        shader.set_uniform_f4("u_Color\0", 1., 0., 0., 1.0);
-
-        let ib = index_buffer::IndexBuffer::new(&indices);
-
-        let pos = Vec2::new(0.0, 0.0);
-
-        va.unbind();
-        vb.unbind();
-        ib.unbind();
-        shader.unbind();
-
         Self {
-            shader,
-            va,
-            ib,
-            pos,
+            points: [p1, p2, p3],
+            color: math::vec4(1., 0., 0., 1.0),
+            blend: BlendMode::Alpha,
+            pos: Vec2::new(0.0, 0.0),
         }
     }
 
     pub fn set_color(&mut self, color: math::Vec4)
     {
-        let g = |idx: usize| unsafe
-        {
-            *color.get_unchecked(idx)
-        };
-        self.shader.bind();
-        self.shader.set_uniform_f4("u_Color\0", g(0), g(1), g(1), g(3));
+        self.color = color;
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode)
+    {
+        self.blend = blend;
     }
 }
 
-use crate::graphics::drawable::{std_draw, Drawable};
+use crate::graphics::drawable::Drawable;
 impl Drawable for Triangle
 {
-    fn draw(&self, proj: math::Mat4)
+    fn draw(&self, renderer: &mut Renderer)
     {
-        let pos = glm::identity();
-        let model = glm::translate(&pos, &glm::vec3(pos[0], pos[1], 0.0));
-        std_draw(&self.va, &self.ib, &self.shader, model, proj, None);
+        let [p1, p2, p3] = self.points.map(|p| Vertex::new(p + self.pos, Vec2::zeros(), self.color));
+
+        // The batch only knows quads, so the last corner is repeated
+        // and the second triangle of the quad collapses.
+        renderer.set_blend_mode(self.blend);
+        renderer.push_quad(Pipeline::Quad, None, [p1, p2, p3, p3]);
     }
 }
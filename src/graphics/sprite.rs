@@ -1,93 +1,111 @@
-use std::rc::Rc;
+use std::{cell::Cell, rc::Rc};
 
 use crate::{
-    core::*,
-    graphics::{entity::Entity, texture},
+    core::renderer::{Pipeline, Renderer},
+    graphics::{atlas::Atlas, blend::BlendMode, entity::Entity, material::Material, texture},
     math,
-    util::{get_shader, ShaderProgram::Texture},
 };
 
-pub struct Sprite<'texture>
+pub struct Sprite
 {
-    va:     vertex_array::VertexArray,
-    vb:     vertex_buffer::VertexBuffer,
-    ib:     index_buffer::IndexBuffer,
-    shader: Rc<shader::Shader>,
+    frame:    Rc<Cell<Frame>>,
+    material: Option<Material>,
+    blend:    BlendMode,
 
     pub entity: Entity,
-    texture:    &'texture texture::Texture,
+    texture:    texture::Texture,
+    normal_map: Option<texture::Texture>,
 }
 
-impl<'tex> Sprite<'tex>
+#[derive(Clone, Copy)]
+struct Frame
 {
-    pub fn new(texture: &'tex texture::Texture) -> Self
+    idx:    u32,
+    cols:   u32,
+    rows:   u32,
+    /// The part of the texture the grid is laid over, as `(u0, v0, u1, v1)`.
+    region: math::Vec4,
+}
+
+impl Frame
+{
+    fn uv(&self) -> math::Vec4
+    {
+        let idx = self.idx % (self.cols * self.rows);
+        let w = (self.region[2] - self.region[0]) / self.cols as f32;
+        let h = (self.region[3] - self.region[1]) / self.rows as f32;
+        let x = self.region[0] + (idx % self.cols) as f32 * w;
+        let y = self.region[1] + (idx / self.cols) as f32 * h;
+        math::vec4(x, y, x + w, y + h)
+    }
+}
+
+impl Sprite
+{
+    /// The sprite keeps its own handle to `texture`, see `Texture`.
+    pub fn new(texture: &texture::Texture) -> Self
     {
         let core = texture.get_core();
         let w = core.width as f32;
         let h = core.height as f32;
-        #[rustfmt::skip]
-        let positions: [f32; 16] = [
-             0.0, 0.0,  0., 0., // bottom left
-             1.0, 0.0,  1., 0., // bottom right
-             1.0, 1.0,  1., 1., // top right
-             0.0, 1.0,  0., 1.  // top left
-        ];
-
-        #[rustfmt::skip]
-        let indices: [u32; 6] = [
-            0, 1, 2, 
-            2, 3, 0
-        ];
-
-        let vb = vertex_buffer::VertexBuffer::new(&positions);
-        let mut va = vertex_array::VertexArray::new();
-        let mut layout = vertex_buffer_layout::VertexBufferLayout::new();
-        layout.push(2, gl::FLOAT);
-        layout.push(2, gl::FLOAT);
-        va.add_buffer(&vb, layout);
-
-        let ib = index_buffer::IndexBuffer::new(&indices);
-
-        let data = get_shader(Texture);
-        let shader = shader::Shader::from_shader_string(data);
-        shader.bind();
-
-        shader.set_uniform_1i("u_Texture\0", 0);
-
-        shader.set_uniform_1u("u_Idx", 0);
-        shader.set_uniform_1u("u_Num_Sprites", 1);
-        shader.set_uniform_1f("u_Cols", 1.0);
-        shader.set_uniform_1f("u_Rows", 1.0);
-
-        va.unbind();
-        vb.unbind();
-        ib.unbind();
-        shader.unbind();
+
+        Self::with_region(texture, math::vec4(0.0, 0.0, 1.0, 1.0), math::vec2(w, h))
+    }
+
+    /// A sprite showing the image packed into `atlas` under `name`.
+    pub fn from_atlas(atlas: &Atlas, name: &str) -> Option<Self>
+    {
+        let region = atlas.region(name)?;
+        let size = math::vec2(region.rect.width as f32, region.rect.height as f32);
+        Some(Self::with_region(atlas.page(region.page), region.uv, size))
+    }
+
+    fn with_region(texture: &texture::Texture, region: math::Vec4, size: math::Vec2) -> Self
+    {
         Self {
-            va,
-            vb,
-            ib,
-            shader: Rc::new(shader),
-            texture,
-            entity: Entity::new(math::vec2(w, h), math::vec2(0.0, 0.0), 0.0),
+            frame: Rc::new(Cell::new(Frame {
+                idx: 0, cols: 1, rows: 1, region
+            })),
+            material: None,
+            blend: BlendMode::Alpha,
+            texture: texture.clone(),
+            normal_map: None,
+            entity: Entity::new(size, math::vec2(0.0, 0.0), 0.0),
         }
     }
 
+    /// Draws with a user shader instead of the default one, `None` goes back to the default.
+    /// The sprite's texture is bound as `u_Texture`.
+    pub fn set_material(&mut self, material: Option<Material>)
+    {
+        self.material = material;
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode)
+    {
+        self.blend = blend;
+    }
+
+    /// Lights the sprite per pixel, see `Window::draw_normals`. The normal map is laid
+    /// out like the sprite's texture and its transparent pixels are left out, so it
+    /// should share the texture's alpha. The normals are not rotated with the sprite.
+    pub fn set_normal_map(&mut self, normal_map: Option<&texture::Texture>)
+    {
+        self.normal_map = normal_map.cloned();
+    }
+
     pub fn make_sprite_sheet(&mut self, num_cols: u32, num_rows: u32) -> SpriteSheet
     {
-        let shader = &self.shader;
-        shader.bind();
-        shader.set_uniform_1u("u_Idx", 0);
-        shader.set_uniform_1u("u_Num_Sprites", num_cols * num_rows);
-        shader.set_uniform_1f("u_Cols", num_cols as _);
-        shader.set_uniform_1f("u_Rows", num_rows as _);
-        shader.unbind();
+        let region = self.frame.get().region;
+        self.frame.set(Frame {
+            idx: 0, cols: num_cols, rows: num_rows, region
+        });
 
         self.entity.size[0] /= num_cols as f32;
         self.entity.size[1] /= num_rows as f32;
 
         SpriteSheet {
-            shader: Rc::clone(&self.shader),
+            frame: Rc::clone(&self.frame),
             num_cols,
             num_rows,
             idx: 0,
@@ -97,7 +115,7 @@ impl<'tex> Sprite<'tex>
 
 pub struct SpriteSheet
 {
-    shader:   Rc<shader::Shader>,
+    frame:    Rc<Cell<Frame>>,
     num_cols: u32,
     num_rows: u32,
     pub idx:  u32,
@@ -122,19 +140,32 @@ impl SpriteSheet
 
     pub fn set_idx(&mut self, idx: u32)
     {
-        self.shader.bind();
-        self.shader.set_uniform_1u("u_Idx\0", idx);
-        self.shader.unbind();
+        let mut frame = self.frame.get();
+        frame.idx = idx;
+        self.frame.set(frame);
         self.idx = idx;
     }
 }
 
-use crate::graphics::drawable::{std_draw, Drawable};
-impl<'t> Drawable for Sprite<'t>
+use crate::graphics::drawable::Drawable;
+impl Drawable for Sprite
 {
-    fn draw(&self, proj: math::Mat4)
+    fn draw(&self, renderer: &mut Renderer)
     {
         let model = self.entity.get_model();
-        std_draw(&self.va, &self.ib, &self.shader, model, proj, Some(&self.texture.texture));
+        let uv = self.frame.get().uv();
+        if renderer.drawing_normals()
+        {
+            if let Some(normal_map) = &self.normal_map
+            {
+                renderer.draw_normals(&model, uv, normal_map);
+            }
+            return;
+        }
+
+        let pipeline = self.material.clone().map_or(Pipeline::Quad, Pipeline::Material);
+        renderer.set_blend_mode(self.blend);
+        let white = math::vec4(1., 1., 1., 1.);
+        renderer.draw_model(pipeline, &model, uv, white, Some(&self.texture));
     }
 }
@@ -2,8 +2,11 @@ use crate::math;
 
 pub struct Character
 {
-    pub texture_id: u32,
-    pub size:       math::IVec2,
-    pub bearing:    math::IVec2,
-    pub advance:    u32,
+    /// Page of the font's glyph atlas the glyph is on.
+    pub page:    usize,
+    /// `(u0, v0, u1, v1)` of the glyph on its page, see `AtlasRegion::uv`.
+    pub uv:      math::Vec4,
+    pub size:    math::IVec2,
+    pub bearing: math::IVec2,
+    pub advance: u32,
 }
@@ -3,16 +3,40 @@ use std::{cell::RefCell, collections::HashMap, rc::Rc};
 use freetype::Library;
 
 use crate::{
-    core::util::ptr,
     error::Error,
-    graphics::text::{character::Character, quad::Quad, CharSet, Handle},
+    graphics::{
+        atlas::{Atlas, AtlasBuilder},
+        text::{character::Character, CharSet, Handle},
+    },
     math::IVec2,
 };
 
+/// Size of the pages glyphs are packed into.
+const PAGE_SIZE: u32 = 1024;
+
 pub struct Inner
 {
     pub characters: RefCell<HashMap<(usize, char), Character>>,
-    pub quad:       Quad,
+    /// The glyph atlas of every font, so a whole text is drawn from a single texture.
+    pub atlases:    RefCell<HashMap<usize, Atlas>>,
+}
+
+impl Inner
+{
+    /// Deletes the glyphs of the font behind `handle_key`.
+    pub fn remove_font(&self, handle_key: usize)
+    {
+        self.characters.borrow_mut().retain(|(key, _), _| *key != handle_key);
+        self.atlases.borrow_mut().remove(&handle_key);
+    }
+}
+
+/// A rendered glyph waiting to be packed.
+struct Glyph
+{
+    image:   image::RgbaImage,
+    bearing: IVec2,
+    advance: u32,
 }
 
 pub struct Freetype
@@ -26,64 +50,68 @@ impl Freetype
 {
     pub fn new() -> Result<Self, Error>
     {
-        unsafe
-        {
-            // idk if this is needed
-            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-        }
-
         let lib = Library::init()?;
         Ok(Self {
             lib,
             inner: Rc::new(Inner {
-                characters: RefCell::default(), quad: Quad::new()
+                characters: RefCell::default(),
+                atlases: RefCell::default(),
             }),
             idx: 0,
         })
     }
 
-    fn add_char(
-        &mut self,
-        face: &freetype::Face,
-        ch: char,
-        handle_key: usize,
-    ) -> Result<(), freetype::Error>
+    /// Renders `ch` as white with its coverage as alpha, which the batch shader
+    /// turns into the text color.
+    fn render_char(face: &freetype::Face, ch: char) -> Result<Glyph, freetype::Error>
     {
         face.load_char(ch as usize, freetype::face::LoadFlag::RENDER)?;
         let glyph = face.glyph();
         let bitmap = glyph.bitmap();
-        let mut texture_id = 0;
-        unsafe
-        {
-            gl::GenTextures(1, &mut texture_id);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RED as _,
-                bitmap.width(),
-                bitmap.rows(),
-                0,
-                gl::RED as _,
-                gl::UNSIGNED_BYTE,
-                ptr!(bitmap.buffer()),
-            );
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
-
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
-        }
-        let character = Character {
-            texture_id,
-            size: IVec2::new(bitmap.width(), bitmap.rows()),
+        let (width, pitch) = (bitmap.width() as u32, bitmap.pitch().unsigned_abs() as usize);
+        let buffer = bitmap.buffer();
+        let image = image::RgbaImage::from_fn(width, bitmap.rows() as u32, |x, y| {
+            image::Rgba([255, 255, 255, buffer[y as usize * pitch + x as usize]])
+        });
+
+        Ok(Glyph {
+            image,
             bearing: IVec2::new(glyph.bitmap_left(), glyph.bitmap_top()),
             advance: glyph.advance().x as _,
-        };
-        self.inner.characters.borrow_mut().insert((handle_key, ch), character);
+        })
+    }
+
+    /// Packs the glyphs of a font into its atlas.
+    fn add_glyphs(&mut self, glyphs: Vec<(char, Glyph)>, handle_key: usize) -> Result<(), Error>
+    {
+        // A pixel of padding keeps linear filtering from bleeding in the neighbors.
+        let mut builder = AtlasBuilder::new(PAGE_SIZE, PAGE_SIZE).padding(1);
+        let mut metrics = Vec::with_capacity(glyphs.len());
+        for (ch, glyph) in glyphs
+        {
+            let size = IVec2::new(glyph.image.width() as _, glyph.image.height() as _);
+            metrics.push((ch, size, glyph.bearing, glyph.advance));
+            builder = builder.add(ch.to_string(), glyph.image.into());
+        }
+        let atlas = builder.build()?;
+
+        let mut characters = self.inner.characters.borrow_mut();
+        for (ch, size, bearing, advance) in metrics
+        {
+            let Some(region) = atlas.region(&ch.to_string())
+            else
+            {
+                continue;
+            };
+            characters.insert((handle_key, ch), Character {
+                page: region.page,
+                uv: region.uv,
+                size,
+                bearing,
+                advance,
+            });
+        }
+        self.inner.atlases.borrow_mut().insert(handle_key, atlas);
         Ok(())
     }
 
@@ -95,23 +123,24 @@ impl Freetype
 
         face.set_pixel_sizes(0, 48)?;
 
-        let handle = Handle(self.idx, Rc::clone(&self.inner));
+        let handle = Handle(self.idx, Rc::clone(&self.inner), Rc::default());
         self.idx += 1;
 
+        let mut glyphs = Vec::new();
         match set
         {
             CharSet::Ascii =>
             {
                 for ch in (0u8 as char)..=(255u8 as char)
                 {
-                    self.add_char(&face, ch, handle.0)?;
+                    glyphs.push((ch, Self::render_char(&face, ch)?));
                 }
             },
             CharSet::Custom(iter) =>
             {
                 for ch in iter.into_iter()
                 {
-                    self.add_char(&face, ch, handle.0)?;
+                    glyphs.push((ch, Self::render_char(&face, ch)?));
                 }
             },
             CharSet::All =>
@@ -127,7 +156,7 @@ impl Freetype
                 {
                     let ch = char::from_u32(code as u32)
                         .ok_or(Error::FontCharError(format!("Error trying to load {}", code)))?;
-                    self.add_char(&face, ch, handle.0)?;
+                    glyphs.push((ch, Self::render_char(&face, ch)?));
                     code = freetype_sys::FT_Get_Next_Char(
                         face.raw_mut(),
                         code,
@@ -136,6 +165,7 @@ impl Freetype
                 }
             },
         }
+        self.add_glyphs(glyphs, handle.0)?;
 
         Ok(handle)
     }
@@ -2,5 +2,7 @@ use std::rc::Rc;
 
 use crate::graphics::text::freetype::Inner;
 
+/// A loaded font. The last field only counts the handles of this font,
+/// see `Assets::unload_unused`.
 #[derive(Clone)]
-pub struct Handle(pub(crate) usize, pub(crate) Rc<Inner>);
+pub struct Handle(pub(crate) usize, pub(crate) Rc<Inner>, pub(crate) Rc<()>);
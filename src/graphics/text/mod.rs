@@ -2,7 +2,6 @@ pub mod char_set;
 mod character;
 pub(crate) mod freetype;
 pub mod handle;
-mod quad;
 pub mod text;
 pub use char_set::CharSet;
 pub use handle::Handle;
@@ -1,4 +1,7 @@
-use crate::{graphics::text::Handle, math};
+use crate::{
+    graphics::{blend::BlendMode, text::Handle},
+    math,
+};
 
 pub struct Text
 {
@@ -8,6 +11,7 @@ pub struct Text
     pub y:      f32,
     pub scale:  f32,
     pub color:  math::Vec4,
+    pub blend:  BlendMode,
 }
 
 impl Text
@@ -28,6 +32,7 @@ impl Text
             y,
             color,
             scale,
+            blend: BlendMode::Alpha,
         }
     }
 
@@ -64,61 +69,46 @@ impl Text
     }
 }
 
-use crate::graphics::drawable::Drawable;
+use crate::{
+    core::renderer::{Pipeline, Renderer, Vertex},
+    graphics::drawable::Drawable,
+};
 impl Drawable for Text
 {
-    fn draw(&self, proj: math::Mat4)
+    fn draw(&self, renderer: &mut Renderer)
     {
-        let quad = &self.handle.1.quad;
-        let shader = &quad.shader;
-        shader.bind();
-        shader.set_uniform_f4(
-            "u_TextColor\0",
-            self.color.x,
-            self.color.y,
-            self.color.z,
-            self.color.w,
-        );
-        shader.set_uniform_mat4f("u_Projection\0", &proj);
-
         let characters = self.handle.1.characters.borrow();
-
-        unsafe
+        let atlases = self.handle.1.atlases.borrow();
+        let Some(atlas) = atlases.get(&self.handle.0)
+        else
         {
-            gl::ActiveTexture(gl::TEXTURE0);
-            quad.va.bind();
-            let mut x = self.x;
-
-            for c in self.text.chars()
-            {
-                let ch = characters.get(&(self.handle.0, c)).unwrap();
-                let xpos = x + (ch.bearing.x as f32) * self.scale;
-                let ypos: f32 = self.y - ((ch.size.y - ch.bearing.y) as f32) * self.scale;
-
-                let w = ch.size.x as f32 * self.scale;
-                let h = ch.size.y as f32 * self.scale;
-
-                #[rustfmt::skip]
-                let vertices: [f32; 24] = [
-                    xpos, ypos + h, 0.0, 0.0,
-                    xpos, ypos, 0.0, 1.0,
-                    xpos + w, ypos, 1.0, 1.0,
+            return;
+        };
+        renderer.set_blend_mode(self.blend);
 
-                    xpos, ypos + h, 0.0, 0.0,
-                    xpos + w, ypos, 1.0, 1.0,
-                    xpos + w, ypos + h, 1.0, 0.0,
-                ];
-
-                gl::BindTexture(gl::TEXTURE_2D, ch.texture_id);
-                quad.vb.sub_data(&vertices);
+        let mut x = self.x;
+        for c in self.text.chars()
+        {
+            let ch = characters.get(&(self.handle.0, c)).unwrap();
+            let xpos = x + (ch.bearing.x as f32) * self.scale;
+            let ypos: f32 = self.y - ((ch.size.y - ch.bearing.y) as f32) * self.scale;
 
-                gl::DrawArrays(gl::TRIANGLES, 0, 6);
-                x += ((ch.advance >> 6) as f32) * self.scale;
-            }
+            let w = ch.size.x as f32 * self.scale;
+            let h = ch.size.y as f32 * self.scale;
 
-            quad.va.unbind();
-            quad.vb.unbind();
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+            let [u0, v0, u1, v1] = ch.uv.into();
+            let v = |x: f32, y: f32, u: f32, v: f32| {
+                Vertex::new(math::vec2(x, y), math::vec2(u, v), self.color)
+            };
+            let page = atlas.page(ch.page);
+            renderer.push_quad(Pipeline::Quad, Some(page), [
+                v(xpos, ypos, u0, v0),
+                v(xpos + w, ypos, u1, v0),
+                v(xpos + w, ypos + h, u1, v1),
+                v(xpos, ypos + h, u0, v1),
+            ]);
+
+            x += ((ch.advance >> 6) as f32) * self.scale;
         }
     }
 }
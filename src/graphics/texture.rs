@@ -1,23 +1,117 @@
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
-use crate::{core, error::Error};
+use crate::{
+    core::{self, texture::Options},
+    error::Error,
+};
 
+/// How texels are sampled when a texture is drawn larger or smaller than its size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter
+{
+    /// Blocky, what pixel art wants.
+    Nearest,
+    Linear,
+}
+
+impl Filter
+{
+    pub(crate) fn gl(self) -> u32
+    {
+        match self
+        {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+/// What happens to texture coordinates outside of `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wrap
+{
+    /// Tiles the texture, for backgrounds and the like.
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl Wrap
+{
+    pub(crate) fn gl(self) -> u32
+    {
+        match self
+        {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// A handle to an image on the GPU.
+///
+/// Cloning is cheap and gives another handle to the same texture,
+/// which is freed once the last handle is dropped.
+#[derive(Clone)]
 pub struct Texture
 {
-    pub(crate) texture: core::texture::Texture,
+    pub(crate) texture: Rc<core::texture::Texture>,
 }
 
 impl Texture
 {
+    /// Loads an image file with the default options, see `TextureBuilder` for more control.
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error>
     {
-        let texture = core::texture::Texture::new(path)?;
-        // texture.bind(None);
-        // shader.set_uniform_1i("u_Texture\0", 0);
+        TextureBuilder::from_path(path).build()
+    }
 
-        Ok(Self {
-            texture,
-        })
+    pub fn width(&self) -> u32
+    {
+        self.texture.width as _
+    }
+
+    pub fn height(&self) -> u32
+    {
+        self.texture.height as _
+    }
+
+    /// Replaces the `width` x `height` block of texels whose top left corner is `x, y`.
+    ///
+    /// `pixels` are tightly packed RGBA8, top row first like image files.
+    pub fn update_region(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<(), Error>
+    {
+        let fits = |start: u32, size: u32, limit: u32| {
+            start.checked_add(size).is_some_and(|end| end <= limit)
+        };
+        if !fits(x, width, self.width()) || !fits(y, height, self.height())
+        {
+            return Err(Error::TextureRegion {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+        core::texture::check_pixels(width, height, pixels)?;
+
+        // The texture itself is stored bottom row first.
+        let row = width as usize * 4;
+        let flipped: Vec<u8> = pixels.chunks_exact(row.max(1)).rev().flatten().copied().collect();
+        let y = self.height() - y - height;
+        self.texture.update_region(x as _, y as _, width as _, height as _, &flipped);
+        Ok(())
     }
 
     pub(crate) fn get_core(&self) -> &core::texture::Texture
@@ -25,3 +119,137 @@ impl Texture
         &self.texture
     }
 }
+
+enum Source<'a>
+{
+    Path(PathBuf),
+    Bytes(&'a [u8]),
+    Image(image::DynamicImage),
+    Rgba
+    {
+        width:  u32,
+        height: u32,
+        pixels: &'a [u8],
+    },
+}
+
+/// Creates a `Texture` with control over where the pixels come from and how they are sampled.
+///
+/// ```ignore
+/// let tiles = TextureBuilder::from_path("tiles.png")
+///     .filter(Filter::Nearest)
+///     .wrap(Wrap::Repeat)
+///     .build()?;
+/// ```
+pub struct TextureBuilder<'a>
+{
+    source:  Source<'a>,
+    options: Options,
+}
+
+impl<'a> TextureBuilder<'a>
+{
+    fn with_source(source: Source<'a>) -> Self
+    {
+        Self {
+            source, options: Options::default()
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self
+    {
+        Self::with_source(Source::Path(path.as_ref().to_path_buf()))
+    }
+
+    /// An encoded image in any format the `image` crate can guess, e.g. from `include_bytes!`.
+    pub fn from_bytes(bytes: &'a [u8]) -> Self
+    {
+        Self::with_source(Source::Bytes(bytes))
+    }
+
+    pub fn from_image(image: image::DynamicImage) -> Self
+    {
+        Self::with_source(Source::Image(image))
+    }
+
+    /// Tightly packed RGBA8 pixels, top row first.
+    pub fn from_rgba(width: u32, height: u32, pixels: &'a [u8]) -> Self
+    {
+        Self::with_source(Source::Rgba {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Sets both the minifying and magnifying filter, `Linear` by default.
+    pub fn filter(mut self, filter: Filter) -> Self
+    {
+        self.options.filter = filter;
+        self
+    }
+
+    /// Sets the wrap mode along both axes, `ClampToEdge` by default.
+    pub fn wrap(mut self, wrap: Wrap) -> Self
+    {
+        self.options.wrap = [wrap; 2];
+        self
+    }
+
+    /// Sets the wrap mode along u and v separately.
+    pub fn wrap_uv(mut self, u: Wrap, v: Wrap) -> Self
+    {
+        self.options.wrap = [u, v];
+        self
+    }
+
+    /// Generates mipmaps so the texture stays smooth when drawn much smaller.
+    /// They are regenerated after every `update_region`.
+    pub fn mipmaps(mut self, mipmaps: bool) -> Self
+    {
+        self.options.mipmaps = mipmaps;
+        self
+    }
+
+    /// Maximum anisotropic filtering samples, clamped to what the driver supports.
+    /// Ignored if the driver has no anisotropic filtering at all.
+    pub fn anisotropy(mut self, samples: f32) -> Self
+    {
+        self.options.anisotropy = samples;
+        self
+    }
+
+    pub fn build(self) -> Result<Texture, Error>
+    {
+        let options = &self.options;
+        let texture = match self.source
+        {
+            Source::Path(path) => core::texture::Texture::new(path, options)?,
+            Source::Bytes(bytes) =>
+            {
+                core::texture::Texture::from_image(image::load_from_memory(bytes)?, options)?
+            },
+            Source::Image(image) => core::texture::Texture::from_image(image, options)?,
+            Source::Rgba {
+                width,
+                height,
+                pixels,
+            } =>
+            {
+                // `from_raw` would take a buffer that is too long.
+                core::texture::check_pixels(width, height, pixels)?;
+                let image = image::RgbaImage::from_raw(width, height, pixels.to_vec())
+                    .ok_or(Error::PixelBuffer {
+                        width,
+                        height,
+                        len: pixels.len(),
+                    })?;
+                core::texture::Texture::from_image(image.into(), options)?
+            },
+        };
+
+        Ok(Texture {
+            texture: Rc::new(texture),
+        })
+    }
+}
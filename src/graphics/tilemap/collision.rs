@@ -0,0 +1,215 @@
+use crate::{
+    graphics::{
+        shape::Rect,
+        tilemap::{Layer, TileMap},
+    },
+    math::{self, grid::GridLayout, Vec2, Vec4},
+};
+
+/// In cells, so boxes that only touch a filled cell do not overlap it.
+const EPSILON: f32 = 1e-4;
+
+/// The cells of a tile or IntGrid layer, placed in the world.
+struct Cells<'a>
+{
+    layer:  &'a Layer,
+    origin: Vec2,
+    cell:   Vec2,
+    width:  u32,
+    height: u32,
+}
+
+impl Cells<'_>
+{
+    /// Whether cell `(x, y)` holds a tile or a value other than `0`.
+    fn filled(&self, x: i64, y: i64) -> bool
+    {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64
+        {
+            return false;
+        }
+        match self.layer
+        {
+            Layer::Tiles(layer) => layer.get(x as u32, y as u32).is_some(),
+            Layer::IntGrid(layer) => layer.get(x as u32, y as u32) != 0,
+            Layer::Objects(_) => false,
+        }
+    }
+
+    fn overlaps(&self, bounds: Vec4) -> bool
+    {
+        let min = (math::vec2(bounds.x, bounds.y) - self.origin).component_div(&self.cell);
+        let max = min + math::vec2(bounds.z, bounds.w).component_div(&self.cell);
+        let (x0, y0) = ((min.x + EPSILON).floor() as i64, (min.y + EPSILON).floor() as i64);
+        let (x1, y1) = ((max.x - EPSILON).ceil() as i64, (max.y - EPSILON).ceil() as i64);
+
+        (y0..y1).any(|y| (x0..x1).any(|x| self.filled(x, y)))
+    }
+
+    /// How far `bounds` gets along `axis`, `0` for x and `1` for y, when moved by `delta`.
+    fn sweep(&self, bounds: Vec4, delta: f32, axis: usize) -> f32
+    {
+        let (cell, origin, size) = (self.cell[axis], self.origin[axis], bounds[axis + 2]);
+        // Steps of at most a cell, so nothing is skipped over.
+        let steps = (delta.abs() / cell).ceil().max(1.0);
+        let step = delta / steps;
+
+        let mut moved = 0.0;
+        for _ in 0..steps as usize
+        {
+            let mut next = bounds;
+            next[axis] += moved + step;
+            if !self.overlaps(next)
+            {
+                moved += step;
+                continue;
+            }
+
+            // Only the row or column just entered can be in the way, stop at its edge.
+            moved = if step > 0.0
+            {
+                let far = (next[axis] + size - origin) / cell;
+                origin + ((far - EPSILON).ceil() - 1.0) * cell - size - bounds[axis]
+            }
+            else
+            {
+                let near = (next[axis] - origin) / cell;
+                origin + ((near + EPSILON).floor() + 1.0) * cell - bounds[axis]
+            };
+            break;
+        }
+        if delta > 0.0 { moved.clamp(0.0, delta) } else { moved.clamp(delta, 0.0) }
+    }
+}
+
+/// Collision against the filled cells of a tile layer, or an IntGrid layer from LDtk.
+///
+/// A cell is filled if it holds any tile or any IntGrid value other than `0`. Boxes
+/// are `(x, y, width, height)` in world units, the way `MapObject::bounds` gives them.
+/// Only `is_solid` works on maps that are not orthogonal, the others find nothing there.
+impl TileMap
+{
+    /// `None` for maps that are not orthogonal, the boxes only line up with square cells.
+    fn cells(&self, name: &str) -> Option<Cells<'_>>
+    {
+        if self.layout != GridLayout::Orthogonal
+        {
+            return None;
+        }
+        let layer = self.layer(name)?;
+        let (offset, width, height) = match layer
+        {
+            Layer::Tiles(layer) => (layer.offset, layer.width, layer.height),
+            Layer::IntGrid(layer) => (layer.offset, layer.width, layer.height),
+            Layer::Objects(_) => return None,
+        };
+
+        Some(Cells {
+            layer,
+            origin: self.position + offset,
+            cell: math::vec2(self.tile_width, self.tile_height),
+            width,
+            height,
+        })
+    }
+
+    /// Whether the world point `p` is in a filled cell of the layer named `layer`.
+    pub fn is_solid(&self, layer: &str, p: Vec2) -> bool
+    {
+        let cell = |offset: Vec2| self.world_to_cell(p - offset);
+        match self.layer(layer)
+        {
+            Some(Layer::Tiles(layer)) =>
+            {
+                cell(layer.offset).is_some_and(|(x, y)| layer.get(x, y).is_some())
+            },
+            Some(Layer::IntGrid(layer)) =>
+            {
+                cell(layer.offset).is_some_and(|(x, y)| layer.get(x, y) != 0)
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether `bounds` overlaps a filled cell of `layer`. Only touching one does not count.
+    pub fn overlaps_solid(&self, layer: &str, bounds: Vec4) -> bool
+    {
+        self.cells(layer).is_some_and(|grid| grid.overlaps(bounds))
+    }
+
+    /// How far `bounds` can move by `delta` before running into the filled cells of
+    /// `layer`, moving along x first and then along y. A box that already overlaps
+    /// filled cells moves freely, so it can get out of them.
+    pub fn move_box(&self, layer: &str, bounds: Vec4, delta: Vec2) -> Vec2
+    {
+        let Some(grid) = self.cells(layer)
+        else
+        {
+            return delta;
+        };
+        if grid.overlaps(bounds)
+        {
+            return delta;
+        }
+
+        let x = grid.sweep(bounds, delta.x, 0);
+        let y = grid.sweep(bounds + math::vec4(x, 0.0, 0.0, 0.0), delta.y, 1);
+        math::vec2(x, y)
+    }
+
+    /// Rectangles covering the filled cells of `layer`, with neighbouring cells merged
+    /// into as few as possible. Handy as lighting `Occluder`s or for a physics engine.
+    pub fn solid_rects(&self, layer: &str) -> Vec<Rect>
+    {
+        let Some(grid) = self.cells(layer)
+        else
+        {
+            return Vec::new();
+        };
+
+        // Runs of filled cells `(x0, x1, y0)` that continue in the row being looked at.
+        let mut open: Vec<(u32, u32, u32)> = Vec::new();
+        let mut rects = Vec::new();
+        for y in 0..=grid.height
+        {
+            let mut runs = Vec::new();
+            let mut x = 0;
+            while y < grid.height && x < grid.width
+            {
+                if !grid.filled(x as i64, y as i64)
+                {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < grid.width && grid.filled(x as i64, y as i64)
+                {
+                    x += 1;
+                }
+                runs.push((start, x));
+            }
+
+            let mut next = Vec::new();
+            for (x0, x1, y0) in open.drain(..)
+            {
+                match runs.iter().position(|run| *run == (x0, x1))
+                {
+                    Some(index) =>
+                    {
+                        runs.remove(index);
+                        next.push((x0, x1, y0));
+                    },
+                    None =>
+                    {
+                        let (x, w) = (x0 as f32 * grid.cell.x, (x1 - x0) as f32 * grid.cell.x);
+                        let (y, h) = (y0 as f32 * grid.cell.y, (y - y0) as f32 * grid.cell.y);
+                        rects.push(Rect::new(grid.origin.x + x, grid.origin.y + y, w, h));
+                    },
+                }
+            }
+            next.extend(runs.into_iter().map(|(x0, x1)| (x0, x1, y)));
+            open = next;
+        }
+        rects
+    }
+}
@@ -0,0 +1,463 @@
+//! Reads LDtk projects (`.ldtk`), including levels saved in separate `.ldtkl` files.
+//!
+//! Every level becomes its own `TileMap`. LDtk measures from the top left with y
+//! pointing down, so levels and everything in them are flipped over to tori's
+//! bottom left origin, one LDtk pixel being one world unit.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::Error,
+    graphics::tilemap::{
+        tiled::{self, Textures},
+        IntGridLayer, Layer, MapObject, ObjectLayer, ObjectShape, Properties, Property, Tile,
+        TileLayer, TileMap, Tileset,
+    },
+    json::Json,
+    math,
+};
+
+fn error(message: String) -> Error
+{
+    Error::Format(format!("LDtk: {}", message))
+}
+
+fn missing(what: &str, field: &str) -> Error
+{
+    error(format!("{} has no valid \"{}\"", what, field))
+}
+
+fn get_u32(json: &Json, what: &str, name: &str) -> Result<u32, Error>
+{
+    json.get(name).and_then(Json::as_u32).ok_or_else(|| missing(what, name))
+}
+
+fn get_f32(json: &Json, what: &str, name: &str) -> Result<f32, Error>
+{
+    json.get(name).and_then(Json::as_f32).ok_or_else(|| missing(what, name))
+}
+
+fn get_str<'a>(json: &'a Json, name: &str) -> &'a str
+{
+    json.get(name).and_then(Json::as_str).unwrap_or_default()
+}
+
+fn get_array<'a>(json: &'a Json, name: &str) -> &'a [Json]
+{
+    json.get(name).and_then(Json::as_array).unwrap_or_default()
+}
+
+/// Two numbers stored as `[x, y]`.
+fn pair(json: &Json, what: &str, name: &str) -> Result<(f32, f32), Error>
+{
+    match json.get(name).and_then(Json::as_array)
+    {
+        Some([x, y]) => x.as_f32().zip(y.as_f32()).ok_or_else(|| missing(what, name)),
+        _ => Err(missing(what, name)),
+    }
+}
+
+struct TilesetDef
+{
+    name:       String,
+    image:      PathBuf,
+    grid:       u32,
+    spacing:    u32,
+    padding:    u32,
+    columns:    u32,
+    rows:       u32,
+    /// The custom data of tiles, as a `"data"` property.
+    properties: HashMap<u32, Properties>,
+}
+
+struct Loader
+{
+    dir:      PathBuf,
+    textures: Textures,
+    tilesets: HashMap<u32, TilesetDef>,
+    /// The names of the IntGrid values, by layer definition.
+    names:    HashMap<u32, HashMap<u32, String>>,
+    /// Ids given to entities, by their iid, so references can point at them.
+    entities: HashMap<String, u32>,
+    /// Height of the level being loaded, in pixels.
+    height:   f32,
+    grid:     u32,
+}
+
+pub(super) fn load(path: &Path) -> Result<Vec<TileMap>, Error>
+{
+    let project = Json::parse(&std::fs::read_to_string(path)?)?;
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+    // Projects with several worlds keep their levels in `worlds` instead.
+    let mut worlds = vec![(get_str(&project, "worldLayout"), get_array(&project, "levels"))];
+    for world in get_array(&project, "worlds")
+    {
+        worlds.push((get_str(world, "worldLayout"), get_array(world, "levels")));
+    }
+
+    // With "Save levels separately" only the project knows where levels are, while
+    // their layers are in `.ldtkl` files next to it.
+    let mut levels = Vec::new();
+    for (layout, list) in worlds
+    {
+        for level in list
+        {
+            let external = match level.get("externalRelPath").and_then(Json::as_str)
+            {
+                Some(file) => Some(Json::parse(&std::fs::read_to_string(dir.join(file))?)?),
+                None => None,
+            };
+            levels.push((layout, level, external));
+        }
+    }
+
+    let defs = project.get("defs").ok_or_else(|| missing("the project", "defs"))?;
+    let mut loader = Loader {
+        tilesets: tileset_defs(defs, &dir)?,
+        names: int_grid_names(defs),
+        entities: HashMap::new(),
+        dir,
+        textures: Textures::default(),
+        height: 0.0,
+        grid: project.get("defaultGridSize").and_then(Json::as_u32).unwrap_or(16),
+    };
+
+    for (_, level, external) in levels.iter()
+    {
+        let level = external.as_ref().unwrap_or(level);
+        for layer in get_array(level, "layerInstances")
+        {
+            for entity in get_array(layer, "entityInstances")
+            {
+                let id = loader.entities.len() as u32 + 1;
+                loader.entities.insert(get_str(entity, "iid").to_string(), id);
+            }
+        }
+    }
+
+    // Linear layouts leave placing the levels to the reader, one after the other.
+    let mut next = (0.0, 0.0);
+    let mut maps = Vec::new();
+    for (layout, level, external) in levels.iter()
+    {
+        let what = format!("level \"{}\"", get_str(level, "identifier"));
+        let size = (get_f32(level, &what, "pxWid")?, get_f32(level, &what, "pxHei")?);
+        let (x, y) = match *layout
+        {
+            "LinearHorizontal" =>
+            {
+                next.0 += size.0;
+                (next.0 - size.0, 0.0)
+            },
+            "LinearVertical" =>
+            {
+                next.1 += size.1;
+                (0.0, next.1 - size.1)
+            },
+            _ => (get_f32(level, &what, "worldX")?, get_f32(level, &what, "worldY")?),
+        };
+
+        let mut map = loader.level(external.as_ref().unwrap_or(level), size)?;
+        map.set_position(math::vec2(x, -(y + size.1)));
+        maps.push(map);
+    }
+    Ok(maps)
+}
+
+fn tileset_defs(defs: &Json, dir: &Path) -> Result<HashMap<u32, TilesetDef>, Error>
+{
+    let mut tilesets = HashMap::new();
+    for def in get_array(defs, "tilesets")
+    {
+        let name = get_str(def, "identifier").to_string();
+        let what = format!("tileset \"{}\"", name);
+        // Embedded ones such as LDtk's own icons have no image file.
+        let Some(image) = def.get("relPath").and_then(Json::as_str)
+        else
+        {
+            continue;
+        };
+
+        let grid = get_u32(def, &what, "tileGridSize")?;
+        let spacing = def.get("spacing").and_then(Json::as_u32).unwrap_or(0);
+        let padding = def.get("padding").and_then(Json::as_u32).unwrap_or(0);
+        let cells =
+            |size: u32| (size.saturating_sub(padding * 2) + spacing) / (grid + spacing).max(1);
+
+        let mut properties = HashMap::new();
+        for data in get_array(def, "customData")
+        {
+            let mut props = Properties::new();
+            props.insert("data".to_string(), Property::String(get_str(data, "data").to_string()));
+            properties.insert(get_u32(data, &what, "tileId")?, props);
+        }
+
+        tilesets.insert(get_u32(def, &what, "uid")?, TilesetDef {
+            image: dir.join(image),
+            grid,
+            spacing,
+            padding,
+            columns: cells(get_u32(def, &what, "pxWid")?),
+            rows: cells(get_u32(def, &what, "pxHei")?),
+            properties,
+            name,
+        });
+    }
+    Ok(tilesets)
+}
+
+fn int_grid_names(defs: &Json) -> HashMap<u32, HashMap<u32, String>>
+{
+    let mut names = HashMap::new();
+    for layer in get_array(defs, "layers")
+    {
+        let values = get_array(layer, "intGridValues").iter().filter_map(|v| {
+            let name = v.get("identifier").and_then(Json::as_str)?;
+            Some((v.get("value").and_then(Json::as_u32)?, name.to_string()))
+        });
+        if let Some(uid) = layer.get("uid").and_then(Json::as_u32)
+        {
+            names.insert(uid, values.collect());
+        }
+    }
+    names
+}
+
+impl Loader
+{
+    fn level(&mut self, level: &Json, (width, height): (f32, f32)) -> Result<TileMap, Error>
+    {
+        let name = get_str(level, "identifier");
+        let what = format!("level \"{}\"", name);
+        let layers = level
+            .get("layerInstances")
+            .and_then(Json::as_array)
+            .ok_or_else(|| missing(&what, "layerInstances"))?;
+
+        let grid = layers.first().and_then(|l| l.get("__gridSize")).and_then(Json::as_u32);
+        self.grid = grid.unwrap_or(self.grid);
+        self.height = height;
+        let cells = |size: f32| (size / self.grid as f32).ceil() as u32;
+
+        let mut map = TileMap::new(cells(width), cells(height), self.grid as f32, self.grid as f32);
+        map.name = name.to_string();
+        map.background = level.get("__bgColor").and_then(Json::as_str).and_then(tiled::color);
+        map.properties = self.fields(level);
+
+        // The first layer is the one on top.
+        let mut first_ids = HashMap::new();
+        for layer in layers.iter().rev()
+        {
+            self.layer(&mut map, layer, &mut first_ids)?;
+        }
+        Ok(map)
+    }
+
+    fn layer(
+        &mut self,
+        map: &mut TileMap,
+        layer: &Json,
+        first_ids: &mut HashMap<u32, u32>,
+    ) -> Result<(), Error>
+    {
+        let name = get_str(layer, "__identifier");
+        let what = format!("layer \"{}\"", name);
+        let grid = get_u32(layer, &what, "__gridSize")?;
+        if grid != self.grid
+        {
+            let message = format!("{} uses a {}px grid, the level {}px", what, grid, self.grid);
+            return Err(error(message));
+        }
+
+        let (width, height) = (get_u32(layer, &what, "__cWid")?, get_u32(layer, &what, "__cHei")?);
+        let offset = math::vec2(
+            get_f32(layer, &what, "__pxTotalOffsetX")?,
+            -get_f32(layer, &what, "__pxTotalOffsetY")?,
+        );
+        let visible = layer.get("visible").and_then(Json::as_bool).unwrap_or(true);
+        let opacity = layer.get("__opacity").and_then(Json::as_f32).unwrap_or(1.0);
+
+        // IntGrid and auto layers can carry tiles too, those go in tile layers of the
+        // same name. Tiles stacked in one cell are spread over several of them.
+        let tiles = get_array(layer, "gridTiles").iter().chain(get_array(layer, "autoLayerTiles"));
+        let mut stack: Vec<TileLayer> = Vec::new();
+        if let Some(uid) = layer.get("__tilesetDefUid").and_then(Json::as_u32)
+        {
+            let first = match first_ids.get(&uid)
+            {
+                Some(first) => *first,
+                None =>
+                {
+                    let first = self.tileset(map, uid, &what)?;
+                    first_ids.insert(uid, first);
+                    first
+                },
+            };
+
+            for tile in tiles
+            {
+                let (x, y) = pair(tile, &what, "px")?;
+                let (x, y) = ((x / grid as f32) as u32, (y / grid as f32) as u32);
+                let flips = tile.get("f").and_then(Json::as_u32).unwrap_or(0);
+                let tile = Tile {
+                    id: first + get_u32(tile, &what, "t")?,
+                    flip_x: flips & 1 != 0,
+                    flip_y: flips & 2 != 0,
+                    flip_diagonal: false,
+                };
+
+                let y = height.saturating_sub(y + 1);
+                let free = stack.iter().position(|l| l.get(x, y).is_none());
+                let index = free.unwrap_or_else(|| {
+                    let mut tiles = TileLayer::new(name, width, height);
+                    tiles.visible = visible;
+                    tiles.opacity = opacity;
+                    tiles.offset = offset;
+                    stack.push(tiles);
+                    stack.len() - 1
+                });
+                stack[index].set(x, y, Some(tile));
+            }
+        }
+        map.layers.extend(stack.into_iter().map(Layer::Tiles));
+
+        match get_str(layer, "__type")
+        {
+            "IntGrid" =>
+            {
+                let mut grid = IntGridLayer::new(name, width, height);
+                grid.visible = visible;
+                grid.offset = offset;
+                let def = layer.get("layerDefUid").and_then(Json::as_u32);
+                grid.names = def.and_then(|uid| self.names.get(&uid)).cloned().unwrap_or_default();
+                for (i, value) in get_array(layer, "intGridCsv").iter().enumerate()
+                {
+                    let (x, row) = (i as u32 % width.max(1), i as u32 / width.max(1));
+                    grid.set(x, height.saturating_sub(row + 1), value.as_u32().unwrap_or(0));
+                }
+                map.layers.push(Layer::IntGrid(grid));
+            },
+            "Entities" =>
+            {
+                let layer_height = (height * grid) as f32;
+                let objects = get_array(layer, "entityInstances")
+                    .iter()
+                    .map(|entity| self.entity(entity, layer_height, offset))
+                    .collect::<Result<_, _>>()?;
+                map.layers.push(Layer::Objects(ObjectLayer {
+                    name: name.to_string(),
+                    visible,
+                    objects,
+                    properties: Properties::new(),
+                }));
+            },
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Adds the tileset with the definition `uid` to `map`, returning its first id.
+    fn tileset(&mut self, map: &mut TileMap, uid: u32, what: &str) -> Result<u32, Error>
+    {
+        let def = self
+            .tilesets
+            .get(&uid)
+            .ok_or_else(|| error(format!("{} uses a tileset without an image", what)))?;
+
+        let texture = self.textures.get(&def.image)?;
+        let mut tileset = Tileset::new(&def.name, &texture, def.grid, def.grid);
+        tileset.columns = def.columns;
+        tileset.tile_count = def.columns * def.rows;
+        tileset.margin = def.padding;
+        tileset.spacing = def.spacing;
+        tileset.properties = def.properties.clone();
+        Ok(map.add_tileset(tileset))
+    }
+
+    /// Entities become rectangle objects covering their size.
+    fn entity(
+        &self,
+        entity: &Json,
+        layer_height: f32,
+        offset: math::Vec2,
+    ) -> Result<MapObject, Error>
+    {
+        let name = get_str(entity, "__identifier");
+        let what = format!("entity \"{}\"", name);
+        let (x, y) = pair(entity, &what, "px")?;
+        let pivot = pair(entity, &what, "__pivot").unwrap_or((0.0, 0.0));
+        let size = math::vec2(get_f32(entity, &what, "width")?, get_f32(entity, &what, "height")?);
+
+        // `px` is where the pivot is, `Rect` objects start at the top left.
+        let corner = math::vec2(x - pivot.0 * size.x, layer_height - (y - pivot.1 * size.y));
+        Ok(MapObject {
+            id: self.entities.get(get_str(entity, "iid")).copied().unwrap_or(0),
+            name: name.to_string(),
+            class: name.to_string(),
+            position: corner + offset,
+            rotation: 0.0,
+            shape: ObjectShape::Rect {
+                size,
+            },
+            visible: true,
+            properties: self.fields(entity),
+        })
+    }
+
+    /// The custom fields of a level or an entity.
+    fn fields(&self, json: &Json) -> Properties
+    {
+        let mut properties = Properties::new();
+        for field in get_array(json, "fieldInstances")
+        {
+            let value = field.get("__value").unwrap_or(&Json::Null);
+            if let Some(value) = self.field(get_str(field, "__type"), value)
+            {
+                properties.insert(get_str(field, "__identifier").to_string(), value);
+            }
+        }
+        properties
+    }
+
+    /// Arrays become classes with members `"0"`, `"1"` and so on, points classes with
+    /// `x` and `y` at the center of their cell. Tile fields are left out.
+    fn field(&self, kind: &str, value: &Json) -> Option<Property>
+    {
+        if let Some(kind) = kind.strip_prefix("Array<").and_then(|k| k.strip_suffix('>'))
+        {
+            let items = value.as_array()?.iter().enumerate();
+            let items = items.filter_map(|(i, v)| Some((i.to_string(), self.field(kind, v)?)));
+            return Some(Property::Class(items.collect()));
+        }
+
+        Some(match kind
+        {
+            _ if value.is_null() => return None,
+            "Int" => Property::Int(value.as_i64()?),
+            "Float" => Property::Float(value.as_f64()?),
+            "Bool" => Property::Bool(value.as_bool()?),
+            "Color" => Property::Color(tiled::color(value.as_str()?)?),
+            "FilePath" => Property::File(self.dir.join(value.as_str()?)),
+            "EntityRef" =>
+            {
+                let iid = value.get("entityIid")?.as_str()?;
+                Property::Object(self.entities.get(iid).copied().unwrap_or(0))
+            },
+            "Point" =>
+            {
+                let grid = self.grid as f64;
+                let x = (value.get("cx")?.as_f64()? + 0.5) * grid;
+                let y = self.height as f64 - (value.get("cy")?.as_f64()? + 0.5) * grid;
+                let point = [("x", x), ("y", y)].map(|(k, v)| (k.to_string(), Property::Float(v)));
+                Property::Class(point.into_iter().collect())
+            },
+            "Tile" => return None,
+            // Strings, multiple lines and enum values.
+            _ => Property::String(value.as_str()?.to_string()),
+        })
+    }
+}
@@ -0,0 +1,740 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::Error,
+    graphics::{
+        blend::BlendMode, camera::Camera2D, material::Material, shape::Rect, texture::Texture,
+        tilemap::render::Cache,
+    },
+    math::{
+        self,
+        grid::{Cell, Grid, GridLayout, StaggerAxis},
+        Vec2, Vec4,
+    },
+};
+
+/// Custom properties of maps, layers, tiles and objects, by name.
+pub type Properties = HashMap<String, Property>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Property
+{
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// RGBA from `0.0` to `1.0`.
+    Color(Vec4),
+    /// Resolved relative to the file the property was loaded from.
+    File(PathBuf),
+    /// The id of a `MapObject`, `0` for none.
+    Object(u32),
+    /// The members of a custom class.
+    Class(Properties),
+}
+
+/// A tile placed in a layer, by global id across all tilesets of the map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Tile
+{
+    pub id:            u32,
+    pub flip_x:        bool,
+    pub flip_y:        bool,
+    /// Swaps the tile's x and y axes, which together with the other flips rotates it.
+    pub flip_diagonal: bool,
+}
+
+impl Tile
+{
+    const FLIP_X: u32 = 0x8000_0000;
+    const FLIP_Y: u32 = 0x4000_0000;
+    const FLIP_DIAGONAL: u32 = 0x2000_0000;
+    /// Also sets the hexagonal rotation bit, which is ignored.
+    const FLAGS: u32 = 0xF000_0000;
+
+    pub fn new(id: u32) -> Self
+    {
+        Self {
+            id,
+            flip_x: false,
+            flip_y: false,
+            flip_diagonal: false,
+        }
+    }
+
+    /// Reads a global id with the flip flags in its top bits, `0` is an empty cell.
+    pub fn from_gid(gid: u32) -> Option<Self>
+    {
+        let id = gid & !Self::FLAGS;
+        (id != 0).then_some(Self {
+            id,
+            flip_x: gid & Self::FLIP_X != 0,
+            flip_y: gid & Self::FLIP_Y != 0,
+            flip_diagonal: gid & Self::FLIP_DIAGONAL != 0,
+        })
+    }
+}
+
+/// One frame of an animated tile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnimationFrame
+{
+    /// Id of the tile shown, local to the tileset.
+    pub tile:     u32,
+    /// In seconds.
+    pub duration: f32,
+}
+
+/// A grid of equally sized tiles cut out of one texture.
+#[derive(Clone)]
+pub struct Tileset
+{
+    pub name:        String,
+    /// The global id of the tileset's first tile, set by `TileMap::add_tileset`.
+    pub first_id:    u32,
+    pub texture:     Texture,
+    pub tile_width:  u32,
+    pub tile_height: u32,
+    pub columns:     u32,
+    pub tile_count:  u32,
+    /// Pixels around the tiles at the edges of the texture.
+    pub margin:      u32,
+    /// Pixels between neighbouring tiles.
+    pub spacing:     u32,
+    /// Where tiles are drawn relative to their cell, in pixels.
+    pub offset:      Vec2,
+    /// Animations by local tile id, the tile is replaced by the frames when drawn.
+    pub animations:  HashMap<u32, Vec<AnimationFrame>>,
+    /// Properties by local tile id.
+    pub properties:  HashMap<u32, Properties>,
+}
+
+impl Tileset
+{
+    /// A tileset covering all of `texture` without margin or spacing.
+    pub fn new(name: &str, texture: &Texture, tile_width: u32, tile_height: u32) -> Self
+    {
+        let columns = texture.width() / tile_width.max(1);
+        let rows = texture.height() / tile_height.max(1);
+
+        Self {
+            name: name.to_string(),
+            first_id: 1,
+            texture: texture.clone(),
+            tile_width,
+            tile_height,
+            columns,
+            tile_count: columns * rows,
+            margin: 0,
+            spacing: 0,
+            offset: math::vec2(0.0, 0.0),
+            animations: HashMap::new(),
+            properties: HashMap::new(),
+        }
+    }
+
+    /// Whether the global `id` belongs to this tileset.
+    pub fn contains(&self, id: u32) -> bool
+    {
+        id >= self.first_id && id - self.first_id < self.tile_count
+    }
+
+    /// `(u0, v0, u1, v1)` of the tile with the local `id`.
+    pub fn uv(&self, id: u32) -> Vec4
+    {
+        let columns = self.columns.max(1);
+        let (col, row) = (id % columns, id / columns);
+        let x = self.margin + col * (self.tile_width + self.spacing);
+        let y = self.margin + row * (self.tile_height + self.spacing);
+
+        let (tw, th) = (self.texture.width() as f32, self.texture.height() as f32);
+        math::vec4(
+            x as f32 / tw,
+            1.0 - (y + self.tile_height) as f32 / th,
+            (x + self.tile_width) as f32 / tw,
+            1.0 - y as f32 / th,
+        )
+    }
+}
+
+/// A grid of tiles, `(0, 0)` being the bottom left cell.
+#[derive(Clone, Debug)]
+pub struct TileLayer
+{
+    pub name:       String,
+    pub width:      u32,
+    pub height:     u32,
+    pub visible:    bool,
+    pub opacity:    f32,
+    /// Multiplies the color of every tile.
+    pub tint:       Vec4,
+    /// Where the layer is drawn relative to the map, in world units.
+    pub offset:     Vec2,
+    pub properties: Properties,
+    tiles:          Vec<Option<Tile>>,
+}
+
+impl TileLayer
+{
+    pub fn new(name: &str, width: u32, height: u32) -> Self
+    {
+        Self {
+            name: name.to_string(),
+            width,
+            height,
+            visible: true,
+            opacity: 1.0,
+            tint: math::vec4(1.0, 1.0, 1.0, 1.0),
+            offset: math::vec2(0.0, 0.0),
+            properties: Properties::new(),
+            tiles: vec![None; (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Option<Tile>
+    {
+        if x >= self.width || y >= self.height
+        {
+            return None;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+
+    /// Only for building layers, placed layers are changed through `TileMap::set_tile`.
+    pub(crate) fn set(&mut self, x: u32, y: u32, tile: Option<Tile>)
+    {
+        if x < self.width && y < self.height
+        {
+            self.tiles[(y * self.width + x) as usize] = tile;
+        }
+    }
+}
+
+/// A grid of numbers painted in LDtk, usually marking what is solid or what kind of
+/// ground a cell is. `0` is an empty cell, `(0, 0)` the bottom left one.
+#[derive(Clone, Debug)]
+pub struct IntGridLayer
+{
+    pub name:       String,
+    pub width:      u32,
+    pub height:     u32,
+    pub visible:    bool,
+    /// Where the layer is relative to the map, in world units.
+    pub offset:     Vec2,
+    /// What the values stand for, such as `"wall"` or `"water"`.
+    pub names:      HashMap<u32, String>,
+    pub properties: Properties,
+    values:         Vec<u32>,
+}
+
+impl IntGridLayer
+{
+    pub fn new(name: &str, width: u32, height: u32) -> Self
+    {
+        Self {
+            name: name.to_string(),
+            width,
+            height,
+            visible: true,
+            offset: math::vec2(0.0, 0.0),
+            names: HashMap::new(),
+            properties: Properties::new(),
+            values: vec![0; (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u32
+    {
+        if x >= self.width || y >= self.height
+        {
+            return 0;
+        }
+        self.values[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, value: u32)
+    {
+        if x < self.width && y < self.height
+        {
+            self.values[(y * self.width + x) as usize] = value;
+        }
+    }
+
+    /// The value named `name` in `names`.
+    pub fn value(&self, name: &str) -> Option<u32>
+    {
+        self.names.iter().find(|(_, n)| *n == name).map(|(value, _)| *value)
+    }
+}
+
+/// The outline of a `MapObject`, relative to its position and in world units.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectShape
+{
+    Point,
+    /// Reaching right and down from the position, like in Tiled.
+    Rect
+    {
+        size: Vec2
+    },
+    /// The ellipse inside a rectangle placed like `Rect`.
+    Ellipse
+    {
+        size: Vec2
+    },
+    Polygon(Vec<Vec2>),
+    Polyline(Vec<Vec2>),
+    /// A tile reaching right and up from the position.
+    Tile
+    {
+        tile: Tile, size: Vec2
+    },
+}
+
+/// Something placed in an object layer, such as a spawn point or a trigger area.
+#[derive(Clone, Debug)]
+pub struct MapObject
+{
+    pub id:         u32,
+    pub name:       String,
+    /// What kind of object this is, Tiled's class or type.
+    pub class:      String,
+    /// Relative to the map's position.
+    pub position:   Vec2,
+    /// In radians counterclockwise around `position`.
+    pub rotation:   f32,
+    pub shape:      ObjectShape,
+    pub visible:    bool,
+    pub properties: Properties,
+}
+
+impl MapObject
+{
+    /// The outline in world units, rotation included. Ellipses are approximated by a polygon.
+    pub fn points(&self) -> Vec<Vec2>
+    {
+        let corners = |x0: f32, y0: f32, x1: f32, y1: f32| {
+            vec![math::vec2(x0, y0), math::vec2(x1, y0), math::vec2(x1, y1), math::vec2(x0, y1)]
+        };
+        let local = match &self.shape
+        {
+            ObjectShape::Point => vec![math::vec2(0.0, 0.0)],
+            ObjectShape::Rect {
+                size,
+            } => corners(0.0, -size.y, size.x, 0.0),
+            ObjectShape::Tile {
+                size, ..
+            } => corners(0.0, 0.0, size.x, size.y),
+            ObjectShape::Ellipse {
+                size,
+            } =>
+            {
+                let half = size / 2.0;
+                (0..16)
+                    .map(|i| {
+                        let (sin, cos) = (i as f32 / 16.0 * std::f32::consts::TAU).sin_cos();
+                        math::vec2(half.x + cos * half.x, -half.y + sin * half.y)
+                    })
+                    .collect()
+            },
+            ObjectShape::Polygon(points) | ObjectShape::Polyline(points) => points.clone(),
+        };
+
+        let (sin, cos) = self.rotation.sin_cos();
+        local
+            .into_iter()
+            .map(|p| self.position + math::vec2(p.x * cos - p.y * sin, p.x * sin + p.y * cos))
+            .collect()
+    }
+
+    /// `(x, y, width, height)` of the axis aligned box around `points`.
+    pub fn bounds(&self) -> Vec4
+    {
+        let points = self.points();
+        let min = points.iter().fold(math::vec2(f32::MAX, f32::MAX), |m, p| m.inf(p));
+        let max = points.iter().fold(math::vec2(f32::MIN, f32::MIN), |m, p| m.sup(p));
+        math::vec4(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    /// A `Rect` covering rectangle and tile objects, `None` for other shapes.
+    pub fn to_rect(&self) -> Option<Rect>
+    {
+        // Objects turn around their position, `Rect`s around their center.
+        let (size, center) = match self.shape
+        {
+            ObjectShape::Rect {
+                size,
+            } => (size, math::vec2(size.x, -size.y) / 2.0),
+            ObjectShape::Tile {
+                size, ..
+            } => (size, size / 2.0),
+            _ => return None,
+        };
+
+        let (sin, cos) = self.rotation.sin_cos();
+        let center = self.position
+            + math::vec2(center.x * cos - center.y * sin, center.x * sin + center.y * cos);
+        let mut rect = Rect::new(center.x - size.x / 2.0, center.y - size.y / 2.0, size.x, size.y);
+        rect.entity.rotation = self.rotation;
+        Some(rect)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjectLayer
+{
+    pub name:       String,
+    pub visible:    bool,
+    pub objects:    Vec<MapObject>,
+    pub properties: Properties,
+}
+
+impl ObjectLayer
+{
+    pub fn object(&self, name: &str) -> Option<&MapObject>
+    {
+        self.objects.iter().find(|o| o.name == name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Layer
+{
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+    IntGrid(IntGridLayer),
+}
+
+impl Layer
+{
+    pub fn name(&self) -> &str
+    {
+        match self
+        {
+            Layer::Tiles(layer) => &layer.name,
+            Layer::Objects(layer) => &layer.name,
+            Layer::IntGrid(layer) => &layer.name,
+        }
+    }
+}
+
+/// Layers of tiles drawn from `Tileset`s, and the objects placed between them.
+///
+/// Tiles are uploaded once in chunks and only the chunks in view are drawn.
+/// The vertices are rebuilt for chunks changed through `set_tile` and for the
+/// whole map when it is moved. Animated tiles are drawn separately every frame.
+///
+/// Cells are laid out as the map's `GridLayout` says. On maps that are not orthogonal
+/// tiles overlap their neighbors, so they are drawn from the back in bands across the map.
+pub struct TileMap
+{
+    /// The file name for Tiled maps, the level identifier for LDtk ones.
+    pub name:        String,
+    /// Size in tiles.
+    pub width:       u32,
+    pub height:      u32,
+    /// Size of a cell in world units.
+    pub tile_width:  f32,
+    pub tile_height: f32,
+    pub background:  Option<Vec4>,
+    pub properties:  Properties,
+
+    pub(crate) position: Vec2,
+    pub(crate) layout:   GridLayout,
+    pub(crate) tilesets: Vec<Tileset>,
+    pub(crate) layers:   Vec<Layer>,
+    pub(crate) time:     f32,
+    pub(crate) material: Option<Material>,
+    pub(crate) blend:    BlendMode,
+    pub(crate) cache:    RefCell<Cache>,
+}
+
+impl TileMap
+{
+    pub fn new(width: u32, height: u32, tile_width: f32, tile_height: f32) -> Self
+    {
+        Self {
+            name: String::new(),
+            width,
+            height,
+            tile_width,
+            tile_height,
+            background: None,
+            properties: Properties::new(),
+            position: math::vec2(0.0, 0.0),
+            layout: GridLayout::Orthogonal,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            time: 0.0,
+            material: None,
+            blend: BlendMode::Alpha,
+            cache: RefCell::default(),
+        }
+    }
+
+    /// Loads a Tiled map, `.tmx` or `.tmj`, with the tilesets it uses.
+    pub fn from_tiled<P: AsRef<Path>>(path: P) -> Result<Self, Error>
+    {
+        crate::graphics::tilemap::tiled::load(path.as_ref())
+    }
+
+    /// Loads every level of an LDtk project, each placed where it is in the project's world.
+    pub fn from_ldtk<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, Error>
+    {
+        crate::graphics::tilemap::ldtk::load(path.as_ref())
+    }
+
+    /// Adds `tileset` behind the ones already added, setting its `first_id`.
+    pub fn add_tileset(&mut self, mut tileset: Tileset) -> u32
+    {
+        tileset.first_id = self.tilesets.last().map_or(1, |t| t.first_id + t.tile_count);
+        let first = tileset.first_id;
+        self.tilesets.push(tileset);
+        self.invalidate();
+        first
+    }
+
+    /// Adds a layer on top of the others.
+    pub fn add_layer(&mut self, layer: Layer)
+    {
+        self.layers.push(layer);
+        self.invalidate();
+    }
+
+    pub fn tilesets(&self) -> &[Tileset]
+    {
+        &self.tilesets
+    }
+
+    /// The tileset holding the global tile `id`, and the id within it.
+    pub fn tileset_of(&self, id: u32) -> Option<(usize, u32)>
+    {
+        let index = self.tilesets.iter().position(|t| t.contains(id))?;
+        Some((index, id - self.tilesets[index].first_id))
+    }
+
+    pub fn layers(&self) -> &[Layer]
+    {
+        &self.layers
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer>
+    {
+        self.layers.iter().find(|l| l.name() == name)
+    }
+
+    pub fn tile_layer(&self, name: &str) -> Option<&TileLayer>
+    {
+        self.layers.iter().find_map(|l| match l
+        {
+            Layer::Tiles(layer) if layer.name == name => Some(layer),
+            _ => None,
+        })
+    }
+
+    pub fn object_layer(&self, name: &str) -> Option<&ObjectLayer>
+    {
+        self.layers.iter().find_map(|l| match l
+        {
+            Layer::Objects(layer) if layer.name == name => Some(layer),
+            _ => None,
+        })
+    }
+
+    pub fn int_grid_layer(&self, name: &str) -> Option<&IntGridLayer>
+    {
+        self.layers.iter().find_map(|l| match l
+        {
+            Layer::IntGrid(layer) if layer.name == name => Some(layer),
+            _ => None,
+        })
+    }
+
+    /// Objects of all object layers.
+    pub fn objects(&self) -> impl Iterator<Item = &MapObject>
+    {
+        self.layers.iter().flat_map(|l| match l
+        {
+            Layer::Objects(layer) => layer.objects.as_slice(),
+            _ => &[],
+        })
+    }
+
+    /// Changes a tile layer's settings such as `visible` or `tint`, without touching its tiles.
+    pub fn tile_layer_mut(&mut self, index: usize) -> Option<&mut TileLayer>
+    {
+        // Opacity and tint are baked into the vertices too.
+        self.invalidate();
+        match self.layers.get_mut(index)
+        {
+            Some(Layer::Tiles(layer)) => Some(layer),
+            _ => None,
+        }
+    }
+
+    pub fn object_layer_mut(&mut self, index: usize) -> Option<&mut ObjectLayer>
+    {
+        match self.layers.get_mut(index)
+        {
+            Some(Layer::Objects(layer)) => Some(layer),
+            _ => None,
+        }
+    }
+
+    pub fn int_grid_layer_mut(&mut self, index: usize) -> Option<&mut IntGridLayer>
+    {
+        match self.layers.get_mut(index)
+        {
+            Some(Layer::IntGrid(layer)) => Some(layer),
+            _ => None,
+        }
+    }
+
+    /// Places `tile` in cell `(x, y)` of the tile layer at `layer`.
+    pub fn set_tile(&mut self, layer: usize, x: u32, y: u32, tile: Option<Tile>)
+    {
+        if let Some(Layer::Tiles(tiles)) = self.layers.get_mut(layer)
+        {
+            tiles.set(x, y, tile);
+            let chunk = self.chunk_of(x, y);
+            self.cache.get_mut().invalidate(layer, chunk);
+        }
+    }
+
+    /// Where the bottom left corner of the map is in the world.
+    pub fn position(&self) -> Vec2
+    {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vec2)
+    {
+        self.position = position;
+        self.invalidate();
+    }
+
+    pub fn layout(&self) -> GridLayout
+    {
+        self.layout
+    }
+
+    /// Lays the cells out differently, keeping their tiles.
+    pub fn set_layout(&mut self, layout: GridLayout)
+    {
+        self.layout = layout;
+        self.invalidate();
+    }
+
+    /// The cells of the map placed in the world, for the helpers of `Grid`.
+    pub fn grid(&self) -> Grid
+    {
+        let cell = math::vec2(self.tile_width, self.tile_height);
+        // Diamonds go down and right from cell `(0, 0)`, which sits at the left corner.
+        let origin = match self.layout
+        {
+            GridLayout::Isometric =>
+            {
+                self.position + math::vec2(0.0, (self.width as f32 - 1.0) * cell.y / 2.0)
+            },
+            _ => self.position,
+        };
+        Grid {
+            layout: self.layout,
+            cell,
+            origin,
+        }
+    }
+
+    /// Width and height of the area the cells cover, in world units.
+    pub fn size(&self) -> Vec2
+    {
+        let (w, h) = (self.tile_width, self.tile_height);
+        let (columns, rows) = (self.width as f32, self.height as f32);
+        let half = math::vec2(w, h) / 2.0;
+        match self.layout
+        {
+            GridLayout::Orthogonal => math::vec2(columns * w, rows * h),
+            GridLayout::Isometric => (columns + rows) * half,
+            GridLayout::Staggered(stagger) => match stagger.axis
+            {
+                StaggerAxis::Y => math::vec2(columns * w + w / 2.0, (rows + 1.0) * h / 2.0),
+                StaggerAxis::X => math::vec2((columns + 1.0) * w / 2.0, rows * h + h / 2.0),
+            },
+            GridLayout::Hexagonal {
+                stagger,
+                side,
+            } => match stagger.axis
+            {
+                StaggerAxis::Y =>
+                {
+                    math::vec2(columns * w + w / 2.0, (rows - 1.0) * (h + side) / 2.0 + h)
+                },
+                StaggerAxis::X =>
+                {
+                    math::vec2((columns - 1.0) * (w + side) / 2.0 + w, rows * h + h / 2.0)
+                },
+            },
+        }
+    }
+
+    /// The cell containing the world point `p`.
+    pub fn world_to_cell(&self, p: Vec2) -> Option<(u32, u32)>
+    {
+        let Cell {
+            x,
+            y,
+        } = self.grid().cell_at(p);
+        let inside = x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32;
+        inside.then_some((x as u32, y as u32))
+    }
+
+    /// The bottom left corner of the box around cell `(x, y)` in the world.
+    pub fn cell_to_world(&self, x: u32, y: u32) -> Vec2
+    {
+        self.cell_center(x, y) - math::vec2(self.tile_width, self.tile_height) / 2.0
+    }
+
+    /// The center of cell `(x, y)` in the world.
+    pub fn cell_center(&self, x: u32, y: u32) -> Vec2
+    {
+        self.grid().center(Cell::new(x as i32, y as i32))
+    }
+
+    /// The cell under the point `p` of the screen, in pixels from the top left.
+    pub fn screen_to_cell(&self, camera: &Camera2D, p: Vec2) -> Option<(u32, u32)>
+    {
+        self.world_to_cell(camera.screen_to_world(p))
+    }
+
+    /// Where the center of cell `(x, y)` is on the screen.
+    pub fn cell_to_screen(&self, camera: &Camera2D, x: u32, y: u32) -> Vec2
+    {
+        camera.world_to_screen(self.cell_center(x, y))
+    }
+
+    /// Advances animated tiles by `dt` seconds.
+    pub fn update(&mut self, dt: f32)
+    {
+        self.time += dt;
+    }
+
+    /// Draws with a user shader instead of the default one, `None` goes back to the default.
+    pub fn set_material(&mut self, material: Option<Material>)
+    {
+        self.material = material;
+    }
+
+    pub fn set_blend_mode(&mut self, blend: BlendMode)
+    {
+        self.blend = blend;
+    }
+
+    fn invalidate(&mut self)
+    {
+        *self.cache.get_mut() = Cache::default();
+    }
+}
@@ -0,0 +1,15 @@
+mod map;
+pub use map::{
+    AnimationFrame, IntGridLayer, Layer, MapObject, ObjectLayer, ObjectShape, Properties,
+    Property, Tile, TileLayer, TileMap, Tileset,
+};
+
+mod collision;
+
+mod ldtk;
+
+mod render;
+
+mod tiled;
+
+mod tmx;
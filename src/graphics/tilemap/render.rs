@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+use crate::{
+    core::renderer::{Pipeline, QuadMesh, Renderer, Vertex, MAX_QUADS},
+    graphics::tilemap::{Layer, Tile, TileLayer, TileMap, Tileset},
+    math::{self, grid::GridLayout, Vec2, Vec4},
+};
+
+/// Cells per side of a chunk, or rows of cells per band on maps that are not orthogonal.
+const CHUNK: u32 = 32;
+
+/// The uploaded vertices of a `TileMap`, built as chunks come into view.
+#[derive(Default)]
+pub(crate) struct Cache
+{
+    /// Chunks by layer index and chunk coordinates, `None` where they are empty.
+    chunks: HashMap<(usize, u32, u32), Option<Chunk>>,
+}
+
+struct Chunk
+{
+    /// `(x0, y0, x1, y1)` in world units, including tiles reaching out of the cells.
+    bounds: Vec4,
+    /// What to draw, in order.
+    items:  Vec<Item>,
+}
+
+enum Item
+{
+    /// Tiles of the tileset at the index.
+    Mesh(usize, QuadMesh),
+    /// A tile drawn every frame because its picture changes.
+    Animated
+    {
+        corner: Vec2, tile: Tile, tileset: usize, color: Vec4
+    },
+}
+
+impl Cache
+{
+    pub(crate) fn invalidate(&mut self, layer: usize, (cx, cy): (u32, u32))
+    {
+        self.chunks.remove(&(layer, cx, cy));
+    }
+}
+
+impl TileMap
+{
+    /// The chunk holding cell `(x, y)`. Orthogonal maps are cut into squares of cells,
+    /// others into bands across the whole map, which are drawn from the back.
+    pub(crate) fn chunk_of(&self, x: u32, y: u32) -> (u32, u32)
+    {
+        if self.layout == GridLayout::Orthogonal
+        {
+            return (x / CHUNK, y / CHUNK);
+        }
+        let bottom = self.cell_to_world(x, y).y - self.position.y;
+        (0, (bottom / (CHUNK as f32 * self.tile_height)).max(0.0) as u32)
+    }
+
+    fn band_count(&self) -> u32
+    {
+        (self.size().y / (CHUNK as f32 * self.tile_height)).ceil() as u32 + 1
+    }
+
+    /// Where the bottom left corner of a tile from `set` goes in cell `(x, y)`.
+    fn tile_corner(&self, layer: &TileLayer, set: &Tileset, x: u32, y: u32) -> Vec2
+    {
+        let corner = self.cell_to_world(x, y) + layer.offset + set.offset;
+        // Like in Tiled, isometric tiles stand centered on their cell while the others
+        // line up with its left side.
+        if self.layout == GridLayout::Isometric
+        {
+            corner + math::vec2((self.tile_width - set.tile_width as f32) / 2.0, 0.0)
+        }
+        else
+        {
+            corner
+        }
+    }
+
+    /// Builds a chunk out of `cells`. Unless `ordered`, tiles are grouped by tileset
+    /// instead of drawn in the order of `cells`.
+    fn build_chunk(&self, layer: &TileLayer, cells: &[(u32, u32)], ordered: bool) -> Option<Chunk>
+    {
+        let color = math::vec4(1.0, 1.0, 1.0, layer.opacity).component_mul(&layer.tint);
+        let mut tiles: Vec<_> = cells
+            .iter()
+            .filter_map(|&(x, y)| {
+                let tile = layer.get(x, y)?;
+                let (tileset, local) = self.tileset_of(tile.id)?;
+                let corner = self.tile_corner(layer, &self.tilesets[tileset], x, y);
+                let animated = self.tilesets[tileset].animations.contains_key(&local);
+                Some((animated, tileset, local, tile, corner))
+            })
+            .collect();
+        if tiles.is_empty()
+        {
+            return None;
+        }
+        if !ordered
+        {
+            tiles.sort_by_key(|(animated, tileset, ..)| (*animated, *tileset));
+        }
+
+        let mut bounds = math::vec4(f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        let mut items = Vec::new();
+        let mut run: Option<(usize, Vec<Vertex>)> = None;
+        for (animated, tileset, local, tile, corner) in tiles
+        {
+            let set = &self.tilesets[tileset];
+            let size = math::vec2(set.tile_width as f32, set.tile_height as f32);
+            bounds = math::vec4(
+                bounds.x.min(corner.x),
+                bounds.y.min(corner.y),
+                bounds.z.max(corner.x + size.x),
+                bounds.w.max(corner.y + size.y),
+            );
+
+            // Bands of large maps hold more tiles than a mesh can, those get split.
+            let full = |(current, vertices): &(usize, Vec<Vertex>)| {
+                *current != tileset || vertices.len() >= MAX_QUADS * 4
+            };
+            if animated || run.as_ref().is_some_and(full)
+            {
+                if let Some((current, vertices)) = run.take()
+                {
+                    items.push(Item::Mesh(current, QuadMesh::new(vertices)));
+                }
+            }
+            if animated
+            {
+                items.push(Item::Animated {
+                    corner,
+                    tile,
+                    tileset,
+                    color,
+                });
+                continue;
+            }
+            let (_, vertices) = run.get_or_insert_with(|| (tileset, Vec::new()));
+            vertices.extend(tile_quad(set, tile, local, corner, color));
+        }
+        if let Some((current, vertices)) = run
+        {
+            items.push(Item::Mesh(current, QuadMesh::new(vertices)));
+        }
+
+        Some(Chunk {
+            bounds,
+            items,
+        })
+    }
+
+    /// Builds the bands of `layer` missing from `cache`, each sorted back to front.
+    fn build_bands(&self, index: usize, layer: &TileLayer, cache: &mut Cache)
+    {
+        let missing = |cache: &Cache, band: u32| !cache.chunks.contains_key(&(index, 0, band));
+        let mut bands: HashMap<u32, Vec<(u32, u32)>> = HashMap::new();
+        for y in 0..layer.height
+        {
+            for x in 0..layer.width
+            {
+                let (_, band) = self.chunk_of(x, y);
+                if missing(cache, band)
+                {
+                    bands.entry(band).or_default().push((x, y));
+                }
+            }
+        }
+
+        for band in 0..self.band_count()
+        {
+            if !missing(cache, band)
+            {
+                continue;
+            }
+            // Higher up is further back, so drawn first.
+            let mut cells: Vec<_> = bands
+                .remove(&band)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(x, y)| (self.cell_center(x, y), (x, y)))
+                .collect();
+            cells.sort_by(|(a, _), (b, _)| b.y.total_cmp(&a.y).then(a.x.total_cmp(&b.x)));
+            let cells: Vec<_> = cells.into_iter().map(|(_, cell)| cell).collect();
+            cache.chunks.insert((index, 0, band), self.build_chunk(layer, &cells, true));
+        }
+    }
+
+    /// The local id of the frame an animated tile shows right now.
+    fn animation_frame(&self, tileset: &Tileset, local: u32) -> u32
+    {
+        let Some(frames) = tileset.animations.get(&local)
+        else
+        {
+            return local;
+        };
+
+        let total: f32 = frames.iter().map(|f| f.duration).sum();
+        if total <= 0.0
+        {
+            return local;
+        }
+        let mut t = self.time.rem_euclid(total);
+        for frame in frames
+        {
+            if t < frame.duration
+            {
+                return frame.tile;
+            }
+            t -= frame.duration;
+        }
+        frames.last().map_or(local, |f| f.tile)
+    }
+
+    fn draw_chunk(&self, renderer: &mut Renderer, pipeline: &Pipeline, chunk: &Chunk)
+    {
+        for item in chunk.items.iter()
+        {
+            match item
+            {
+                Item::Mesh(tileset, mesh) =>
+                {
+                    let texture = &self.tilesets[*tileset].texture;
+                    renderer.draw_mesh(pipeline.clone(), Some(texture), mesh);
+                },
+                Item::Animated {
+                    corner,
+                    tile,
+                    tileset,
+                    color,
+                } =>
+                {
+                    let set = &self.tilesets[*tileset];
+                    let local = self.animation_frame(set, tile.id - set.first_id);
+                    let quad = tile_quad(set, *tile, local, *corner, *color);
+                    renderer.push_quad(pipeline.clone(), Some(&set.texture), quad);
+                },
+            }
+        }
+    }
+
+    /// Draws the chunks of an orthogonal layer that are in view.
+    fn draw_squares(
+        &self,
+        renderer: &mut Renderer,
+        pipeline: &Pipeline,
+        (index, layer): (usize, &TileLayer),
+        cache: &mut Cache,
+        view: &Vec4,
+    )
+    {
+        // Tiles can be larger than their cell, so chunks are culled by what they
+        // hold once built. Before that they are only skipped when even the largest
+        // tile could not reach into view.
+        let reach = self.tilesets.iter().fold(math::vec2(0.0, 0.0), |m: Vec2, t| {
+            m.sup(&(math::vec2(t.tile_width as f32, t.tile_height as f32) + t.offset.abs()))
+        });
+        let chunk = math::vec2(CHUNK as f32 * self.tile_width, CHUNK as f32 * self.tile_height);
+        let origin = self.position + layer.offset;
+
+        let cols = layer.width.div_ceil(CHUNK);
+        let rows = layer.height.div_ceil(CHUNK);
+        for cy in 0..rows
+        {
+            for cx in 0..cols
+            {
+                let corner = origin + math::vec2(cx as f32, cy as f32).component_mul(&chunk);
+                let area = math::vec4(
+                    corner.x - reach.x,
+                    corner.y - reach.y,
+                    corner.x + chunk.x + reach.x,
+                    corner.y + chunk.y + reach.y,
+                );
+                if !overlaps(&area, view)
+                {
+                    continue;
+                }
+
+                let built = cache.chunks.entry((index, cx, cy)).or_insert_with(|| {
+                    let xs = cx * CHUNK..((cx + 1) * CHUNK).min(layer.width);
+                    let ys = cy * CHUNK..((cy + 1) * CHUNK).min(layer.height);
+                    let cells: Vec<_> = ys.flat_map(|y| xs.clone().map(move |x| (x, y))).collect();
+                    self.build_chunk(layer, &cells, false)
+                });
+                if let Some(built) = built.as_ref().filter(|c| overlaps(&c.bounds, view))
+                {
+                    self.draw_chunk(renderer, pipeline, built);
+                }
+            }
+        }
+    }
+}
+
+/// The four vertices of `tile`, with its flips applied to the texture coordinates.
+fn tile_quad(set: &Tileset, tile: Tile, local: u32, corner: Vec2, color: Vec4) -> [Vertex; 4]
+{
+    let [u0, v0, u1, v1] = set.uv(local).into();
+    let size = math::vec2(set.tile_width as f32, set.tile_height as f32);
+
+    // Tiled flips in image space, with y pointing down: diagonally first, then
+    // horizontally, then vertically. Going back from a drawn corner to the texel
+    // it shows undoes them in reverse.
+    let texel = |s: f32, t: f32| {
+        let t = if tile.flip_y { 1.0 - t } else { t };
+        let s = if tile.flip_x { 1.0 - s } else { s };
+        let (s, t) = if tile.flip_diagonal { (t, s) } else { (s, t) };
+        math::vec2(u0 + s * (u1 - u0), v1 - t * (v1 - v0))
+    };
+
+    [
+        Vertex::new(corner, texel(0.0, 1.0), color),
+        Vertex::new(corner + math::vec2(size.x, 0.0), texel(1.0, 1.0), color),
+        Vertex::new(corner + size, texel(1.0, 0.0), color),
+        Vertex::new(corner + math::vec2(0.0, size.y), texel(0.0, 0.0), color),
+    ]
+}
+
+/// `(x0, y0, x1, y1)` of the world area `proj` shows.
+fn view_bounds(proj: &math::Mat4) -> Vec4
+{
+    let inverse = proj.try_inverse().unwrap_or_else(math::identity);
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(x, y)| {
+        let p = inverse * math::vec4(x, y, 0.0, 1.0);
+        math::vec2(p.x / p.w, p.y / p.w)
+    });
+
+    let min = corners.iter().fold(math::vec2(f32::MAX, f32::MAX), |m, p| m.inf(p));
+    let max = corners.iter().fold(math::vec2(f32::MIN, f32::MIN), |m, p| m.sup(p));
+    math::vec4(min.x, min.y, max.x, max.y)
+}
+
+fn overlaps(a: &Vec4, b: &Vec4) -> bool
+{
+    a.x < b.z && b.x < a.z && a.y < b.w && b.y < a.w
+}
+
+use crate::graphics::drawable::Drawable;
+impl Drawable for TileMap
+{
+    fn draw(&self, renderer: &mut Renderer)
+    {
+        let view = view_bounds(&renderer.drawing_projection());
+        let pipeline = self.material.clone().map_or(Pipeline::Quad, Pipeline::Material);
+        renderer.set_blend_mode(self.blend);
+
+        let mut cache = self.cache.borrow_mut();
+        for (index, layer) in self.layers.iter().enumerate()
+        {
+            let Layer::Tiles(layer) = layer
+            else
+            {
+                continue;
+            };
+            if !layer.visible
+            {
+                continue;
+            }
+
+            if self.layout == GridLayout::Orthogonal
+            {
+                self.draw_squares(renderer, &pipeline, (index, layer), &mut cache, &view);
+                continue;
+            }
+
+            // Tiles of other layouts overlap their neighbors, so the bands all get
+            // built at once and drawn from the back.
+            let bands = self.band_count();
+            if (0..bands).any(|band| !cache.chunks.contains_key(&(index, 0, band)))
+            {
+                self.build_bands(index, layer, &mut cache);
+            }
+            for band in (0..bands).rev()
+            {
+                let chunk = cache.chunks.get(&(index, 0, band)).and_then(Option::as_ref);
+                if let Some(chunk) = chunk.filter(|c| overlaps(&c.bounds, &view))
+                {
+                    self.draw_chunk(renderer, &pipeline, chunk);
+                }
+            }
+        }
+    }
+}
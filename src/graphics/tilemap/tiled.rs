@@ -0,0 +1,776 @@
+//! Reads maps made with Tiled, in both its XML (`.tmx`) and JSON (`.tmj`) formats.
+//!
+//! Tiled measures from the top left with y pointing down, everything is flipped
+//! over to tori's bottom left origin here. Objects of isometric maps are placed in
+//! the diamond grid's space and get projected to the world.
+
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::Error,
+    graphics::{
+        texture::{Filter, Texture, TextureBuilder},
+        tilemap::{
+            tmx, AnimationFrame, Layer, MapObject, ObjectLayer, ObjectShape, Properties,
+            Property, Tile, TileLayer, TileMap, Tileset,
+        },
+    },
+    json::Json,
+    math::{
+        self,
+        grid::{GridLayout, Stagger, StaggerAxis, StaggerIndex},
+        Vec2, Vec4,
+    },
+};
+
+pub(super) fn load(path: &Path) -> Result<TileMap, Error>
+{
+    let text = std::fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let mut map = match path.extension().and_then(|e| e.to_str())
+    {
+        Some("tmx") => tmx::load(&text, dir)?,
+        Some("tmj" | "json") => load_json(&text, dir)?,
+        _ => return Err(error(format!("{} is neither a .tmx nor a .tmj file", path.display()))),
+    };
+    map.name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    Ok(map)
+}
+
+pub(super) fn error(message: String) -> Error
+{
+    Error::Format(format!("Tiled: {}", message))
+}
+
+/// Loads each tileset image once, however many tilesets or levels use it.
+#[derive(Default)]
+pub(super) struct Textures(HashMap<PathBuf, Texture>);
+
+impl Textures
+{
+    pub fn get(&mut self, path: &Path) -> Result<Texture, Error>
+    {
+        if let Some(texture) = self.0.get(path)
+        {
+            return Ok(texture.clone());
+        }
+        let texture = TextureBuilder::from_path(path).filter(Filter::Nearest).build()?;
+        self.0.insert(path.to_path_buf(), texture.clone());
+        Ok(texture)
+    }
+}
+
+/// What a group layer passes on to the layers inside it.
+#[derive(Clone, Copy)]
+pub(super) struct Group
+{
+    /// In world units, y up.
+    pub offset:  Vec2,
+    pub opacity: f32,
+    pub visible: bool,
+    pub tint:    Vec4,
+}
+
+impl Group
+{
+    pub fn root() -> Self
+    {
+        Self {
+            offset: math::vec2(0.0, 0.0),
+            opacity: 1.0,
+            visible: true,
+            tint: math::vec4(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+
+    /// A layer or group with these settings inside this group.
+    pub fn nest(&self, offset: (f32, f32), opacity: f32, visible: bool, tint: Vec4) -> Self
+    {
+        Self {
+            offset: self.offset + math::vec2(offset.0, -offset.1),
+            opacity: self.opacity * opacity,
+            visible: self.visible && visible,
+            tint: self.tint.component_mul(&tint),
+        }
+    }
+}
+
+/// The parts of an object both formats agree on, still in Tiled's coordinates.
+pub(super) struct RawObject
+{
+    pub id:         u32,
+    pub name:       String,
+    pub class:      String,
+    pub x:          f32,
+    pub y:          f32,
+    pub width:      f32,
+    pub height:     f32,
+    /// Degrees clockwise.
+    pub rotation:   f32,
+    pub gid:        Option<u32>,
+    pub ellipse:    bool,
+    pub point:      bool,
+    pub polygon:    Option<Vec<(f32, f32)>>,
+    pub polyline:   Option<Vec<(f32, f32)>>,
+    pub visible:    bool,
+    pub properties: Properties,
+}
+
+/// A tileset as described in a file, before its image is loaded.
+pub(super) struct RawTileset
+{
+    pub name:        String,
+    pub first_id:    u32,
+    pub image:       PathBuf,
+    pub tile_width:  u32,
+    pub tile_height: u32,
+    pub columns:     u32,
+    pub tile_count:  u32,
+    pub margin:      u32,
+    pub spacing:     u32,
+    /// In pixels, y down.
+    pub offset:      (f32, f32),
+    pub animations:  HashMap<u32, Vec<AnimationFrame>>,
+    pub properties:  HashMap<u32, Properties>,
+}
+
+/// Puts a `TileMap` together from what either format read.
+pub(super) struct Builder
+{
+    pub map:  TileMap,
+    /// The directory of the map file.
+    pub dir:  PathBuf,
+    textures: Textures,
+}
+
+impl Builder
+{
+    pub fn new(dir: PathBuf, width: u32, height: u32, tile_width: f32, tile_height: f32) -> Self
+    {
+        Self {
+            map: TileMap::new(width, height, tile_width, tile_height),
+            dir,
+            textures: Textures::default(),
+        }
+    }
+
+    /// Reads the map's `orientation` and the stagger settings that go with it.
+    pub fn layout(&mut self, orientation: &str, axis: &str, index: &str, side: f32)
+        -> Result<(), Error>
+    {
+        let axis = if axis == "x" { StaggerAxis::X } else { StaggerAxis::Y };
+        // Flipping the rows over changes which ones are pushed over when there is an
+        // even number of them. Columns are pushed down in Tiled, so up are the others.
+        let odd = match axis
+        {
+            StaggerAxis::Y => (index == "odd") != self.map.height.is_multiple_of(2),
+            StaggerAxis::X => index != "odd",
+        };
+        let index = if odd { StaggerIndex::Odd } else { StaggerIndex::Even };
+        let stagger = Stagger::new(axis, index);
+
+        self.map.layout = match orientation
+        {
+            "orthogonal" => GridLayout::Orthogonal,
+            "isometric" => GridLayout::Isometric,
+            "staggered" => GridLayout::Staggered(stagger),
+            "hexagonal" => GridLayout::Hexagonal {
+                stagger,
+                side,
+            },
+            _ => return Err(error(format!("{} maps are not supported", orientation))),
+        };
+        Ok(())
+    }
+
+    /// Turns Tiled's y into a world y.
+    fn flip(&self, y: f32) -> f32
+    {
+        self.map.size().y - y
+    }
+
+    /// Projects an object of an isometric map from the diamond grid's space, where
+    /// cells are `tile_height` wide on both axes, to Tiled's screen space. Rectangles
+    /// and ellipses turn into the polygons they look like.
+    fn isometric(&self, mut raw: RawObject) -> RawObject
+    {
+        let (w, h) = (self.map.tile_width, self.map.tile_height);
+        let project = |(x, y): (f32, f32)| ((x - y) / h * w / 2.0, (x + y) / 2.0);
+
+        let outline = raw.gid.is_none() && !raw.point;
+        if outline && raw.polygon.is_none() && raw.polyline.is_none()
+        {
+            let (width, height) = (raw.width, raw.height);
+            raw.polygon = Some(if raw.ellipse
+            {
+                (0..32)
+                    .map(|i| {
+                        let angle = i as f32 / 32.0 * std::f32::consts::TAU;
+                        ((1.0 + angle.cos()) * width / 2.0, (1.0 + angle.sin()) * height / 2.0)
+                    })
+                    .collect()
+            }
+            else
+            {
+                vec![(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)]
+            });
+        }
+        raw.polygon = raw.polygon.map(|points| points.into_iter().map(project).collect());
+        raw.polyline = raw.polyline.map(|points| points.into_iter().map(project).collect());
+
+        let (x, y) = project((raw.x, raw.y));
+        raw.x = x + self.map.height as f32 * w / 2.0;
+        raw.y = y;
+        // Tile objects stand centered on their position.
+        if raw.gid.is_some()
+        {
+            raw.x -= raw.width / 2.0;
+        }
+        raw
+    }
+
+    pub fn tileset(&mut self, raw: RawTileset) -> Result<(), Error>
+    {
+        let texture = self.textures.get(&raw.image)?;
+
+        self.map.tilesets.push(Tileset {
+            name: raw.name,
+            first_id: raw.first_id,
+            texture,
+            tile_width: raw.tile_width,
+            tile_height: raw.tile_height,
+            columns: raw.columns,
+            tile_count: raw.tile_count,
+            margin: raw.margin,
+            spacing: raw.spacing,
+            offset: math::vec2(raw.offset.0, -raw.offset.1),
+            animations: raw.animations,
+            properties: raw.properties,
+        });
+        Ok(())
+    }
+
+    /// `gids` go row by row from the top, as Tiled stores them.
+    pub fn tile_layer(
+        &mut self,
+        name: String,
+        (width, height): (u32, u32),
+        gids: &[u32],
+        group: Group,
+        properties: Properties,
+    ) -> Result<(), Error>
+    {
+        let Some(expected) = width.checked_mul(height)
+        else
+        {
+            return Err(error(format!("layer \"{}\" is too large", name)));
+        };
+        if gids.len() != expected as usize
+        {
+            let count = gids.len();
+            let message = format!("layer \"{}\" has {} tiles, not {}", name, count, expected);
+            return Err(error(message));
+        }
+
+        let mut layer = TileLayer::new(&name, width, height);
+        for (i, gid) in gids.iter().enumerate()
+        {
+            let (x, row) = (i as u32 % width, i as u32 / width);
+            layer.set(x, height - 1 - row, Tile::from_gid(*gid));
+        }
+        layer.visible = group.visible;
+        layer.opacity = group.opacity;
+        layer.tint = group.tint;
+        layer.offset = group.offset;
+        layer.properties = properties;
+
+        self.map.layers.push(Layer::Tiles(layer));
+        Ok(())
+    }
+
+    pub fn object_layer(
+        &mut self,
+        name: String,
+        objects: Vec<RawObject>,
+        group: Group,
+        properties: Properties,
+    )
+    {
+        let objects = objects.into_iter().map(|raw| self.object(raw, group.offset)).collect();
+        self.map.layers.push(Layer::Objects(ObjectLayer {
+            name,
+            visible: group.visible,
+            objects,
+            properties,
+        }));
+    }
+
+    fn object(&self, raw: RawObject, offset: Vec2) -> MapObject
+    {
+        let raw = if self.map.layout == GridLayout::Isometric { self.isometric(raw) } else { raw };
+        let size = math::vec2(raw.width, raw.height);
+        let points = |points: Vec<(f32, f32)>| points.into_iter().map(|(x, y)| math::vec2(x, -y));
+        let shape = if let Some(tile) = raw.gid.and_then(Tile::from_gid)
+        {
+            ObjectShape::Tile {
+                tile,
+                size,
+            }
+        }
+        else if let Some(polygon) = raw.polygon
+        {
+            ObjectShape::Polygon(points(polygon).collect())
+        }
+        else if let Some(polyline) = raw.polyline
+        {
+            ObjectShape::Polyline(points(polyline).collect())
+        }
+        else if raw.point
+        {
+            ObjectShape::Point
+        }
+        else if raw.ellipse
+        {
+            ObjectShape::Ellipse {
+                size,
+            }
+        }
+        else
+        {
+            ObjectShape::Rect {
+                size,
+            }
+        };
+
+        MapObject {
+            id: raw.id,
+            name: raw.name,
+            class: raw.class,
+            position: math::vec2(raw.x, self.flip(raw.y)) + offset,
+            rotation: -raw.rotation.to_radians(),
+            shape,
+            visible: raw.visible,
+            properties: raw.properties,
+        }
+    }
+
+    pub fn finish(mut self) -> TileMap
+    {
+        self.map.tilesets.sort_by_key(|t| t.first_id);
+        self.map
+    }
+}
+
+/// Reads `#RRGGBB` and `#AARRGGBB`.
+pub(super) fn color(text: &str) -> Option<Vec4>
+{
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let channel = |shift: u32| ((value >> shift) & 0xFF) as f32 / 255.0;
+    match hex.len()
+    {
+        6 => Some(math::vec4(channel(16), channel(8), channel(0), 1.0)),
+        8 => Some(math::vec4(channel(16), channel(8), channel(0), channel(24))),
+        _ => None,
+    }
+}
+
+/// Turns a property value written as text into a `Property` of type `kind`.
+pub(super) fn property(kind: &str, value: &str, dir: &Path) -> Option<Property>
+{
+    Some(match kind
+    {
+        "bool" => Property::Bool(value == "true"),
+        "int" => Property::Int(value.parse().ok()?),
+        "float" => Property::Float(value.parse().ok()?),
+        "color" => Property::Color(color(value).unwrap_or_default()),
+        "file" => Property::File(dir.join(value)),
+        "object" => Property::Object(value.parse().ok()?),
+        _ => Property::String(value.to_string()),
+    })
+}
+
+/// Decodes the base64 and possibly compressed tile data of a layer.
+pub(super) fn decode(data: &str, compression: Option<&str>) -> Result<Vec<u32>, Error>
+{
+    let bytes = base64(data)?;
+    let bytes = match compression
+    {
+        None | Some("") => bytes,
+        Some("zlib") =>
+        {
+            let mut out = Vec::new();
+            flate2::read::ZlibDecoder::new(bytes.as_slice()).read_to_end(&mut out)?;
+            out
+        },
+        Some("gzip") =>
+        {
+            let mut out = Vec::new();
+            flate2::read::GzDecoder::new(bytes.as_slice()).read_to_end(&mut out)?;
+            out
+        },
+        Some(other) =>
+        {
+            return Err(error(format!("{} compressed layers are not supported", other)));
+        },
+    };
+
+    Ok(bytes.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+}
+
+fn base64(text: &str) -> Result<Vec<u8>, Error>
+{
+    let digit = |c: u8| match c
+    {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+    {
+        let value = digit(c).ok_or_else(|| error("layer data is not valid base64".to_string()))?;
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8
+        {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn missing(what: &str, field: &str) -> Error
+{
+    error(format!("{} has no valid \"{}\"", what, field))
+}
+
+fn get_u32(json: &Json, what: &str, name: &str) -> Result<u32, Error>
+{
+    json.get(name).and_then(Json::as_u32).ok_or_else(|| missing(what, name))
+}
+
+fn get_f32(json: &Json, name: &str, default: f32) -> f32
+{
+    json.get(name).and_then(Json::as_f32).unwrap_or(default)
+}
+
+fn get_str<'a>(json: &'a Json, name: &str) -> &'a str
+{
+    json.get(name).and_then(Json::as_str).unwrap_or_default()
+}
+
+fn get_bool(json: &Json, name: &str, default: bool) -> bool
+{
+    json.get(name).and_then(Json::as_bool).unwrap_or(default)
+}
+
+fn load_json(text: &str, dir: PathBuf) -> Result<TileMap, Error>
+{
+    let json = Json::parse(text)?;
+    let what = "the map";
+
+    if get_bool(&json, "infinite", false)
+    {
+        return Err(error("infinite maps are not supported".to_string()));
+    }
+
+    let mut builder = Builder::new(
+        dir,
+        get_u32(&json, what, "width")?,
+        get_u32(&json, what, "height")?,
+        get_u32(&json, what, "tilewidth")? as f32,
+        get_u32(&json, what, "tileheight")? as f32,
+    );
+    builder.layout(
+        get_str(&json, "orientation"),
+        get_str(&json, "staggeraxis"),
+        get_str(&json, "staggerindex"),
+        get_f32(&json, "hexsidelength", 0.0),
+    )?;
+    builder.map.background = json.get("backgroundcolor").and_then(Json::as_str).and_then(color);
+    builder.map.properties = json_properties(&json, &builder.dir);
+
+    for tileset in json.get("tilesets").and_then(Json::as_array).unwrap_or_default()
+    {
+        let first_id = get_u32(tileset, "a tileset", "firstgid")?;
+        let raw = match tileset.get("source").and_then(Json::as_str)
+        {
+            Some(source) => external_tileset(&builder.dir.join(source), first_id)?,
+            None => json_tileset(tileset, first_id, &builder.dir)?,
+        };
+        builder.tileset(raw)?;
+    }
+
+    let layers = json.get("layers").and_then(Json::as_array).unwrap_or_default();
+    json_layers(&mut builder, layers, Group::root())?;
+    Ok(builder.finish())
+}
+
+/// Loads a `.tsx` or `.tsj` file.
+pub(super) fn external_tileset(path: &Path, first_id: u32) -> Result<RawTileset, Error>
+{
+    let text = std::fs::read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    match path.extension().and_then(|e| e.to_str())
+    {
+        Some("tsx") => tmx::tileset(&tmx::parse(&text)?, first_id, dir),
+        _ => json_tileset(&Json::parse(&text)?, first_id, dir),
+    }
+}
+
+fn json_tileset(json: &Json, first_id: u32, dir: &Path) -> Result<RawTileset, Error>
+{
+    let name = get_str(json, "name").to_string();
+    let what = format!("tileset \"{}\"", name);
+    let image = json.get("image").and_then(Json::as_str).ok_or_else(|| {
+        error(format!("{} has no image, image collections are not supported", what))
+    })?;
+
+    let mut animations = HashMap::new();
+    let mut properties = HashMap::new();
+    for tile in json.get("tiles").and_then(Json::as_array).unwrap_or_default()
+    {
+        let id = get_u32(tile, &what, "id")?;
+        if let Some(frames) = tile.get("animation").and_then(Json::as_array)
+        {
+            let frames = frames
+                .iter()
+                .map(|f| {
+                    Ok(AnimationFrame {
+                        tile: get_u32(f, &what, "tileid")?,
+                        duration: get_u32(f, &what, "duration")? as f32 / 1000.0,
+                    })
+                })
+                .collect::<Result<_, Error>>()?;
+            animations.insert(id, frames);
+        }
+        let props = json_properties(tile, dir);
+        if !props.is_empty()
+        {
+            properties.insert(id, props);
+        }
+    }
+
+    let offset = json.get("tileoffset");
+    Ok(RawTileset {
+        first_id,
+        image: dir.join(image),
+        tile_width: get_u32(json, &what, "tilewidth")?,
+        tile_height: get_u32(json, &what, "tileheight")?,
+        columns: get_u32(json, &what, "columns")?,
+        tile_count: get_u32(json, &what, "tilecount")?,
+        margin: json.get("margin").and_then(Json::as_u32).unwrap_or(0),
+        spacing: json.get("spacing").and_then(Json::as_u32).unwrap_or(0),
+        offset: offset.map_or((0.0, 0.0), |o| (get_f32(o, "x", 0.0), get_f32(o, "y", 0.0))),
+        animations,
+        properties,
+        name,
+    })
+}
+
+fn json_layers(builder: &mut Builder, layers: &[Json], parent: Group) -> Result<(), Error>
+{
+    for layer in layers
+    {
+        let name = get_str(layer, "name").to_string();
+        let what = format!("layer \"{}\"", name);
+        let tint = layer.get("tintcolor").and_then(Json::as_str).and_then(color);
+        let group = parent.nest(
+            (get_f32(layer, "offsetx", 0.0), get_f32(layer, "offsety", 0.0)),
+            get_f32(layer, "opacity", 1.0),
+            get_bool(layer, "visible", true),
+            tint.unwrap_or(math::vec4(1.0, 1.0, 1.0, 1.0)),
+        );
+        let properties = json_properties(layer, &builder.dir);
+
+        match get_str(layer, "type")
+        {
+            "tilelayer" =>
+            {
+                let size = (get_u32(layer, &what, "width")?, get_u32(layer, &what, "height")?);
+                let gids = match layer.get("data")
+                {
+                    Some(Json::String(data)) =>
+                    {
+                        let compression = layer.get("compression").and_then(Json::as_str);
+                        decode(data, compression)?
+                    },
+                    Some(Json::Array(data)) => data
+                        .iter()
+                        .map(|gid| gid.as_u32().ok_or_else(|| missing(&what, "data")))
+                        .collect::<Result<_, _>>()?,
+                    _ => return Err(missing(&what, "data")),
+                };
+                builder.tile_layer(name, size, &gids, group, properties)?;
+            },
+            "objectgroup" =>
+            {
+                let objects = layer
+                    .get("objects")
+                    .and_then(Json::as_array)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|o| json_object(o, &builder.dir))
+                    .collect();
+                builder.object_layer(name, objects, group, properties);
+            },
+            "group" =>
+            {
+                let layers = layer.get("layers").and_then(Json::as_array).unwrap_or_default();
+                json_layers(builder, layers, group)?;
+            },
+            other => log::warn!(target: "tori::tilemap", "skipping {} of type {}", what, other),
+        }
+    }
+    Ok(())
+}
+
+fn json_object(json: &Json, dir: &Path) -> RawObject
+{
+    let points = |name| {
+        json.get(name).and_then(Json::as_array).map(|points| {
+            points.iter().map(|p| (get_f32(p, "x", 0.0), get_f32(p, "y", 0.0))).collect()
+        })
+    };
+    // Tiled 1.9 renamed `type` to `class`.
+    let class = json.get("class").or_else(|| json.get("type")).and_then(Json::as_str);
+
+    RawObject {
+        id: json.get("id").and_then(Json::as_u32).unwrap_or(0),
+        name: get_str(json, "name").to_string(),
+        class: class.unwrap_or_default().to_string(),
+        x: get_f32(json, "x", 0.0),
+        y: get_f32(json, "y", 0.0),
+        width: get_f32(json, "width", 0.0),
+        height: get_f32(json, "height", 0.0),
+        rotation: get_f32(json, "rotation", 0.0),
+        gid: json.get("gid").and_then(Json::as_u32),
+        ellipse: get_bool(json, "ellipse", false),
+        point: get_bool(json, "point", false),
+        polygon: points("polygon"),
+        polyline: points("polyline"),
+        visible: get_bool(json, "visible", true),
+        properties: json_properties(json, dir),
+    }
+}
+
+fn json_properties(json: &Json, dir: &Path) -> Properties
+{
+    let mut properties = Properties::new();
+    for p in json.get("properties").and_then(Json::as_array).unwrap_or_default()
+    {
+        let value = match (get_str(p, "type"), p.get("value"))
+        {
+            ("class", Some(Json::Object(members))) => Some(Property::Class(class(members))),
+            ("bool", Some(value)) => value.as_bool().map(Property::Bool),
+            ("int", Some(value)) => value.as_i64().map(Property::Int),
+            ("float", Some(value)) => value.as_f64().map(Property::Float),
+            ("object", Some(value)) => value.as_u32().map(Property::Object),
+            (kind, Some(Json::String(s))) => property(kind, s, dir),
+            _ => None,
+        };
+        if let Some(value) = value
+        {
+            properties.insert(get_str(p, "name").to_string(), value);
+        }
+    }
+    properties
+}
+
+/// The members of a class property. Their types are not stored with them, so they
+/// are guessed from the values.
+fn class(members: &[(String, Json)]) -> Properties
+{
+    let value = |json: &Json| match json
+    {
+        Json::Bool(b) => Some(Property::Bool(*b)),
+        Json::Number(n) if n.fract() == 0.0 => Some(Property::Int(*n as i64)),
+        Json::Number(n) => Some(Property::Float(*n)),
+        Json::String(s) if s.starts_with('#') => color(s).map(Property::Color),
+        Json::String(s) => Some(Property::String(s.clone())),
+        Json::Object(members) => Some(Property::Class(class(members))),
+        Json::Null | Json::Array(_) => None,
+    };
+    members.iter().filter_map(|(k, v)| Some((k.clone(), value(v)?))).collect()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const GIDS: [u32; 4] = [1, 2, 0x8000_0003, 0x6000_0004];
+
+    #[test]
+    fn base64_padding_and_whitespace()
+    {
+        assert_eq!(base64("TWFu").unwrap(), b"Man");
+        assert_eq!(base64("TWE=").unwrap(), b"Ma");
+        assert_eq!(base64("TQ==").unwrap(), b"M");
+        assert_eq!(base64("\n   TW\n   Fu\n").unwrap(), b"Man");
+        assert!(matches!(base64("TW!u"), Err(Error::Format(_))));
+    }
+
+    #[test]
+    fn decode_compressions()
+    {
+        assert_eq!(decode("AQAAAAIAAAADAACABAAAYA==", None).unwrap(), GIDS);
+        assert_eq!(decode("AQAAAAIAAAADAACABAAAYA==", Some("")).unwrap(), GIDS);
+        assert_eq!(decode("eJxjZGBgYAJiZgaGBhYGhgQAA0AA6w==", Some("zlib")).unwrap(), GIDS);
+        let gzip = "H4sIAAAAAAACA2NkYGBgAmJmBoYGFgaGBAAlBVFTEAAAAA==";
+        assert_eq!(decode(gzip, Some("gzip")).unwrap(), GIDS);
+        assert!(matches!(decode("AQAAAA==", Some("zstd")), Err(Error::Format(_))));
+        assert!(decode("AQAAAA==", Some("zlib")).is_err());
+    }
+
+    #[test]
+    fn gid_flip_bits()
+    {
+        assert_eq!(Tile::from_gid(0), None);
+        assert_eq!(Tile::from_gid(0xE000_0000), None);
+        assert_eq!(Tile::from_gid(GIDS[0]), Some(Tile::new(1)));
+
+        let flipped = Tile::from_gid(GIDS[2]).unwrap();
+        assert_eq!((flipped.id, flipped.flip_x, flipped.flip_y), (3, true, false));
+        assert!(!flipped.flip_diagonal);
+
+        let rotated = Tile::from_gid(GIDS[3]).unwrap();
+        assert_eq!((rotated.id, rotated.flip_x, rotated.flip_y), (4, false, true));
+        assert!(rotated.flip_diagonal);
+
+        // The hexagonal rotation bit is not part of the id.
+        assert_eq!(Tile::from_gid(0x1000_0005), Some(Tile::new(5)));
+    }
+
+    #[test]
+    fn tile_layer_size()
+    {
+        let mut builder = Builder::new(PathBuf::new(), 2, 2, 16.0, 16.0);
+        let mut add = |size, gids: &[u32]| {
+            builder.tile_layer("ground".to_string(), size, gids, Group::root(), Properties::new())
+        };
+        assert!(matches!(add((0x1_0000, 0x1_0000), &[]), Err(Error::Format(_))));
+        assert!(matches!(add((2, 2), &GIDS[..3]), Err(Error::Format(_))));
+        add((2, 2), &GIDS).unwrap();
+
+        // The first row in the file is the top one.
+        let Some(Layer::Tiles(layer)) = builder.map.layers.first()
+        else
+        {
+            panic!("no tile layer");
+        };
+        assert_eq!(layer.get(0, 1), Tile::from_gid(GIDS[0]));
+        assert_eq!(layer.get(1, 0), Tile::from_gid(GIDS[3]));
+    }
+}
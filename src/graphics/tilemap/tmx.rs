@@ -0,0 +1,328 @@
+//! The XML side of Tiled: `.tmx` maps and `.tsx` tilesets.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::{
+    error::Error,
+    graphics::tilemap::{
+        tiled::{self, Builder, Group, RawObject, RawTileset},
+        AnimationFrame, Properties, Property,
+    },
+    math,
+};
+
+/// An XML element with everything below it read in.
+pub(super) struct Element
+{
+    name:       String,
+    attributes: Vec<(String, String)>,
+    children:   Vec<Element>,
+    text:       String,
+}
+
+impl Element
+{
+    fn attr(&self, name: &str) -> Option<&str>
+    {
+        self.attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn parse_attr<T: std::str::FromStr>(&self, name: &str) -> Option<T>
+    {
+        self.attr(name).and_then(|v| v.parse().ok())
+    }
+
+    fn number(&self, name: &str, default: f32) -> f32
+    {
+        self.parse_attr(name).unwrap_or(default)
+    }
+
+    fn required<T: std::str::FromStr>(&self, name: &str) -> Result<T, Error>
+    {
+        self.parse_attr(name).ok_or_else(|| {
+            tiled::error(format!("<{}> has no valid \"{}\"", self.name, name))
+        })
+    }
+
+    /// Tiled writes `0` and `1` for booleans.
+    fn flag(&self, name: &str, default: bool) -> bool
+    {
+        self.attr(name).map_or(default, |v| v == "1" || v == "true")
+    }
+
+    fn child(&self, name: &str) -> Option<&Element>
+    {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element>
+    {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+/// Reads a whole document into its root element.
+pub(super) fn parse(text: &str) -> Result<Element, Error>
+{
+    let mut stack: Vec<Element> = Vec::new();
+    for event in EventReader::new(text.as_bytes())
+    {
+        match event.map_err(|e| tiled::error(format!("XML: {}", e)))?
+        {
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                ..
+            } => stack.push(Element {
+                name: name.local_name,
+                attributes: attributes.into_iter().map(|a| (a.name.local_name, a.value)).collect(),
+                children: Vec::new(),
+                text: String::new(),
+            }),
+            XmlEvent::EndElement {
+                ..
+            } =>
+            {
+                let element = stack.pop().expect("xml-rs matches end tags to start tags");
+                match stack.last_mut()
+                {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) =>
+            {
+                if let Some(element) = stack.last_mut()
+                {
+                    element.text.push_str(&text);
+                }
+            },
+            _ => (),
+        }
+    }
+    Err(tiled::error("the XML document has no root element".to_string()))
+}
+
+pub(super) fn load(text: &str, dir: PathBuf) -> Result<crate::graphics::tilemap::TileMap, Error>
+{
+    let root = parse(text)?;
+    if root.name != "map"
+    {
+        return Err(tiled::error(format!("expected <map>, found <{}>", root.name)));
+    }
+
+    if root.flag("infinite", false)
+    {
+        return Err(tiled::error("infinite maps are not supported".to_string()));
+    }
+
+    let mut builder = Builder::new(
+        dir,
+        root.required("width")?,
+        root.required("height")?,
+        root.required("tilewidth")?,
+        root.required("tileheight")?,
+    );
+    builder.layout(
+        root.attr("orientation").unwrap_or_default(),
+        root.attr("staggeraxis").unwrap_or_default(),
+        root.attr("staggerindex").unwrap_or_default(),
+        root.number("hexsidelength", 0.0),
+    )?;
+    builder.map.background = root.attr("backgroundcolor").and_then(tiled::color);
+    builder.map.properties = properties(&root, &builder.dir);
+
+    for element in root.children("tileset")
+    {
+        let first_id = element.required("firstgid")?;
+        let raw = match element.attr("source")
+        {
+            Some(source) => tiled::external_tileset(&builder.dir.join(source), first_id)?,
+            None => tileset(element, first_id, &builder.dir)?,
+        };
+        builder.tileset(raw)?;
+    }
+
+    layers(&mut builder, &root, Group::root())?;
+    Ok(builder.finish())
+}
+
+/// Reads a `<tileset>`, from a map or the root of a `.tsx` file.
+pub(super) fn tileset(element: &Element, first_id: u32, dir: &Path) -> Result<RawTileset, Error>
+{
+    let name = element.attr("name").unwrap_or_default().to_string();
+    let image = element.child("image").and_then(|i| i.attr("source")).ok_or_else(|| {
+        let message = format!("tileset \"{}\" has no image", name);
+        tiled::error(format!("{}, image collections are not supported", message))
+    })?;
+
+    let mut animations = HashMap::new();
+    let mut tile_properties = HashMap::new();
+    for tile in element.children("tile")
+    {
+        let id = tile.required("id")?;
+        if let Some(animation) = tile.child("animation")
+        {
+            let frames = animation
+                .children("frame")
+                .map(|f| {
+                    Ok(AnimationFrame {
+                        tile: f.required("tileid")?,
+                        duration: f.required::<u32>("duration")? as f32 / 1000.0,
+                    })
+                })
+                .collect::<Result<_, Error>>()?;
+            animations.insert(id, frames);
+        }
+        let props = properties(tile, dir);
+        if !props.is_empty()
+        {
+            tile_properties.insert(id, props);
+        }
+    }
+
+    let offset = element.child("tileoffset");
+    Ok(RawTileset {
+        name,
+        first_id,
+        image: dir.join(image),
+        tile_width: element.required("tilewidth")?,
+        tile_height: element.required("tileheight")?,
+        columns: element.required("columns")?,
+        tile_count: element.required("tilecount")?,
+        margin: element.parse_attr("margin").unwrap_or(0),
+        spacing: element.parse_attr("spacing").unwrap_or(0),
+        offset: offset.map_or((0.0, 0.0), |o| (o.number("x", 0.0), o.number("y", 0.0))),
+        animations,
+        properties: tile_properties,
+    })
+}
+
+fn layers(builder: &mut Builder, parent: &Element, outer: Group) -> Result<(), Error>
+{
+    for element in parent.children.iter()
+    {
+        let name = element.attr("name").unwrap_or_default().to_string();
+        let tint = element.attr("tintcolor").and_then(tiled::color);
+        let group = outer.nest(
+            (element.number("offsetx", 0.0), element.number("offsety", 0.0)),
+            element.number("opacity", 1.0),
+            element.flag("visible", true),
+            tint.unwrap_or(math::vec4(1.0, 1.0, 1.0, 1.0)),
+        );
+        let properties = properties(element, &builder.dir);
+
+        match element.name.as_str()
+        {
+            "layer" =>
+            {
+                let size = (element.required("width")?, element.required("height")?);
+                let gids = tile_data(element, &name)?;
+                builder.tile_layer(name, size, &gids, group, properties)?;
+            },
+            "objectgroup" =>
+            {
+                let objects = element.children("object").map(|o| object(o, &builder.dir)).collect();
+                builder.object_layer(name, objects, group, properties);
+            },
+            "group" => layers(builder, element, group)?,
+            "imagelayer" =>
+            {
+                log::warn!(target: "tori::tilemap", "skipping image layer \"{}\"", name);
+            },
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// The gids of a `<layer>`, in any of the encodings Tiled writes.
+fn tile_data(layer: &Element, name: &str) -> Result<Vec<u32>, Error>
+{
+    let data = layer
+        .child("data")
+        .ok_or_else(|| tiled::error(format!("layer \"{}\" has no <data>", name)))?;
+
+    match data.attr("encoding")
+    {
+        Some("csv") => data
+            .text
+            .split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| {
+                gid.parse().map_err(|_| tiled::error(format!("layer \"{}\" has bad csv", name)))
+            })
+            .collect(),
+        Some("base64") => tiled::decode(&data.text, data.attr("compression")),
+        Some(other) => Err(tiled::error(format!("{} encoded layers are not supported", other))),
+        None => Ok(data.children("tile").map(|t| t.parse_attr("gid").unwrap_or(0)).collect()),
+    }
+}
+
+fn object(element: &Element, dir: &Path) -> RawObject
+{
+    let points = |name| {
+        let points = element.child(name)?.attr("points")?;
+        let point = |p: &str| {
+            let (x, y) = p.split_once(',')?;
+            Some((x.parse().ok()?, y.parse().ok()?))
+        };
+        Some(points.split_whitespace().filter_map(point).collect())
+    };
+    // Tiled 1.9 renamed `type` to `class`.
+    let class = element.attr("class").or_else(|| element.attr("type"));
+
+    RawObject {
+        id: element.parse_attr("id").unwrap_or(0),
+        name: element.attr("name").unwrap_or_default().to_string(),
+        class: class.unwrap_or_default().to_string(),
+        x: element.number("x", 0.0),
+        y: element.number("y", 0.0),
+        width: element.number("width", 0.0),
+        height: element.number("height", 0.0),
+        rotation: element.number("rotation", 0.0),
+        gid: element.parse_attr("gid"),
+        ellipse: element.child("ellipse").is_some(),
+        point: element.child("point").is_some(),
+        polygon: points("polygon"),
+        polyline: points("polyline"),
+        visible: element.flag("visible", true),
+        properties: properties(element, dir),
+    }
+}
+
+fn properties(element: &Element, dir: &Path) -> Properties
+{
+    let mut properties = Properties::new();
+    let Some(list) = element.child("properties")
+    else
+    {
+        return properties;
+    };
+
+    for p in list.children("property")
+    {
+        let kind = p.attr("type").unwrap_or("string");
+        let value = if kind == "class"
+        {
+            Some(Property::Class(self::properties(p, dir)))
+        }
+        else
+        {
+            // Multi-line strings are written as text instead of an attribute.
+            let value = p.attr("value").unwrap_or(&p.text);
+            tiled::property(kind, value, dir)
+        };
+        if let Some(value) = value
+        {
+            properties.insert(p.attr("name").unwrap_or_default().to_string(), value);
+        }
+    }
+    properties
+}
@@ -0,0 +1,214 @@
+//! Just enough JSON to read the data files exported by sprite and level editors.
+
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::error::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json
+{
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in file order, some formats rely on it.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json
+{
+    pub fn parse(text: &str) -> Result<Json, Error>
+    {
+        serde_json::from_str(text).map_err(|e| Error::Format(format!("JSON: {}", e)))
+    }
+
+    /// The member `key` of an object, `None` for anything else.
+    pub fn get(&self, key: &str) -> Option<&Json>
+    {
+        self.as_object()?.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn as_bool(&self) -> Option<bool>
+    {
+        match self
+        {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64>
+    {
+        match self
+        {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32>
+    {
+        self.as_f64().map(|n| n as f32)
+    }
+
+    pub fn as_i64(&self) -> Option<i64>
+    {
+        self.as_f64().filter(|n| n.fract() == 0.0).map(|n| n as i64)
+    }
+
+    pub fn as_u32(&self) -> Option<u32>
+    {
+        self.as_i64().and_then(|n| u32::try_from(n).ok())
+    }
+
+    pub fn as_str(&self) -> Option<&str>
+    {
+        match self
+        {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]>
+    {
+        match self
+        {
+            Json::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]>
+    {
+        match self
+        {
+            Json::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool
+    {
+        *self == Json::Null
+    }
+}
+
+struct JsonVisitor;
+
+impl<'de> Visitor<'de> for JsonVisitor
+{
+    type Value = Json;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result
+    {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Json, E>
+    {
+        Ok(Json::Null)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Json, E>
+    {
+        Ok(Json::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Json, E>
+    {
+        Ok(Json::Number(n as f64))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Json, E>
+    {
+        Ok(Json::Number(n as f64))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Json, E>
+    {
+        Ok(Json::Number(n))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Json, E>
+    {
+        Ok(Json::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Json, E>
+    {
+        Ok(Json::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error>
+    {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()?
+        {
+            items.push(item);
+        }
+        Ok(Json::Array(items))
+    }
+
+    // Read by hand rather than through `serde_json::Value` to keep the members in order.
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Json, A::Error>
+    {
+        let mut members = Vec::new();
+        while let Some(member) = map.next_entry()?
+        {
+            members.push(member);
+        }
+        Ok(Json::Object(members))
+    }
+}
+
+impl<'de> Deserialize<'de> for Json
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Json, D::Error>
+    {
+        deserializer.deserialize_any(JsonVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::Json;
+
+    #[test]
+    fn values()
+    {
+        let json = Json::parse(r#"{"a": [1, -2.5, 3e2], "b": "\u00e9\ud83d\ude00\n", "c": null}"#)
+            .unwrap();
+        let a = json.get("a").and_then(Json::as_array).unwrap();
+        assert_eq!(a, [Json::Number(1.0), Json::Number(-2.5), Json::Number(300.0)]);
+        assert_eq!(json.get("b").and_then(Json::as_str), Some("\u{e9}\u{1f600}\n"));
+        assert!(json.get("c").unwrap().is_null());
+        assert_eq!(a[1].as_u32(), None);
+    }
+
+    #[test]
+    fn members_keep_file_order()
+    {
+        let json = Json::parse(r#"{"walk 2": 0, "walk 0": 1, "walk 1": 2}"#).unwrap();
+        let keys: Vec<_> = json.as_object().unwrap().iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["walk 2", "walk 0", "walk 1"]);
+    }
+
+    #[test]
+    fn errors()
+    {
+        for text in ["", "{", "[1,]", "{\"a\" 1}", "\"\\x\"", "1 2", "\"\\ud800\""]
+        {
+            assert!(Json::parse(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error()
+    {
+        let text = "[".repeat(100_000) + &"]".repeat(100_000);
+        assert!(Json::parse(&text).is_err());
+    }
+}
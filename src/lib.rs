@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+pub mod assets;
 pub mod core;
 pub mod error;
 pub mod event;
@@ -7,4 +8,5 @@ pub mod graphics;
 pub mod math;
 pub mod window;
 
+mod json;
 mod util;
@@ -0,0 +1,125 @@
+use std::ops::{Add, Sub};
+
+/// A cell of a square grid. Diamond isometric grids are square grids turned on their
+/// side, so they use the same coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Cell
+{
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Cell
+{
+    pub const fn new(x: i32, y: i32) -> Self
+    {
+        Self {
+            x, y
+        }
+    }
+
+    /// The four cells sharing a side with this one, counterclockwise from `x + 1`.
+    pub fn neighbors(self) -> [Cell; 4]
+    {
+        [(1, 0), (0, 1), (-1, 0), (0, -1)].map(|(x, y)| self + Cell::new(x, y))
+    }
+
+    /// The eight cells sharing a side or a corner with this one, counterclockwise from `x + 1`.
+    pub fn neighbors8(self) -> [Cell; 8]
+    {
+        [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)]
+            .map(|(x, y)| self + Cell::new(x, y))
+    }
+
+    /// Steps to `other` going only through cells sharing a side.
+    pub fn distance(self, other: Cell) -> u32
+    {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+
+    /// The cells a straight line to `other` passes through, including both ends.
+    /// Consecutive cells share a side or a corner.
+    pub fn line_to(self, other: Cell) -> impl Iterator<Item = Cell>
+    {
+        let (dx, dy) = (other.x - self.x, other.y - self.y);
+        let steps = dx.abs().max(dy.abs());
+        (0..=steps).map(move |i| {
+            let t = if steps == 0 { 0.0 } else { i as f32 / steps as f32 };
+            // Nudged so lines through corners always pick the same side.
+            let x = self.x as f32 + dx as f32 * t + 1e-4;
+            let y = self.y as f32 + dy as f32 * t + 1e-4;
+            Cell::new(x.round() as i32, y.round() as i32)
+        })
+    }
+
+    /// Every cell at most `radius` steps away, as `distance` counts them.
+    pub fn range(self, radius: u32) -> impl Iterator<Item = Cell>
+    {
+        let radius = radius as i32;
+        (-radius..=radius).flat_map(move |y| {
+            let width = radius - y.abs();
+            (-width..=width).map(move |x| self + Cell::new(x, y))
+        })
+    }
+}
+
+impl Add for Cell
+{
+    type Output = Cell;
+
+    fn add(self, other: Cell) -> Cell
+    {
+        Cell::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Cell
+{
+    type Output = Cell;
+
+    fn sub(self, other: Cell) -> Cell
+    {
+        Cell::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn distance_and_neighbors()
+    {
+        let cell = Cell::new(2, -3);
+        assert_eq!(cell.distance(Cell::new(-1, 1)), 7);
+        assert!(cell.neighbors().iter().all(|n| cell.distance(*n) == 1));
+        assert!(cell.neighbors8().iter().all(|n| (1..=2).contains(&cell.distance(*n))));
+    }
+
+    #[test]
+    fn line_steps_to_touching_cells()
+    {
+        let (a, b) = (Cell::new(0, 0), Cell::new(5, -3));
+        let line: Vec<_> = a.line_to(b).collect();
+        assert_eq!((line[0], *line.last().unwrap()), (a, b));
+        assert_eq!(line.len(), 6);
+        for pair in line.windows(2)
+        {
+            assert!((pair[0].x - pair[1].x).abs() <= 1 && (pair[0].y - pair[1].y).abs() <= 1);
+        }
+        assert_eq!(a.line_to(a).collect::<Vec<_>>(), [a]);
+    }
+
+    #[test]
+    fn range_is_a_diamond()
+    {
+        let center = Cell::new(-4, 1);
+        for radius in 0..5
+        {
+            let range: Vec<_> = center.range(radius).collect();
+            assert_eq!(range.len() as u32, 2 * radius * (radius + 1) + 1);
+            assert!(range.iter().all(|c| c.distance(center) <= radius));
+        }
+    }
+}
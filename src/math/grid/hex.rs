@@ -0,0 +1,217 @@
+use std::ops::{Add, Sub};
+
+use crate::math::grid::{Cell, Stagger, StaggerAxis};
+
+/// A hexagon in axial coordinates, the third cube coordinate being `s`.
+///
+/// With pointy tops `q` runs to the right and `r` up and to the right. With flat tops
+/// `q` runs up and to the right and `r` up. Maps store hexagons in offset coordinates,
+/// see `from_offset`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Hex
+{
+    pub q: i32,
+    pub r: i32,
+}
+
+impl Hex
+{
+    /// The six directions, counterclockwise starting at `q + 1`.
+    pub const DIRECTIONS: [Hex; 6] = [
+        Hex::new(1, 0),
+        Hex::new(0, 1),
+        Hex::new(-1, 1),
+        Hex::new(-1, 0),
+        Hex::new(0, -1),
+        Hex::new(1, -1),
+    ];
+
+    pub const fn new(q: i32, r: i32) -> Self
+    {
+        Self {
+            q, r
+        }
+    }
+
+    pub fn s(self) -> i32
+    {
+        -self.q - self.r
+    }
+
+    /// The hexagon closest to fractional axial coordinates.
+    pub fn round(q: f32, r: f32) -> Self
+    {
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        // The coordinate that was rounded the most is fixed up from the others.
+        if dq > dr && dq > ds
+        {
+            rq = -rr - rs;
+        }
+        else if dr > ds
+        {
+            rr = -rq - rs;
+        }
+        Hex::new(rq as i32, rr as i32)
+    }
+
+    pub fn neighbor(self, direction: usize) -> Hex
+    {
+        self + Self::DIRECTIONS[direction % 6]
+    }
+
+    /// The six hexagons around this one, in the order of `DIRECTIONS`.
+    pub fn neighbors(self) -> [Hex; 6]
+    {
+        Self::DIRECTIONS.map(|d| self + d)
+    }
+
+    /// Steps to `other` from neighbor to neighbor.
+    pub fn distance(self, other: Hex) -> u32
+    {
+        let d = self - other;
+        (d.q.unsigned_abs() + d.r.unsigned_abs() + d.s().unsigned_abs()) / 2
+    }
+
+    /// The hexagons a straight line to `other` passes through, including both ends.
+    pub fn line_to(self, other: Hex) -> impl Iterator<Item = Hex>
+    {
+        let steps = self.distance(other);
+        let (dq, dr) = ((other.q - self.q) as f32, (other.r - self.r) as f32);
+        (0..=steps).map(move |i| {
+            let t = if steps == 0 { 0.0 } else { i as f32 / steps as f32 };
+            // Nudged so lines along edges always pick the same side.
+            Hex::round(self.q as f32 + dq * t + 1e-4, self.r as f32 + dr * t + 2e-4)
+        })
+    }
+
+    /// Every hexagon at most `radius` steps away.
+    pub fn range(self, radius: u32) -> impl Iterator<Item = Hex>
+    {
+        let radius = radius as i32;
+        (-radius..=radius).flat_map(move |q| {
+            let (from, to) = ((-radius).max(-q - radius), radius.min(-q + radius));
+            (from..=to).map(move |r| self + Hex::new(q, r))
+        })
+    }
+
+    /// The hexagons exactly `radius` steps away, counterclockwise.
+    pub fn ring(self, radius: u32) -> impl Iterator<Item = Hex>
+    {
+        let center = (radius == 0).then_some(self);
+        let radius = radius as i32;
+        let times = |d: Hex, n: i32| Hex::new(d.q * n, d.r * n);
+        // Side `i` starts at a corner and runs along direction `i` to the next one.
+        let sides = (0..6).flat_map(move |i| {
+            let corner = self + times(Self::DIRECTIONS[(i + 4) % 6], radius);
+            (0..radius).map(move |step| corner + times(Self::DIRECTIONS[i], step))
+        });
+        center.into_iter().chain(sides)
+    }
+
+    /// Converts offset coordinates, the columns and rows of a map, to axial ones.
+    pub fn from_offset(cell: Cell, stagger: Stagger) -> Hex
+    {
+        match stagger.axis
+        {
+            StaggerAxis::Y => Hex::new(cell.x - stagger.shifts(cell.y), cell.y),
+            StaggerAxis::X => Hex::new(cell.x, cell.y - stagger.shifts(cell.x)),
+        }
+    }
+
+    /// Converts back to the offset coordinates of a map.
+    pub fn to_offset(self, stagger: Stagger) -> Cell
+    {
+        match stagger.axis
+        {
+            StaggerAxis::Y => Cell::new(self.q + stagger.shifts(self.r), self.r),
+            StaggerAxis::X => Cell::new(self.q, self.r + stagger.shifts(self.q)),
+        }
+    }
+}
+
+impl Add for Hex
+{
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex
+    {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Hex
+{
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex
+    {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::math::grid::StaggerIndex;
+
+    #[test]
+    fn distance()
+    {
+        let hex = Hex::new(2, -1);
+        assert_eq!(hex.distance(hex), 0);
+        assert_eq!(hex.distance(Hex::new(-3, 4)), 5);
+        assert_eq!(hex.distance(Hex::new(2, 3)), 4);
+        assert_eq!(Hex::new(0, 0).distance(Hex::new(3, -1)), 3);
+        assert!(hex.neighbors().iter().all(|n| n.distance(hex) == 1));
+    }
+
+    #[test]
+    fn line_runs_between_the_ends()
+    {
+        let (a, b) = (Hex::new(2, -1), Hex::new(-3, 4));
+        let line: Vec<_> = a.line_to(b).collect();
+        assert_eq!((line[0], *line.last().unwrap()), (a, b));
+        assert_eq!(line.len() as u32, a.distance(b) + 1);
+        assert!(line.windows(2).all(|pair| pair[0].distance(pair[1]) == 1));
+        assert_eq!(a.line_to(a).collect::<Vec<_>>(), [a]);
+    }
+
+    #[test]
+    fn range_and_ring_counts()
+    {
+        let center = Hex::new(-2, 5);
+        for radius in 0..6
+        {
+            let range: Vec<_> = center.range(radius).collect();
+            assert_eq!(range.len() as u32, 3 * radius * (radius + 1) + 1);
+            assert!(range.iter().all(|h| h.distance(center) <= radius));
+
+            let ring: Vec<_> = center.ring(radius).collect();
+            assert_eq!(ring.len() as u32, if radius == 0 { 1 } else { 6 * radius });
+            assert!(ring.iter().all(|h| h.distance(center) == radius));
+        }
+    }
+
+    #[test]
+    fn offset_round_trip()
+    {
+        for axis in [StaggerAxis::X, StaggerAxis::Y]
+        {
+            for index in [StaggerIndex::Odd, StaggerIndex::Even]
+            {
+                let stagger = Stagger::new(axis, index);
+                for y in -6..6
+                {
+                    for x in -6..6
+                    {
+                        let cell = Cell::new(x, y);
+                        assert_eq!(Hex::from_offset(cell, stagger).to_offset(stagger), cell);
+                    }
+                }
+            }
+        }
+    }
+}
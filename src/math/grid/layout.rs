@@ -0,0 +1,483 @@
+use crate::math::{
+    self,
+    grid::{Cell, Hex},
+    Vec2,
+};
+
+/// Which rows or columns of a staggered grid are pushed over by half a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StaggerAxis
+{
+    /// Every other column is pushed up, which makes hexagons flat topped.
+    X,
+    /// Every other row is pushed right, which makes hexagons pointy topped.
+    Y,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StaggerIndex
+{
+    Odd,
+    Even,
+}
+
+/// Which rows or columns of a staggered or hexagonal grid are pushed over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Stagger
+{
+    pub axis:  StaggerAxis,
+    pub index: StaggerIndex,
+}
+
+impl Stagger
+{
+    pub fn new(axis: StaggerAxis, index: StaggerIndex) -> Self
+    {
+        Self {
+            axis, index
+        }
+    }
+
+    /// How many of the rows or columns before `n` are pushed over, counting from `0`.
+    pub(super) fn shifts(self, n: i32) -> i32
+    {
+        match self.index
+        {
+            StaggerIndex::Odd => (n - (n & 1)) / 2,
+            StaggerIndex::Even => (n + (n & 1)) / 2,
+        }
+    }
+
+    /// `1` for the rows or columns that are pushed over. For even ones it is `-1`
+    /// instead, which keeps the diamond coordinates whole.
+    fn push(self, n: i32) -> i32
+    {
+        match self.index
+        {
+            StaggerIndex::Odd => n & 1,
+            StaggerIndex::Even => -(n & 1),
+        }
+    }
+
+    /// Converts a cell of a staggered isometric grid to diamond coordinates.
+    pub fn to_diamond(self, cell: Cell) -> Cell
+    {
+        let Cell {
+            x,
+            y,
+        } = cell;
+        match self.axis
+        {
+            StaggerAxis::Y =>
+            {
+                let s = self.push(y);
+                Cell::new((2 * x + s - y) / 2, (2 * x + s + y) / 2)
+            },
+            StaggerAxis::X =>
+            {
+                let s = self.push(x);
+                Cell::new((x - 2 * y - s) / 2, (x + 2 * y + s) / 2)
+            },
+        }
+    }
+
+    /// Converts diamond coordinates back to a cell of a staggered isometric grid.
+    pub fn from_diamond(self, cell: Cell) -> Cell
+    {
+        match self.axis
+        {
+            StaggerAxis::Y =>
+            {
+                let y = cell.y - cell.x;
+                Cell::new((cell.x + cell.y - self.push(y)) / 2, y)
+            },
+            StaggerAxis::X =>
+            {
+                let x = cell.x + cell.y;
+                Cell::new(x, (cell.y - cell.x - self.push(x)) / 2)
+            },
+        }
+    }
+}
+
+/// The shape of the cells of a `Grid` and how they fit together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GridLayout
+{
+    /// Rectangles in rows and columns.
+    Orthogonal,
+    /// Diamonds, `x` running right and down, `y` right and up.
+    Isometric,
+    /// Diamonds in rows and columns, every other one pushed over by half a cell.
+    Staggered(Stagger),
+    /// Hexagons in rows and columns, every other one pushed over by half a cell.
+    /// `side` is the length of the flat sides along the stagger axis, so regular
+    /// hexagons are `cell.y / 2` for `StaggerAxis::Y` and `cell.x / 2` for `X`.
+    Hexagonal
+    {
+        stagger: Stagger, side: f32
+    },
+}
+
+/// Cell coordinates placed in the world, with the helpers to move around on them.
+///
+/// Cells are the rows and columns a map stores, `(0, 0)` being at the bottom left.
+/// Hexagonal grids work with `Hex`es underneath and staggered ones with diamond
+/// `Cell`s, the conversions are on `Hex` and `Stagger`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grid
+{
+    pub layout: GridLayout,
+    /// Width and height of the box around a cell.
+    pub cell:   Vec2,
+    /// Where the box around cell `(0, 0)` has its bottom left corner.
+    pub origin: Vec2,
+}
+
+impl Grid
+{
+    pub fn new(layout: GridLayout, cell: Vec2) -> Self
+    {
+        Self {
+            layout,
+            cell,
+            origin: math::vec2(0.0, 0.0),
+        }
+    }
+
+    /// A grid of regular hexagons with corners `radius` away from their centers.
+    pub fn hexagons(stagger: Stagger, radius: f32) -> Self
+    {
+        let short = 3f32.sqrt() * radius;
+        let cell = match stagger.axis
+        {
+            StaggerAxis::Y => math::vec2(short, radius * 2.0),
+            StaggerAxis::X => math::vec2(radius * 2.0, short),
+        };
+        Self::new(
+            GridLayout::Hexagonal {
+                stagger,
+                side: radius,
+            },
+            cell,
+        )
+    }
+
+    /// How far the centers of staggered diamonds are from the diamond grid's ones.
+    fn stagger_offset(&self, stagger: Stagger) -> Vec2
+    {
+        match (stagger.index, stagger.axis)
+        {
+            (StaggerIndex::Odd, _) => math::vec2(0.0, 0.0),
+            (StaggerIndex::Even, StaggerAxis::Y) => math::vec2(self.cell.x / 2.0, 0.0),
+            (StaggerIndex::Even, StaggerAxis::X) => math::vec2(0.0, self.cell.y / 2.0),
+        }
+    }
+
+    /// The center of `cell` in the world.
+    pub fn center(&self, cell: Cell) -> Vec2
+    {
+        let half = self.cell / 2.0;
+        let diamond = |c: Cell| {
+            let steps = math::vec2((c.x + c.y + 1) as f32, (c.y - c.x + 1) as f32);
+            self.origin + steps.component_mul(&half)
+        };
+
+        match self.layout
+        {
+            GridLayout::Orthogonal =>
+            {
+                let corner = math::vec2(cell.x as f32, cell.y as f32).component_mul(&self.cell);
+                self.origin + corner + half
+            },
+            GridLayout::Isometric => diamond(cell),
+            GridLayout::Staggered(stagger) =>
+            {
+                diamond(stagger.to_diamond(cell)) + self.stagger_offset(stagger)
+            },
+            GridLayout::Hexagonal {
+                stagger,
+                side,
+            } =>
+            {
+                let hex = Hex::from_offset(cell, stagger);
+                let (q, r) = (hex.q as f32, hex.r as f32);
+                let center = match stagger.axis
+                {
+                    StaggerAxis::Y =>
+                    {
+                        let row = (self.cell.y + side) / 2.0;
+                        math::vec2(self.cell.x * (q + r / 2.0), row * r)
+                    },
+                    StaggerAxis::X =>
+                    {
+                        let column = (self.cell.x + side) / 2.0;
+                        math::vec2(column * q, self.cell.y * (r + q / 2.0))
+                    },
+                };
+                self.origin + center + half + self.stagger_offset(stagger)
+            },
+        }
+    }
+
+    /// The cell the world point `p` is in. For hexagons this is exact if they are regular.
+    pub fn cell_at(&self, p: Vec2) -> Cell
+    {
+        let half = self.cell / 2.0;
+        let diamond = |p: Vec2| {
+            let local = (p - self.origin - half).component_div(&half);
+            let (x, y) = ((local.x - local.y) / 2.0, (local.x + local.y) / 2.0);
+            Cell::new(x.round() as i32, y.round() as i32)
+        };
+
+        match self.layout
+        {
+            GridLayout::Orthogonal =>
+            {
+                let local = (p - self.origin).component_div(&self.cell);
+                Cell::new(local.x.floor() as i32, local.y.floor() as i32)
+            },
+            GridLayout::Isometric => diamond(p),
+            GridLayout::Staggered(stagger) =>
+            {
+                stagger.from_diamond(diamond(p - self.stagger_offset(stagger)))
+            },
+            GridLayout::Hexagonal {
+                stagger,
+                side,
+            } =>
+            {
+                let local = p - self.origin - half - self.stagger_offset(stagger);
+                let hex = match stagger.axis
+                {
+                    StaggerAxis::Y =>
+                    {
+                        let r = local.y / ((self.cell.y + side) / 2.0);
+                        Hex::round(local.x / self.cell.x - r / 2.0, r)
+                    },
+                    StaggerAxis::X =>
+                    {
+                        let q = local.x / ((self.cell.x + side) / 2.0);
+                        Hex::round(q, local.y / self.cell.y - q / 2.0)
+                    },
+                };
+                hex.to_offset(stagger)
+            },
+        }
+    }
+
+    /// The outline of `cell` in the world, counterclockwise.
+    pub fn corners(&self, cell: Cell) -> Vec<Vec2>
+    {
+        let center = self.center(cell);
+        let (w, h) = (self.cell.x / 2.0, self.cell.y / 2.0);
+        let points: &[(f32, f32)] = match self.layout
+        {
+            GridLayout::Orthogonal => &[(-w, -h), (w, -h), (w, h), (-w, h)],
+            GridLayout::Isometric | GridLayout::Staggered(_) =>
+            {
+                &[(w, 0.0), (0.0, h), (-w, 0.0), (0.0, -h)]
+            },
+            GridLayout::Hexagonal {
+                stagger,
+                side,
+            } =>
+            {
+                let s = side / 2.0;
+                match stagger.axis
+                {
+                    StaggerAxis::Y => &[(w, s), (0.0, h), (-w, s), (-w, -s), (0.0, -h), (w, -s)],
+                    StaggerAxis::X => &[(w, 0.0), (s, h), (-s, h), (-w, 0.0), (-s, -h), (s, -h)],
+                }
+            },
+        };
+        points.iter().map(|(x, y)| center + math::vec2(*x, *y)).collect()
+    }
+
+    /// The cells sharing a side with `cell`, counterclockwise.
+    pub fn neighbors(&self, cell: Cell) -> Vec<Cell>
+    {
+        match self.layout
+        {
+            GridLayout::Orthogonal | GridLayout::Isometric => cell.neighbors().to_vec(),
+            GridLayout::Staggered(stagger) =>
+            {
+                let neighbors = stagger.to_diamond(cell).neighbors();
+                neighbors.map(|c| stagger.from_diamond(c)).to_vec()
+            },
+            GridLayout::Hexagonal {
+                stagger, ..
+            } =>
+            {
+                let neighbors = Hex::from_offset(cell, stagger).neighbors();
+                neighbors.map(|h| h.to_offset(stagger)).to_vec()
+            },
+        }
+    }
+
+    /// Steps from `a` to `b` going from neighbor to neighbor.
+    pub fn distance(&self, a: Cell, b: Cell) -> u32
+    {
+        match self.layout
+        {
+            GridLayout::Orthogonal | GridLayout::Isometric => a.distance(b),
+            GridLayout::Staggered(stagger) => stagger.to_diamond(a).distance(stagger.to_diamond(b)),
+            GridLayout::Hexagonal {
+                stagger, ..
+            } => Hex::from_offset(a, stagger).distance(Hex::from_offset(b, stagger)),
+        }
+    }
+
+    /// The cells on a straight line from `a` to `b`, including both.
+    pub fn line(&self, a: Cell, b: Cell) -> Vec<Cell>
+    {
+        match self.layout
+        {
+            GridLayout::Orthogonal | GridLayout::Isometric => a.line_to(b).collect(),
+            GridLayout::Staggered(stagger) =>
+            {
+                let line = stagger.to_diamond(a).line_to(stagger.to_diamond(b));
+                line.map(|c| stagger.from_diamond(c)).collect()
+            },
+            GridLayout::Hexagonal {
+                stagger, ..
+            } =>
+            {
+                let line = Hex::from_offset(a, stagger).line_to(Hex::from_offset(b, stagger));
+                line.map(|h| h.to_offset(stagger)).collect()
+            },
+        }
+    }
+
+    /// Every cell at most `radius` steps from `center`, as `distance` counts them.
+    pub fn range(&self, center: Cell, radius: u32) -> Vec<Cell>
+    {
+        match self.layout
+        {
+            GridLayout::Orthogonal | GridLayout::Isometric => center.range(radius).collect(),
+            GridLayout::Staggered(stagger) =>
+            {
+                let range = stagger.to_diamond(center).range(radius);
+                range.map(|c| stagger.from_diamond(c)).collect()
+            },
+            GridLayout::Hexagonal {
+                stagger, ..
+            } =>
+            {
+                let range = Hex::from_offset(center, stagger).range(radius);
+                range.map(|h| h.to_offset(stagger)).collect()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn staggers() -> Vec<Stagger>
+    {
+        let axes = [StaggerAxis::X, StaggerAxis::Y];
+        let indices = [StaggerIndex::Odd, StaggerIndex::Even];
+        axes.iter().flat_map(|a| indices.iter().map(|i| Stagger::new(*a, *i))).collect()
+    }
+
+    fn grids() -> Vec<Grid>
+    {
+        let mut grids = vec![
+            Grid::new(GridLayout::Orthogonal, math::vec2(16.0, 12.0)),
+            Grid::new(GridLayout::Isometric, math::vec2(64.0, 32.0)),
+        ];
+        for stagger in staggers()
+        {
+            grids.push(Grid::new(GridLayout::Staggered(stagger), math::vec2(64.0, 32.0)));
+            grids.push(Grid::hexagons(stagger, 10.0));
+
+            let layout = GridLayout::Hexagonal {
+                stagger,
+                side: 14.0,
+            };
+            let mut grid = Grid::new(layout, math::vec2(28.0, 32.0));
+            grid.origin = math::vec2(5.0, -3.0);
+            grids.push(grid);
+        }
+        grids
+    }
+
+    fn cells() -> impl Iterator<Item = Cell>
+    {
+        (-6..6).flat_map(|y| (-6..6).map(move |x| Cell::new(x, y)))
+    }
+
+    #[test]
+    fn diamond_round_trip()
+    {
+        for stagger in staggers()
+        {
+            for cell in cells()
+            {
+                assert_eq!(stagger.from_diamond(stagger.to_diamond(cell)), cell, "{:?}", stagger);
+            }
+        }
+    }
+
+    #[test]
+    fn cell_at_center_and_inside_corners()
+    {
+        for grid in grids()
+        {
+            for cell in cells()
+            {
+                let center = grid.center(cell);
+                assert_eq!(grid.cell_at(center), cell, "{:?}", grid.layout);
+                for corner in grid.corners(cell)
+                {
+                    let inside = center + (corner - center) * 0.9;
+                    assert_eq!(grid.cell_at(inside), cell, "{:?}", grid.layout);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighbors_are_one_step_and_close()
+    {
+        for grid in grids()
+        {
+            let sides = if matches!(grid.layout, GridLayout::Hexagonal { .. }) { 6 } else { 4 };
+            for cell in cells()
+            {
+                let neighbors = grid.neighbors(cell);
+                assert_eq!(neighbors.len(), sides);
+                for neighbor in neighbors
+                {
+                    assert_eq!(grid.distance(cell, neighbor), 1);
+                    let apart = (grid.center(neighbor) - grid.center(cell)).norm();
+                    assert!(apart < grid.cell.x.max(grid.cell.y) * 1.01, "{:?}", grid.layout);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lines_and_ranges()
+    {
+        for grid in grids()
+        {
+            for cell in cells()
+            {
+                let end = Cell::new(3 - cell.y, cell.x + 2);
+                let line = grid.line(cell, end);
+                assert_eq!((line[0], *line.last().unwrap()), (cell, end), "{:?}", grid.layout);
+
+                let mut range = grid.range(cell, 3);
+                assert!(range.iter().all(|c| grid.distance(cell, *c) <= 3));
+                let count = range.len();
+                range.sort_by_key(|c| (c.x, c.y));
+                range.dedup();
+                assert_eq!(range.len(), count);
+            }
+        }
+    }
+}
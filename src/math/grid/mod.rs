@@ -0,0 +1,11 @@
+//! Square, isometric and hexagonal grid coordinates. Plain math, nothing here needs a
+//! window or touches OpenGL.
+
+mod cell;
+pub use cell::Cell;
+
+mod hex;
+pub use hex::Hex;
+
+mod layout;
+pub use layout::{Grid, GridLayout, Stagger, StaggerAxis, StaggerIndex};
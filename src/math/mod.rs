@@ -1 +1,3 @@
 pub use glm::*;
+
+pub mod grid;
@@ -1,16 +1,35 @@
-const SHADERS: [&str; 4] = [
-    include_str!("../res/shaders/shape.color.shader"),
-    include_str!("../res/shaders/shape.texture.shader"),
-    include_str!("../res/shaders/text.shader"),
-    include_str!("../res/shaders/circle.shader"),
+const SHADERS: [&str; 11] = [
+    include_str!("../res/shaders/batch.shader"),
+    include_str!("../res/shaders/batch.circle.shader"),
+    include_str!("../res/shaders/post/copy.shader"),
+    include_str!("../res/shaders/post/blur.shader"),
+    include_str!("../res/shaders/post/bright.shader"),
+    include_str!("../res/shaders/post/bloom.shader"),
+    include_str!("../res/shaders/post/crt.shader"),
+    include_str!("../res/shaders/post/vignette.shader"),
+    include_str!("../res/shaders/post/lut.shader"),
+    include_str!("../res/shaders/post/pixelate.shader"),
+    include_str!("../res/shaders/light.shader"),
 ];
 
+/// Files `#include`-able from any shader by name.
+const INCLUDES: [(&str, &str); 1] =
+    [("batch.vertex.glsl", include_str!("../res/shaders/batch.vertex.glsl"))];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ShaderProgram
 {
-    Basic,
-    Texture,
-    Text,
+    Batch,
     Circle,
+    Copy,
+    Blur,
+    Bright,
+    Bloom,
+    Crt,
+    Vignette,
+    Lut,
+    Pixelate,
+    Light,
 }
 
 pub fn get_shader(program: ShaderProgram) -> &'static str
@@ -18,9 +37,21 @@ pub fn get_shader(program: ShaderProgram) -> &'static str
     use ShaderProgram as SP;
     match program
     {
-        SP::Basic => SHADERS[0],
-        SP::Texture => SHADERS[1],
-        SP::Text => SHADERS[2],
-        SP::Circle => SHADERS[3],
+        SP::Batch => SHADERS[0],
+        SP::Circle => SHADERS[1],
+        SP::Copy => SHADERS[2],
+        SP::Blur => SHADERS[3],
+        SP::Bright => SHADERS[4],
+        SP::Bloom => SHADERS[5],
+        SP::Crt => SHADERS[6],
+        SP::Vignette => SHADERS[7],
+        SP::Lut => SHADERS[8],
+        SP::Pixelate => SHADERS[9],
+        SP::Light => SHADERS[10],
     }
 }
+
+pub fn get_include(name: &str) -> Option<&'static str>
+{
+    INCLUDES.iter().find(|(n, _)| *n == name).map(|(_, src)| *src)
+}
@@ -1,2 +1,5 @@
 mod window;
 pub use window::Window;
+
+mod scaling;
+pub use scaling::{Layout, Scaling};
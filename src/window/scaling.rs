@@ -0,0 +1,92 @@
+use crate::math::{self, Vec2};
+
+/// How the drawing area reacts when the window is resized.
+///
+/// The fixed size policies draw in a virtual resolution of `width` x `height`
+/// logical units no matter how big the window is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scaling
+{
+    /// One logical unit per window coordinate, a bigger window shows more of the world.
+    Expand,
+    /// The virtual resolution is stretched over the whole window, ignoring aspect ratio.
+    Stretch
+    {
+        width: f32, height: f32
+    },
+    /// The virtual resolution is scaled as large as fits while keeping its aspect ratio,
+    /// with bars in the clear color filling the rest.
+    Letterbox
+    {
+        width: f32, height: f32
+    },
+    /// Like `Letterbox`, but only scales by whole numbers so pixel art stays crisp.
+    PixelPerfect
+    {
+        width: u32, height: u32
+    },
+}
+
+/// Where to draw inside the framebuffer and how many logical units that area covers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout
+{
+    /// `x, y, width, height` in framebuffer pixels.
+    pub viewport: [i32; 4],
+    pub size:     Vec2,
+}
+
+impl Scaling
+{
+    /// Fits the policy into a framebuffer of `fb` pixels belonging to a window
+    /// that is `window` screen coordinates large. The two differ on high DPI displays.
+    pub fn layout(&self, fb: (i32, i32), window: (i32, i32)) -> Layout
+    {
+        let (fw, fh) = (fb.0.max(1) as f32, fb.1.max(1) as f32);
+        let full = [0, 0, fb.0, fb.1];
+
+        let centered = |w: f32, h: f32| {
+            let w = w.round() as i32;
+            let h = h.round() as i32;
+            [(fb.0 - w) / 2, (fb.1 - h) / 2, w, h]
+        };
+
+        match *self
+        {
+            Scaling::Expand => Layout {
+                viewport: full,
+                size: math::vec2(window.0 as f32, window.1 as f32),
+            },
+            Scaling::Stretch {
+                width,
+                height,
+            } => Layout {
+                viewport: full,
+                size: math::vec2(width, height),
+            },
+            Scaling::Letterbox {
+                width,
+                height,
+            } =>
+            {
+                let scale = (fw / width).min(fh / height);
+                Layout {
+                    viewport: centered(width * scale, height * scale),
+                    size: math::vec2(width, height),
+                }
+            },
+            Scaling::PixelPerfect {
+                width,
+                height,
+            } =>
+            {
+                let (width, height) = (width as f32, height as f32);
+                let scale = (fw / width).min(fh / height).floor().max(1.0);
+                Layout {
+                    viewport: centered(width * scale, height * scale),
+                    size: math::vec2(width, height),
+                }
+            },
+        }
+    }
+}
@@ -1,64 +1,124 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell, RefMut};
 
 use crate::{
-    core::renderer::Renderer,
+    core::{
+        debug,
+        renderer::{Renderer, Stats},
+    },
     error::Error,
     graphics::{
+        blend::BlendMode,
+        camera::Camera2D,
         drawable::Drawable,
+        lighting::Lighting,
+        post::PostChain,
+        render_target::RenderTarget,
         text::{freetype::Freetype, CharSet, Handle},
     },
+    math,
+    window::{Layout, Scaling},
 };
 
+/// Post-processing of everything drawn to the window.
+struct Post
+{
+    chain: PostChain,
+    /// What is drawn to the window ends up here first.
+    scene: RenderTarget,
+}
+
 type Recv = std::sync::mpsc::Receiver<(f64, glfw::WindowEvent)>;
 pub struct Window
 {
-    glfw:     RefCell<glfw::Glfw>,
-    window:   RefCell<glfw::Window>,
-    events:   Recv,
-    renderer: Renderer,
-    proj:     glm::Mat4,
-    freetype: Option<Freetype>,
+    // Fields drop in order, GL objects have to go before the context does.
+    post:      RefCell<Option<Post>>,
+    renderer:  RefCell<Renderer>,
+    glfw:      RefCell<glfw::Glfw>,
+    window:    RefCell<glfw::Window>,
+    events:    Recv,
+    freetype:  Option<Freetype>,
+    scaling:   Cell<Scaling>,
+    fb_size:   Cell<(i32, i32)>,
+    layout:    Cell<Layout>,
+    /// Whether the post chain already ran this frame, `read_pixels` runs it early.
+    presented: Cell<bool>,
 }
 
 impl Window
 {
     pub fn new<S: AsRef<str>>(name: S, width: usize, height: usize) -> Result<Self, Error>
+    {
+        Self::create(name.as_ref(), width, height, &[glfw::ContextCreationApi::Native], true)
+    }
+
+    /// Creates a window that is never shown, for rendering on machines without a display
+    /// or GPU, like CI runners using Mesa's llvmpipe.
+    ///
+    /// An EGL context is tried first, then OSMesa, then the platform default.
+    /// Note that GLFW still needs a display connection to initialize unless it was
+    /// built for OSMesa, so run under `xvfb-run` otherwise.
+    /// Use `read_pixels` to get at what was drawn.
+    pub fn headless(width: usize, height: usize) -> Result<Self, Error>
+    {
+        use glfw::ContextCreationApi as Api;
+        Self::create("tori", width, height, &[Api::Egl, Api::OsMesa, Api::Native], false)
+    }
+
+    fn create(
+        name: &str,
+        width: usize,
+        height: usize,
+        apis: &[glfw::ContextCreationApi],
+        visible: bool,
+    ) -> Result<Self, Error>
     {
         let mut glfw = glfw::init(glfw::LOG_ERRORS)?;
 
-        let (mut window, events) = glfw
-            .create_window(width as _, height as _, name.as_ref(), glfw::WindowMode::Windowed)
-            .ok_or(Error::Glfw)?;
+        let (mut window, events) = apis
+            .iter()
+            .find_map(|api| {
+                glfw.default_window_hints();
+                glfw.window_hint(glfw::WindowHint::ContextVersionMajor(3));
+                glfw.window_hint(glfw::WindowHint::ContextVersionMinor(3));
+                glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
+                glfw.window_hint(glfw::WindowHint::OpenGlDebugContext(cfg!(debug_assertions)));
+                glfw.window_hint(glfw::WindowHint::ContextCreationApi(*api));
+                glfw.window_hint(glfw::WindowHint::Visible(visible));
+                glfw.window_hint(glfw::WindowHint::StencilBits(Some(8)));
 
+                glfw.create_window(width as _, height as _, name, glfw::WindowMode::Windowed)
+            })
+            .ok_or(Error::Glfw)?;
 
         gl::load_with(|s| window.get_proc_address(s));
 
         glfw.make_context_current(Some(&window));
-        glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
+        glfw.set_swap_interval(glfw::SwapInterval::Sync(visible as _));
         window.set_all_polling(true);
 
-        glfw.window_hint(glfw::WindowHint::ContextVersionMajor(3));
-        glfw.window_hint(glfw::WindowHint::ContextVersionMinor(3));
-        glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
+        use log::LevelFilter as LF;
+        debug::install(if cfg!(debug_assertions) { LF::Info } else { LF::Warn });
 
-        unsafe
-        {
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-            gl::Enable(gl::BLEND);
-        }
-
-        let renderer = Renderer::new();
+        let renderer = Renderer::new(width as _, height as _)?;
 
-        let proj = glm::ortho(0., width as _, 0., height as _, -1., 1.);
-
-        Ok(Self {
+        let window = Self {
             glfw: RefCell::new(glfw),
             window: RefCell::new(window),
             events,
-            renderer,
-            proj,
+            renderer: RefCell::new(renderer),
             freetype: None,
-        })
+            scaling: Cell::new(Scaling::Expand),
+            fb_size: Cell::new((width as _, height as _)),
+            layout: Cell::new(Layout {
+                viewport: [0, 0, width as _, height as _],
+                size: math::vec2(width as _, height as _),
+            }),
+            post: RefCell::new(None),
+            presented: Cell::new(false),
+        };
+        window.relayout();
+
+        Ok(window)
     }
 
     pub fn load_font<P>(&mut self, p: P, set: CharSet) -> Result<Handle, Error>
@@ -83,9 +143,158 @@ impl Window
     pub fn poll_events(&self) -> impl Iterator<Item = glfw::WindowEvent> + '_
     {
         self.glfw.borrow_mut().poll_events();
+
+        #[cfg(feature = "hot-reload")]
+        crate::core::hot_reload::poll();
+
+        // Checked here rather than through `FramebufferSize` events,
+        // since callers are free to ignore the returned events.
+        if self.window.borrow().get_framebuffer_size() != self.fb_size.get()
+        {
+            self.relayout();
+        }
+
         glfw::flush_messages(&self.events).map(|e| e.1)
     }
 
+    /// Only driver debug messages at `filter` or more severe are generated.
+    /// They are logged under the `tori::gl` target.
+    pub fn set_gl_debug_filter(&self, filter: log::LevelFilter)
+    {
+        debug::set_filter(filter);
+    }
+
+    pub fn set_scaling(&self, scaling: Scaling)
+    {
+        self.scaling.set(scaling);
+        self.relayout();
+    }
+
+    pub fn scaling(&self) -> Scaling
+    {
+        self.scaling.get()
+    }
+
+    /// Size of the drawing area in the units drawables are positioned in.
+    /// This is the virtual resolution for the fixed size scaling policies.
+    pub fn logical_size(&self) -> math::Vec2
+    {
+        self.layout.get().size
+    }
+
+    fn relayout(&self)
+    {
+        let window = self.window.borrow();
+        let fb = window.get_framebuffer_size();
+        let layout = self.scaling.get().layout(fb, window.get_size());
+
+        self.fb_size.set(fb);
+        self.layout.set(layout);
+
+        let mut renderer = self.renderer.borrow_mut();
+        let mut post = self.post.borrow_mut();
+        let Some(post) = post.as_mut()
+        else
+        {
+            renderer.set_screen_fbo(0);
+            renderer.set_screen(layout.viewport, layout.size);
+            return;
+        };
+
+        // The scene is drawn at the size it takes up on screen.
+        let [_, _, w, h] = layout.viewport;
+        let (w, h) = (w.max(1) as u32, h.max(1) as u32);
+        if post.scene.width() != w || post.scene.height() != h
+        {
+            renderer.flush();
+            match RenderTarget::new(w, h)
+            {
+                Ok(scene) => post.scene = scene,
+                Err(e) => log::error!(target: "tori::post", "resizing the scene failed: {}", e),
+            }
+        }
+        renderer.set_screen_fbo(post.scene.framebuffer().id());
+        renderer.set_screen([0, 0, post.scene.width() as _, post.scene.height() as _], layout.size);
+    }
+
+    /// Runs `chain` over everything drawn to the window before it is shown,
+    /// `None` turns post-processing off again.
+    pub fn set_post_chain(&self, chain: Option<PostChain>) -> Result<(), Error>
+    {
+        self.renderer.borrow_mut().flush();
+        let post = match chain
+        {
+            Some(chain) =>
+            {
+                let [_, _, w, h] = self.layout.get().viewport;
+                Some(Post {
+                    chain,
+                    scene: RenderTarget::new(w.max(1) as _, h.max(1) as _)?,
+                })
+            },
+            None => None,
+        };
+
+        *self.post.borrow_mut() = post;
+        self.relayout();
+        Ok(())
+    }
+
+    /// The chain set with `set_post_chain`, e.g. to change its effects.
+    pub fn post_chain(&self) -> Option<RefMut<'_, PostChain>>
+    {
+        RefMut::filter_map(self.post.borrow_mut(), |p| p.as_mut().map(|p| &mut p.chain)).ok()
+    }
+
+    /// Runs `chain` over the contents of `source` and draws the result into `target`,
+    /// or the window for `None`. Together with `draw_to` this post-processes what a
+    /// single camera sees, leaving e.g. the UI alone.
+    pub fn post_process(
+        &self,
+        chain: &mut PostChain,
+        source: &RenderTarget,
+        target: Option<&RenderTarget>,
+    ) -> Result<(), Error>
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(target.map(|t| t.framebuffer()));
+        let (fbo, viewport) = match target
+        {
+            Some(t) => (t.framebuffer().id(), [0, 0, t.width() as _, t.height() as _]),
+            None => renderer.screen(),
+        };
+        chain.run(&mut renderer, source.texture(), fbo, viewport, BlendMode::Premultiplied)
+    }
+
+    /// Draws the post-processed scene to the actual window, once per frame.
+    fn present_post(&self)
+    {
+        let mut post = self.post.borrow_mut();
+        let Some(post) = post.as_mut()
+        else
+        {
+            return;
+        };
+        if self.presented.replace(true)
+        {
+            return;
+        }
+
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.flush_layers();
+        renderer.flush();
+
+        // Fills the bars around a letterboxed picture.
+        renderer.clear_fbo(0);
+        let viewport = self.layout.get().viewport;
+        let scene = post.scene.texture();
+        if let Err(e) = post.chain.run(&mut renderer, scene, 0, viewport, BlendMode::None)
+        {
+            log::error!(target: "tori::post", "{}", e);
+        }
+    }
+
     pub fn is_key_pressed(&self, key: crate::event::Key) -> bool
     {
         use glfw::Action as A;
@@ -106,12 +315,26 @@ impl Window
         }
     }
 
+    /// The cursor position in logical units, origin at the bottom left.
     pub fn get_mouse_pos(&self) -> crate::math::Vec2
     {
         let wind = self.window.borrow();
         let (x, y) = wind.get_cursor_pos();
-        let (_, y_max) = wind.get_size();
-        crate::math::vec2(x as f32, y_max as f32 - y as f32)
+        let (w, h) = wind.get_size();
+        let (fw, fh) = wind.get_framebuffer_size();
+
+        // Window coordinates -> framebuffer pixels -> position in the viewport.
+        let px = x as f32 * fw as f32 / w.max(1) as f32;
+        let py = (h as f32 - y as f32) * fh as f32 / h.max(1) as f32;
+
+        let Layout {
+            viewport: [vx, vy, vw, vh],
+            size,
+        } = self.layout.get();
+        crate::math::vec2(
+            (px - vx as f32) / vw.max(1) as f32 * size.x,
+            (py - vy as f32) / vh.max(1) as f32 * size.y,
+        )
     }
 
     pub fn is_open(&self) -> bool
@@ -126,23 +349,272 @@ impl Window
 
     pub fn clear(&self)
     {
-        self.renderer.clear();
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.clear();
     }
 
     pub fn swap_buffers(&self)
     {
         use glfw::Context;
+        self.present_post();
+        self.presented.set(false);
+        self.renderer.borrow_mut().end_frame();
         self.window.borrow_mut().swap_buffers();
     }
 
     pub fn draw<D: Drawable>(&self, d: D)
     {
-        d.draw(self.proj);
+        self.draw_dyn(&d);
     }
 
     pub fn draw_dyn(&self, d: &dyn Drawable)
     {
-        d.draw(self.proj);
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.set_blend_mode(BlendMode::Alpha);
+        d.draw(&mut renderer);
+    }
+
+    /// Queues `d` on `layer` instead of drawing it right away.
+    ///
+    /// Queued drawables are drawn on `flush_layers` or `swap_buffers`, on top of
+    /// everything drawn directly. Lower layers come first, within a layer lower `z`
+    /// comes first, and ties keep the order of the calls.
+    ///
+    /// Clip rectangles and masks pushed onto the window apply as they were when this
+    /// was called.
+    pub fn draw_layered<D: Drawable>(&self, layer: i32, z: f32, d: D)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        let start = renderer.begin_layered(layer, z);
+        renderer.set_blend_mode(BlendMode::Alpha);
+        d.draw(&mut renderer);
+        renderer.end_layered(start);
+    }
+
+    /// Views `layer` through `camera`, or in window units for `None`, which is the default
+    /// and what UI layers want.
+    ///
+    /// The camera is copied, so call this again after moving it.
+    pub fn set_layer_camera(&self, layer: i32, camera: Option<&Camera2D>)
+    {
+        let proj = camera.map(|c| c.view_projection());
+        self.renderer.borrow_mut().set_layer_projection(layer, proj);
+    }
+
+    /// Makes drawables on `layer` with the same z sort by their bottom edge,
+    /// higher ones first, as top-down games want.
+    pub fn set_layer_y_sort(&self, layer: i32, y_sort: bool)
+    {
+        self.renderer.borrow_mut().set_layer_y_sort(layer, y_sort);
+    }
+
+    /// Draws everything queued with `draw_layered` so far.
+    pub fn flush_layers(&self)
+    {
+        self.renderer.borrow_mut().flush_layers();
+    }
+
+    /// Views everything drawn to the window from now on through `camera`.
+    ///
+    /// The camera is copied, so call this again after moving it.
+    pub fn set_camera(&self, camera: &Camera2D)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.set_projection(camera.view_projection());
+    }
+
+    /// Goes back to drawing in window pixels, e.g. for UI on top of a scrolled world.
+    pub fn reset_camera(&self)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.reset_projection();
+    }
+
+    /// Draws `d` through `camera` without changing the window's camera.
+    pub fn draw_with_camera<D: Drawable>(&self, camera: &Camera2D, d: D)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+
+        let proj = renderer.projection();
+        renderer.set_projection(camera.view_projection());
+        renderer.set_blend_mode(BlendMode::Alpha);
+        d.draw(&mut renderer);
+        renderer.set_projection(proj);
+    }
+
+    /// Draws the normal maps of the sprites in `d` for `lighting` to shade them with,
+    /// through `camera` or the window's projection. Nothing else of `d` is drawn.
+    /// Needs to happen every frame before `draw_lighting`, see `Sprite::set_normal_map`.
+    pub fn draw_normals<D: Drawable>(
+        &self,
+        lighting: &mut Lighting,
+        camera: Option<&Camera2D>,
+        d: D,
+    ) -> Result<(), Error>
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        let proj = camera.map_or_else(|| renderer.projection(), Camera2D::view_projection);
+
+        lighting.begin_normals(&mut renderer, proj)?;
+        d.draw(&mut renderer);
+        lighting.end_normals(&mut renderer);
+        Ok(())
+    }
+
+    /// Multiplies the light of `lighting` over everything drawn to the window so far,
+    /// including what is queued with `draw_layered`. Lights and occluders are placed
+    /// through `camera`, or the window's projection for `None`.
+    pub fn draw_lighting(
+        &self,
+        lighting: &mut Lighting,
+        camera: Option<&Camera2D>,
+    ) -> Result<(), Error>
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.flush_layers();
+        let proj = camera.map_or_else(|| renderer.projection(), Camera2D::view_projection);
+
+        lighting.render(&mut renderer, proj)
+    }
+
+    /// Only draws inside `rect` until the matching `pop_clip`, as `(x, y, width, height)`
+    /// in logical window units. Cameras do not move the clip rectangle.
+    /// Nested clips only draw where all of them overlap.
+    pub fn push_clip(&self, rect: math::Vec4)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.push_clip(rect);
+    }
+
+    pub fn pop_clip(&self)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.pop_clip();
+    }
+
+    /// Like `push_clip` for drawing into `target`, with `rect` in its pixels.
+    /// Every target has its own clip stack.
+    pub fn push_clip_to(&self, target: &RenderTarget, rect: math::Vec4)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(Some(target.framebuffer()));
+        renderer.push_clip(rect);
+    }
+
+    pub fn pop_clip_to(&self, target: &RenderTarget)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(Some(target.framebuffer()));
+        renderer.pop_clip();
+    }
+
+    /// Uses the shape of `mask` to cut out everything drawn until the matching `pop_mask`.
+    ///
+    /// The mask itself is not visible. It is drawn with the current camera, and fully
+    /// transparent pixels of shapes and sprites are left out, though materials get no such
+    /// treatment. Nested masks only draw where all of them overlap. For `draw_layered`
+    /// the mask is drawn again when the layers are, which leaves out tile maps in it.
+    pub fn push_mask<D: Drawable>(&self, mask: D)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.begin_mask();
+        renderer.set_blend_mode(BlendMode::Alpha);
+        mask.draw(&mut renderer);
+        renderer.end_mask();
+    }
+
+    pub fn pop_mask(&self)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(None);
+        renderer.pop_mask();
+    }
+
+    /// Like `push_mask` for drawing into `target`. Every target has its own masks.
+    pub fn push_mask_to<D: Drawable>(&self, target: &RenderTarget, mask: D)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(Some(target.framebuffer()));
+        renderer.begin_mask();
+        renderer.set_blend_mode(BlendMode::Alpha);
+        mask.draw(&mut renderer);
+        renderer.end_mask();
+    }
+
+    pub fn pop_mask_to(&self, target: &RenderTarget)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(Some(target.framebuffer()));
+        renderer.pop_mask();
+    }
+
+    /// Draws `d` into `target` instead of the window.
+    pub fn draw_to<D: Drawable>(&self, target: &RenderTarget, d: D)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(Some(target.framebuffer()));
+        renderer.set_blend_mode(BlendMode::Alpha);
+        d.draw(&mut renderer);
+    }
+
+    pub fn clear_target(&self, target: &RenderTarget, color: crate::math::Vec4)
+    {
+        let mut renderer = self.renderer.borrow_mut();
+        renderer.set_target(Some(target.framebuffer()));
+        renderer.clear_with(color);
+    }
+
+    /// Returns the current contents of the back buffer, top row first.
+    ///
+    /// Call this before `swap_buffers`, afterwards the back buffer is undefined.
+    /// With post-processing the frame is finished here, so anything drawn after
+    /// this call only shows up in the next frame.
+    pub fn read_pixels(&self) -> image::RgbaImage
+    {
+        self.present_post();
+        self.renderer.borrow_mut().set_target(None);
+        self.renderer.borrow_mut().flush_layers();
+        self.renderer.borrow_mut().flush();
+
+        let (width, height) = self.window.borrow().get_framebuffer_size();
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let (screen, _) = self.renderer.borrow().screen();
+        unsafe
+        {
+            // With post-processing the screen is the scene, not what is shown.
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width,
+                height,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut std::ffi::c_void,
+            );
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, screen);
+        }
+
+        let mut image = image::RgbaImage::from_raw(width as _, height as _, pixels).unwrap();
+        image::imageops::flip_vertical_in_place(&mut image);
+        image
+    }
+
+    /// Draw calls and vertices submitted during the last frame.
+    pub fn stats(&self) -> Stats
+    {
+        self.renderer.borrow().stats()
     }
 }
 